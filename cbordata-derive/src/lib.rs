@@ -139,7 +139,7 @@ fn from_cbor_to_struct(
     let preamble = quote! {
         // validate the cbor msg for this type.
         if items.len() == 0 {
-            #croot::err_at!(FailConvert, msg: "empty msg for {}", #name_lit)?;
+            #croot::err_at!(TypeMismatch, msg: "empty msg for {}", #name_lit)?;
         }
         let data_id = items.remove(0);
         let type_id: #croot::Cbor = {
@@ -147,17 +147,17 @@ fn from_cbor_to_struct(
             #croot::Tag::from_identifier(id).into()
         };
         if data_id != type_id {
-            #croot::err_at!(FailConvert, msg: "bad id for {}", #name_lit)?;
+            #croot::err_at!(TypeMismatch, msg: "bad id for {}", #name_lit)?;
         }
         if #n_fields != items.len() {
-            #croot::err_at!(FailConvert, msg: "bad arity {} {}", #n_fields, items.len())?;
+            #croot::err_at!(InvalidData, msg: "bad arity {} {}", #n_fields, items.len())?;
         }
     };
 
     let token_fields = match fields {
         Fields::Unit => quote! {},
         Fields::Named(fields) => {
-            let token_fields = cbor_to_named_fields(fields, croot.clone());
+            let token_fields = cbor_to_named_fields(fields, &name_lit, croot.clone());
             quote! { { #token_fields } }
         }
         Fields::Unnamed(_) => {
@@ -295,7 +295,7 @@ fn from_cbor_to_enum(
     let preamble = quote! {
         // validate the cbor msg for this type.
         if items.len() < 2 {
-            #croot::err_at!(FailConvert, msg: "empty msg for {}", #name_lit)?;
+            #croot::err_at!(TypeMismatch, msg: "empty msg for {}", #name_lit)?;
         }
         let data_id = items.remove(0);
         let type_id: #croot::Cbor= {
@@ -303,7 +303,7 @@ fn from_cbor_to_enum(
             #croot::Tag::from_identifier(id).into()
         };
         if data_id != type_id {
-            #croot::err_at!(FailConvert, msg: "bad {}", #name_lit)?
+            #croot::err_at!(TypeMismatch, msg: "bad {}", #name_lit)?
         }
 
         let variant_name = String::from_cbor(items.remove(0))?;
@@ -319,7 +319,7 @@ fn from_cbor_to_enum(
                    #variant_lit => {
                         if #n_fields != items.len() {
                             #croot::err_at!(
-                                FailConvert, msg: "bad arity {} {}",
+                                InvalidData, msg: "bad arity {} {}",
                                 #n_fields, items.len()
                             )?;
                         }
@@ -332,7 +332,7 @@ fn from_cbor_to_enum(
                     #variant_lit => {
                         if #n_fields != items.len() {
                             #croot::err_at!(
-                                FailConvert, msg: "bad arity {} {}",
+                                InvalidData, msg: "bad arity {} {}",
                                 #n_fields, items.len()
                             )?;
                         }
@@ -344,7 +344,7 @@ fn from_cbor_to_enum(
                     #variant_lit => {
                         if items.len() > 0 {
                             #croot::err_at!(
-                                FailConvert, msg: "bad arity {}", items.len()
+                                InvalidData, msg: "bad arity {}", items.len()
                             )?;
                         }
                     }
@@ -358,16 +358,18 @@ fn from_cbor_to_enum(
     for variant in variants.iter() {
         let variant_name = &variant.ident;
         let variant_lit = &variant.ident.to_string();
+        let path_lit = format!("{}::{}", name_lit, variant_lit);
         let arm = match &variant.fields {
             Fields::Unit => quote! {
                 #variant_lit => #name::#variant_name
             },
             Fields::Named(fields) => {
-                let (_, body) = cbor_to_named_var_fields(fields, croot.clone());
+                let (_, body) =
+                    cbor_to_named_var_fields(fields, &path_lit, croot.clone());
                 quote! { #variant_lit => #name::#variant_name { #body }, }
             }
             Fields::Unnamed(fields) => {
-                let (_, body) = cbor_to_unnamed_fields(fields, croot.clone());
+                let (_, body) = cbor_to_unnamed_fields(fields, &path_lit, croot.clone());
                 quote! { #variant_lit => #name::#variant_name(#body), }
             }
         };
@@ -397,14 +399,14 @@ fn from_cbor_to_enum(
                 match variant_name.as_str() {
                     #check_variants
                     _ => #croot::err_at!(
-                        FailConvert, msg: "invalid variant_name {}", variant_name
+                        InvalidData, msg: "invalid variant_name {}", variant_name
                     )?,
                 }
 
                 let val = match variant_name.as_str() {
                     #tok_variants
                     _ => #croot::err_at!(
-                        FailConvert, msg: "invalid variant_name {}", variant_name
+                        InvalidData, msg: "invalid variant_name {}", variant_name
                     )?,
                 };
                 Ok(val)
@@ -481,20 +483,25 @@ fn unnamed_fields_to_cbor(
     (params, body)
 }
 
-fn cbor_to_named_fields(fields: &FieldsNamed, croot: TokenStream) -> TokenStream {
+fn cbor_to_named_fields(
+    fields: &FieldsNamed,
+    name_lit: &str,
+    croot: TokenStream,
+) -> TokenStream {
     let mut tokens = TokenStream::new();
     for field in fields.named.iter() {
         let is_bytes = is_bytes_ty(&field.ty);
 
         let field_name = field.ident.as_ref().unwrap();
+        let at = at_path(&field_name.to_string(), name_lit);
         let ty = &field.ty;
         let field_tokens = if is_bytes {
             quote! {
-                #field_name: items.remove(0).into_bytes()?,
+                #field_name: items.remove(0).into_bytes()#at?,
             }
         } else {
             quote! {
-                #field_name: <#ty as #croot::FromCbor>::from_cbor(items.remove(0))?,
+                #field_name: <#ty as #croot::FromCbor>::from_cbor(items.remove(0))#at?,
             }
         };
         tokens.extend(field_tokens);
@@ -504,6 +511,7 @@ fn cbor_to_named_fields(fields: &FieldsNamed, croot: TokenStream) -> TokenStream
 
 fn cbor_to_named_var_fields(
     fields: &FieldsNamed,
+    name_lit: &str,
     croot: TokenStream,
 ) -> (TokenStream, TokenStream) {
     let mut params = TokenStream::new();
@@ -514,14 +522,15 @@ fn cbor_to_named_var_fields(
         let field_name = field.ident.as_ref().unwrap();
         params.extend(quote! { #field_name, });
 
+        let at = at_path(&field_name.to_string(), name_lit);
         let ty = &field.ty;
         if is_bytes {
             body.extend(quote! {
-                #field_name: items.remove(0).into_bytes()?,
+                #field_name: items.remove(0).into_bytes()#at?,
             });
         } else {
             body.extend(quote! {
-                #field_name: <#ty as #croot::FromCbor>::from_cbor(items.remove(0))?,
+                #field_name: <#ty as #croot::FromCbor>::from_cbor(items.remove(0))#at?,
             });
         }
     }
@@ -530,28 +539,36 @@ fn cbor_to_named_var_fields(
 
 fn cbor_to_unnamed_fields(
     fields: &FieldsUnnamed,
+    name_lit: &str,
     croot: TokenStream,
 ) -> (TokenStream, TokenStream) {
     let mut params = TokenStream::new();
     let mut body = TokenStream::new();
-    for (field_name, field) in UNNAMED_FIELDS.iter().zip(fields.unnamed.iter()) {
+    let iter = UNNAMED_FIELDS.iter().zip(fields.unnamed.iter()).enumerate();
+    for (i, (field_name, field)) in iter {
         let field_name = Ident::new(field_name, field.span());
         let is_bytes = is_bytes_ty(&field.ty);
 
         params.extend(quote! { #field_name, });
 
+        let at = at_path(&i.to_string(), name_lit);
         let ty = &field.ty;
         if is_bytes {
-            body.extend(quote! { items.remove(0).into_bytes()?, });
+            body.extend(quote! { items.remove(0).into_bytes()#at?, });
         } else {
             body.extend(
-                quote! { <#ty as #croot::FromCbor>::from_cbor(items.remove(0))?, },
+                quote! { <#ty as #croot::FromCbor>::from_cbor(items.remove(0))#at?, },
             );
         }
     }
     (params, body)
 }
 
+// prefix field to the logical path of conversion error, refer Error::path.
+fn at_path(field_lit: &str, name_lit: &str) -> TokenStream {
+    quote! { .map_err(|err: Error| err.at_field(#field_lit).at_type(#name_lit)) }
+}
+
 fn let_id(name: &Ident, generics: &Generics) -> TokenStream {
     if generics.params.is_empty() {
         quote! { let id = #name::ID.into_cbor()? }
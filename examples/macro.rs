@@ -27,7 +27,7 @@ impl Parent {
 }
 
 #[derive(Cborize, Default, Clone, Debug)]
-#[allow(non_snake_case)]
+#[allow(non_snake_case, dead_code)]
 struct Floats {
     field1: f32,
     field2: f64,
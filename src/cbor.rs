@@ -4,8 +4,9 @@ use num_bigint::{BigInt, Sign};
 
 use crate::{Error, FromCbor, IntoCbor, Result};
 
+use std::collections::HashMap;
 use std::convert::{TryFrom, TryInto};
-use std::{cmp, io};
+use std::{cmp, io, ops};
 
 macro_rules! read_r {
     ($r:ident, $buf:expr) => {
@@ -15,7 +16,7 @@ macro_rules! read_r {
 
 macro_rules! write_w {
     ($w:ident, $buf:expr) => {
-        err_at!(IOError, $w.write($buf))?
+        err_at!(IOError, $w.write_all($buf))?
     };
 }
 
@@ -36,7 +37,7 @@ pub enum Cbor {
     Major5(Info, Vec<(Key, Cbor)>), // dict 0-23,24,25,26,27,31
     Major6(Info, Tag),              // tags similar to major0
     Major7(Info, SimpleValue),      // type refer SimpleValue
-    Binary(Vec<u8>),                // for lazy decoding cbor data
+    Binary(Vec<u8>),                // for lazy decoding cbor data, refer decode_lazy
 }
 
 #[cfg(any(feature = "arbitrary", test))]
@@ -144,13 +145,849 @@ impl Cbor {
     }
 }
 
+/// Options to control serialization of Cbor values, refer [Cbor::encode_with].
+#[derive(Debug, Clone, Default)]
+pub struct EncodeOptions {
+    /// Serialize values in deterministic form, as per RFC-8949 section 4.2.
+    /// Integers, lengths and tags use the shortest argument, floating point
+    /// values use the shortest form that preserves the value, bignums that
+    /// fit within 64-bits are encoded as integers, map entries are sorted
+    /// by the bytewise order of their encoded keys and [Tag::Set] items by
+    /// the bytewise order of their encoding. Indefinite length items,
+    /// duplicate map keys and duplicate set items are rejected. Equal values shall produce
+    /// identical bytes, useful for content hashing and signatures.
+    pub deterministic: bool,
+    /// Wrap the value in [Tag::StringRefNamespace] and replace repeated
+    /// text and byte strings, including map keys, with [Tag::StringRef].
+    /// Strings are numbered in order of appearance, those too short to
+    /// gain from a reference are not numbered.
+    pub string_refs: bool,
+    /// Encode arrays, maps, tagged items and, without `string_refs`, strings
+    /// that repeat within the value only once, as [Tag::Shareable] item, and
+    /// replace their repetitions with [Tag::SharedRef]. Values are shared by
+    /// equality and only the encoded size is reduced, `Arc<T>` is converted
+    /// to its value and its identity is not preserved across a round-trip,
+    /// refer [DecodeOptions::resolve_refs]. Map keys are not shared, existing
+    /// [Tag::Shareable] items are always numbered.
+    pub share_values: bool,
+}
+
+/// Options to control deserialization of Cbor values, refer [Cbor::decode_with].
+///
+/// Default options does not impose any limit, other than nesting depth of
+/// [RECURSION_LIMIT]. Applications decoding untrusted input shall set limits
+/// suitable for their data, like,
+///
+/// ```
+/// use cbordata::DecodeOptions;
+///
+/// let opts = DecodeOptions {
+///     max_bytes: 1024 * 1024,
+///     max_string: 64 * 1024,
+///     ..DecodeOptions::default()
+/// };
+/// ```
+#[derive(Debug, Clone)]
+pub struct DecodeOptions {
+    /// Maximum nesting depth of arrays, maps and tags. Larger values are
+    /// capped at [RECURSION_LIMIT], to bound the stack used for decoding.
+    pub max_depth: u32,
+    /// Maximum number of bytes to read for the data item.
+    pub max_bytes: usize,
+    /// Maximum length of a byte string or text string, for indefinite
+    /// length strings this is the combined length of all chunks.
+    pub max_string: usize,
+    /// Maximum number of items in an array or entries in a map.
+    pub max_entries: u64,
+    /// Maximum size of bignum, in bytes.
+    pub max_bignum: usize,
+    /// Decode arrays and maps nested deeper than `lazy_depth` as
+    /// [Cbor::Binary], refer [Cbor::decode_lazy].
+    pub lazy_depth: Option<u32>,
+    /// Resolve string references and shared values. [Tag::StringRef] is
+    /// replaced with the referred string, [Tag::StringRefNamespace] and
+    /// [Tag::Shareable] with their item and [Tag::SharedRef] with a copy of
+    /// the referred shareable item. References are resolved within the
+    /// decoded data item. Resolved references count towards
+    /// `max_bytes`, as if the referred value was repeated in the input.
+    /// Each reference is a separate value in the decoded tree, converting
+    /// it into `Arc<T>` creates a new Arc for every reference.
+    /// Cannot be combined with `lazy_depth`.
+    pub resolve_refs: bool,
+    /// Reject data items that are well-formed but not valid, like
+    /// duplicate items in a [Tag::Set].
+    pub strict: bool,
+    /// How to handle duplicate keys in a map, refer [DuplicateKeys].
+    pub duplicate_keys: DuplicateKeys,
+}
+
+impl Default for DecodeOptions {
+    fn default() -> DecodeOptions {
+        DecodeOptions {
+            max_depth: RECURSION_LIMIT,
+            max_bytes: usize::MAX,
+            max_string: usize::MAX,
+            max_entries: u64::MAX,
+            max_bignum: usize::MAX,
+            lazy_depth: None,
+            resolve_refs: false,
+            strict: false,
+            duplicate_keys: DuplicateKeys::Allow,
+        }
+    }
+}
+
+/// Policy for duplicate keys in a map. Keys are compared as [Key] values,
+/// so `1` encoded in different widths are duplicates, while `1` and `1.0`
+/// are not.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum DuplicateKeys {
+    /// Keep all entries, default.
+    Allow,
+    /// Fail with [ErrorKind::InvalidData][crate::ErrorKind].
+    Reject,
+    /// Keep the first entry for a key.
+    KeepFirst,
+    /// Keep the last entry for a key.
+    KeepLast,
+}
+
+// Apply `policy` on duplicate keys in `map`, remaining entries retain their
+// order. For definite length maps, `info` is updated with the new length.
+fn dedup_map(
+    info: &mut Info,
+    map: &mut Vec<(Key, Cbor)>,
+    policy: DuplicateKeys,
+) -> Result<()> {
+    if policy == DuplicateKeys::Allow {
+        return Ok(());
+    }
+
+    // stable sort, entries with equal keys remain in input order.
+    let mut index: Vec<usize> = (0..map.len()).collect();
+    index.sort_by(|a, b| map[*a].0.cmp(&map[*b].0));
+
+    let mut keep = vec![true; map.len()];
+    for w in index.windows(2) {
+        let (a, b) = (w[0], w[1]);
+        if map[a].0 != map[b].0 {
+            continue;
+        }
+        match policy {
+            DuplicateKeys::Reject => {
+                err_at!(InvalidData, msg: "duplicate map key {:?}", map[a].0)?
+            }
+            DuplicateKeys::KeepFirst => keep[b] = false,
+            DuplicateKeys::KeepLast => keep[a] = false,
+            DuplicateKeys::Allow => unreachable!(),
+        }
+    }
+
+    if keep.iter().any(|k| !k) {
+        let mut keep = keep.into_iter();
+        map.retain(|_| keep.next().unwrap());
+        if *info != Info::Indefinite {
+            *info = Info::try_from(map.len())?;
+        }
+    }
+    Ok(())
+}
+
+// State maintained while encoding a data item with references.
+struct PackCtx<'a> {
+    opts: &'a EncodeOptions,
+    // strings in the current namespace, only when string_refs is enabled.
+    strings: Option<HashMap<(u8, Vec<u8>), u64>>,
+    // number of times an item occurs, keyed by its encoding, and index of
+    // encoded shareable items, only when share_values is enabled.
+    repeats: HashMap<Vec<u8>, usize>,
+    shared: Option<HashMap<Vec<u8>, u64>>,
+    n_shared: u64,
+    in_key: bool, // map keys are not shared
+}
+
+impl<'a> PackCtx<'a> {
+    fn new(opts: &'a EncodeOptions, val: &Cbor) -> Result<PackCtx<'a>> {
+        let mut ctx = PackCtx {
+            opts,
+            strings: opts.string_refs.then(HashMap::default),
+            repeats: HashMap::default(),
+            shared: opts.share_values.then(HashMap::default),
+            n_shared: 0,
+            in_key: false,
+        };
+        if opts.share_values {
+            ctx.count_repeats(val, 1)?;
+        }
+        Ok(ctx)
+    }
+
+    // return the index of a previously seen string, else number the string
+    // if it is long enough.
+    fn string_ref(&mut self, major: u8, byts: &[u8]) -> Option<u64> {
+        let strings = self.strings.as_mut()?;
+        let key = (major, byts.to_vec());
+        match strings.get(&key) {
+            Some(index) => Some(*index),
+            None if byts.len() >= stringref_min_len(strings.len() as u64) => {
+                strings.insert(key, strings.len() as u64);
+                None
+            }
+            None => None,
+        }
+    }
+
+    // items that are shared when they repeat, strings are left to string_refs
+    // and existing shareable items and references are taken as is.
+    fn is_candidate(&self, val: &Cbor) -> bool {
+        match val {
+            _ if self.in_key => false,
+            Cbor::Major2(_, _) | Cbor::Major3(_, _) => !self.opts.string_refs,
+            Cbor::Major4(_, _) | Cbor::Major5(_, _) => true,
+            Cbor::Major6(_, Tag::Shareable(_)) | Cbor::Major6(_, Tag::SharedRef(_)) => {
+                false
+            }
+            Cbor::Major6(_, _) => true,
+            _ => false,
+        }
+    }
+
+    // count repeating items, in the order they are encoded. Items within a
+    // repeated item are not counted again, they are encoded only once.
+    fn count_repeats(&mut self, val: &Cbor, depth: u32) -> Result<()> {
+        if depth > RECURSION_LIMIT {
+            return err_at!(LimitExceeded, msg: "encode recursion limit exceeded");
+        }
+
+        let (item, depth, existing) = match val {
+            Cbor::Major6(_, Tag::Shareable(item)) => (item.as_ref(), depth + 1, true),
+            Cbor::Binary(data) => {
+                let (val, _) = Cbor::decode(&mut data.as_slice())?;
+                return self.count_repeats(&val, depth);
+            }
+            val => (val, depth, false),
+        };
+        if existing || self.is_candidate(item) {
+            let mut key: Vec<u8> = vec![];
+            item.encode(&mut key)?;
+            let count = self.repeats.entry(key).or_insert(0);
+            *count += 1;
+            if *count > 1 {
+                return Ok(());
+            }
+        }
+
+        match item {
+            Cbor::Major4(_, items) => {
+                for item in items.iter() {
+                    self.count_repeats(item, depth + 1)?;
+                }
+            }
+            Cbor::Major5(_, map) => {
+                for (_, val) in map.iter() {
+                    self.count_repeats(val, depth + 1)?;
+                }
+            }
+            Cbor::Major6(_, tag) => self.count_repeats(tag.as_item(), depth + 1)?,
+            _ => (),
+        }
+
+        Ok(())
+    }
+
+    // return the index of a previously encoded shareable item, else number
+    // the item if it shall be encoded as shareable. Existing shareable items
+    // are always numbered, other items only if they repeat and are longer
+    // than a reference to them.
+    fn shared_ref(&mut self, item: &Cbor, existing: bool) -> Result<Shared> {
+        let shared = match self.shared.as_mut() {
+            Some(shared) => shared,
+            None => return Ok(Shared::No),
+        };
+        let mut key: Vec<u8> = vec![];
+        item.encode(&mut key)?;
+        if let Some(index) = shared.get(&key) {
+            return Ok(Shared::Ref(*index));
+        }
+
+        // bytes saved by references shall cover the shareable tag.
+        let repeats = self.repeats.get(&key).copied().unwrap_or(0);
+        let ref_len = match Info::from(self.n_shared) {
+            Info::Tiny(_) => 3,
+            Info::U8 => 4,
+            Info::U16 => 5,
+            Info::U32 => 7,
+            _ => 11,
+        };
+        let saved = repeats.saturating_sub(1) * key.len().saturating_sub(ref_len);
+        if existing || saved > 2 {
+            shared.insert(key, self.n_shared);
+            self.n_shared += 1;
+            Ok(Shared::Yes)
+        } else {
+            Ok(Shared::No)
+        }
+    }
+}
+
+enum Shared {
+    No,
+    Yes,
+    Ref(u64),
+}
+
+fn encode_shared_ref<W>(index: u64, w: &mut W, depth: u32) -> Result<usize>
+where
+    W: io::Write,
+{
+    let val: Cbor = Tag::SharedRef(Box::new(index.into_cbor()?)).into();
+    val.do_encode(w, depth)
+}
+
+// Set items shall be an array of unique items. Items are compared in their
+// deterministic form, so that equal values encoded in different widths are
+// detected as duplicates.
+fn check_set(val: &Cbor) -> Result<()> {
+    let items = match val {
+        Cbor::Major4(_, items) => items,
+        _ => err_at!(InvalidData, msg: "set is not an array")?,
+    };
+
+    let mut keys = items.iter().map(canonical_bytes).collect::<Result<Vec<Vec<u8>>>>()?;
+    keys.sort();
+    match keys.windows(2).any(|w| w[0] == w[1]) {
+        true => err_at!(InvalidData, msg: "duplicate item in set"),
+        false => Ok(()),
+    }
+}
+
+// Encode `val` in deterministic form, used to compare items. Unlike
+// EncodeOptions::deterministic, indefinite lengths are encoded as definite,
+// duplicate keys and set items are kept and simple values, other than floats,
+// are encoded as is. Fails only if `val` cannot be encoded at all.
+fn canonical_bytes(val: &Cbor) -> Result<Vec<u8>> {
+    let mut buf: Vec<u8> = vec![];
+    val.do_encode_deterministic(&mut buf, 1, false)?;
+    Ok(buf)
+}
+
+// Minimum length of a string to be numbered for [Tag::StringRef], such that
+// the reference is shorter than the string, refer http://cbor.schmorp.de/stringref
+fn stringref_min_len(index: u64) -> usize {
+    match index {
+        0..=23 => 3,
+        24..=255 => 4,
+        256..=65535 => 5,
+        65536..=4294967295 => 7,
+        _ => 11,
+    }
+}
+
+// State maintained while decoding a data item.
+struct DecodeCtx<'a> {
+    opts: &'a DecodeOptions,
+    n_bytes: usize, // bytes read so far
+    // string namespaces, innermost last, only when resolving references.
+    strings: Vec<Vec<Cbor>>,
+    // shareable items in order of appearance, only when resolving references.
+    shared: Vec<SharedItem>,
+}
+
+// Shareable item and its references are decoded as placeholders, that are
+// replaced with the item once the data item is decoded, refer DecodeCtx::fill.
+// Item is moved into its last reference and cloned for the others.
+#[derive(Default)]
+struct SharedItem {
+    val: Option<Cbor>,   // None while decoding the item
+    size: usize,         // bytes read for the item, counted for each reference
+    uses: usize,         // number of placeholders yet to be filled
+    height: Option<u32>, // nesting depth of the item, once its placeholders are filled
+}
+
+impl<'a> DecodeCtx<'a> {
+    fn consume(&mut self, n: usize) -> Result<()> {
+        self.n_bytes = self.n_bytes.saturating_add(n);
+        if self.n_bytes > self.opts.max_bytes {
+            let limit = self.opts.max_bytes;
+            err_at!(LimitExceeded, msg: "max_bytes limit {} exceeded", limit)?
+        }
+        Ok(())
+    }
+
+    fn check_string(&self, len: usize) -> Result<()> {
+        if len > self.opts.max_string {
+            let limit = self.opts.max_string;
+            err_at!(LimitExceeded, msg: "max_string limit {} exceeded", limit)?
+        }
+        Ok(())
+    }
+
+    fn check_entries(&self, len: u64) -> Result<()> {
+        if len > self.opts.max_entries {
+            let limit = self.opts.max_entries;
+            err_at!(LimitExceeded, msg: "max_entries limit {} exceeded", limit)?
+        }
+        Ok(())
+    }
+
+    // read definite length string, limits are checked before reading the data.
+    fn read_string<R>(&mut self, info: Info, r: &mut R) -> Result<(Vec<u8>, usize)>
+    where
+        R: io::Read,
+    {
+        let (len, m) = decode_addnl(info, r)?;
+        self.consume(m)?;
+        let n = err_at!(OutOfRange, usize::try_from(len))?;
+        self.check_string(n)?;
+        self.consume(n)?;
+        Ok((read_bytes(r, len)?, m + n))
+    }
+
+    // read chunks of indefinite length string, until break.
+    fn read_chunks<R>(&mut self, major: u8, r: &mut R) -> Result<(Vec<u8>, usize)>
+    where
+        R: io::Read,
+    {
+        let mut data: Vec<u8> = Vec::default();
+        let mut m = 0_usize;
+        loop {
+            let (chunk_major, info, k) = decode_hdr(r)?;
+            self.consume(k)?;
+            m += k;
+            match (chunk_major, info) {
+                (7, Info::Indefinite) => break,
+                (chunk_major, info)
+                    if chunk_major == major && info != Info::Indefinite =>
+                {
+                    let (chunk, k) = self.read_string(info, r)?;
+                    data.extend_from_slice(&chunk);
+                    m += k;
+                }
+                _ => err_at!(InvalidHeader, msg: "expected chunk of major {}", major)?,
+            }
+            self.check_string(data.len())?;
+        }
+        Ok((data, m))
+    }
+
+    // number the string in the current namespace, if it is long enough.
+    fn add_string(&mut self, val: &Cbor) {
+        if let Some(strings) = self.strings.last_mut() {
+            match val {
+                Cbor::Major2(_, byts) | Cbor::Major3(_, byts)
+                    if byts.len() >= stringref_min_len(strings.len() as u64) =>
+                {
+                    strings.push(val.clone())
+                }
+                _ => (),
+            }
+        }
+    }
+
+    fn resolve(&mut self, info: Info, tag: Tag) -> Result<Cbor> {
+        let val = match tag {
+            Tag::StringRef(item) => {
+                let index = u64::from_cbor(*item)?;
+                let strings = match self.strings.last() {
+                    Some(strings) => strings,
+                    None => {
+                        err_at!(InvalidData, msg: "string reference outside namespace")?
+                    }
+                };
+                let val = match usize::try_from(index).ok().and_then(|i| strings.get(i)) {
+                    Some(val) => val.clone(),
+                    None => {
+                        err_at!(InvalidData, msg: "invalid string reference {}", index)?
+                    }
+                };
+                if let Cbor::Major2(_, byts) | Cbor::Major3(_, byts) = &val {
+                    self.consume(byts.len())?;
+                }
+                val
+            }
+            Tag::StringRefNamespace(item) => *item,
+            // item is the index of the shareable item, refer Tag::decode.
+            Tag::Shareable(item) => {
+                let index = u64::from_cbor(*item)?;
+                self.shared[index as usize].uses += 1;
+                Tag::SharedRef(Box::new(index.into_cbor()?)).into()
+            }
+            Tag::SharedRef(item) => {
+                let index = u64::from_cbor(*item)?;
+                let shared =
+                    usize::try_from(index).ok().and_then(|i| self.shared.get_mut(i));
+                let n = match shared {
+                    Some(SharedItem { val: Some(_), size, uses, .. }) => {
+                        *uses += 1;
+                        *size
+                    }
+                    Some(_) => {
+                        err_at!(InvalidData, msg: "shared reference {} to incomplete value", index)?
+                    }
+                    None => {
+                        err_at!(InvalidData, msg: "invalid shared reference {}", index)?
+                    }
+                };
+                self.consume(n)?;
+                Tag::SharedRef(Box::new(index.into_cbor()?)).into()
+            }
+            tag => Cbor::Major6(info, tag),
+        };
+        Ok(val)
+    }
+
+    // replace placeholders for shared items in `val`, return its height.
+    fn fill(&mut self, val: &mut Cbor, depth: u32) -> Result<u32> {
+        let limit = cmp::min(self.opts.max_depth, RECURSION_LIMIT);
+        if depth > limit {
+            return err_at!(LimitExceeded, msg: "decode recursion limt exceeded");
+        }
+
+        let height = match val {
+            Cbor::Major4(_, items) => {
+                let mut height = 0;
+                for item in items.iter_mut() {
+                    height = cmp::max(height, self.fill(item, depth + 1)?);
+                }
+                height + 1
+            }
+            Cbor::Major5(_, map) => {
+                let mut height = 0;
+                for (key, val) in map.iter_mut() {
+                    if let Key::Cbor(item) = key {
+                        height = cmp::max(height, self.fill(item, depth + 1)?);
+                        *key = Key::from_cbor(item.as_ref().clone())?;
+                    }
+                    height = cmp::max(height, self.fill(val, depth + 1)?);
+                }
+                height + 1
+            }
+            Cbor::Major6(_, Tag::SharedRef(item)) => {
+                let index = u64::from_cbor(item.as_ref().clone())? as usize;
+                let height = match self.shared[index].height {
+                    Some(height) => height,
+                    None => {
+                        let mut item = self.shared[index].val.take().unwrap();
+                        let height = self.fill(&mut item, 1)?;
+                        self.shared[index].val = Some(item);
+                        self.shared[index].height = Some(height);
+                        height
+                    }
+                };
+                if (depth - 1) + height > limit {
+                    return err_at!(LimitExceeded, msg: "decode recursion limt exceeded");
+                }
+                let shared = &mut self.shared[index];
+                shared.uses -= 1;
+                *val = match shared.uses {
+                    0 => shared.val.take().unwrap(),
+                    _ => shared.val.clone().unwrap(),
+                };
+                height
+            }
+            Cbor::Major6(_, tag) => self.fill(tag.as_item_mut(), depth + 1)? + 1,
+            _ => 1,
+        };
+
+        Ok(height)
+    }
+}
+
 impl Cbor {
     /// Serialize this cbor value.
     pub fn encode<W>(&self, w: &mut W) -> Result<usize>
     where
         W: io::Write,
     {
-        self.do_encode(w, 1)
+        self.do_encode(w, 1).map_err(Error::with_backtrace)
+    }
+
+    /// Serialize this cbor value using `opts`.
+    pub fn encode_with<W>(&self, w: &mut W, opts: &EncodeOptions) -> Result<usize>
+    where
+        W: io::Write,
+    {
+        self.do_encode_with(w, opts).map_err(Error::with_backtrace)
+    }
+
+    fn do_encode_with<W>(&self, w: &mut W, opts: &EncodeOptions) -> Result<usize>
+    where
+        W: io::Write,
+    {
+        match opts {
+            EncodeOptions {
+                deterministic: true,
+                string_refs: false,
+                share_values: false,
+            } => self.do_encode_deterministic(w, 1, true),
+            EncodeOptions { deterministic: true, .. } => {
+                err_at!(Unsupported, msg: "references in deterministic encoding")
+            }
+            EncodeOptions { string_refs: true, .. } => {
+                let num = TagNum::StringRefNamespace as u64;
+                let n = encode_hdr(6, num.into(), w)? + encode_addnl(num.into(), num, w)?;
+                let mut ctx = PackCtx::new(opts, self)?;
+                Ok(n + self.do_encode_packed(w, 2, &mut ctx)?)
+            }
+            EncodeOptions { share_values: true, .. } => {
+                let mut ctx = PackCtx::new(opts, self)?;
+                self.do_encode_packed(w, 1, &mut ctx)
+            }
+            _ => self.do_encode(w, 1),
+        }
+    }
+
+    // encode with string references and shared values, refer EncodeOptions.
+    fn do_encode_packed<W>(
+        &self,
+        w: &mut W,
+        depth: u32,
+        ctx: &mut PackCtx,
+    ) -> Result<usize>
+    where
+        W: io::Write,
+    {
+        if depth > RECURSION_LIMIT {
+            return err_at!(LimitExceeded, msg: "encode recursion limit exceeded");
+        }
+
+        if ctx.is_candidate(self) {
+            match ctx.shared_ref(self, false)? {
+                Shared::Ref(index) => return encode_shared_ref(index, w, depth),
+                Shared::Yes => {
+                    let num = TagNum::Shareable as u64;
+                    let n =
+                        encode_hdr(6, num.into(), w)? + encode_addnl(num.into(), num, w)?;
+                    return Ok(n + self.do_encode_item_packed(w, depth + 1, ctx)?);
+                }
+                Shared::No => (),
+            }
+        }
+
+        self.do_encode_item_packed(w, depth, ctx)
+    }
+
+    fn do_encode_item_packed<W>(
+        &self,
+        w: &mut W,
+        depth: u32,
+        ctx: &mut PackCtx,
+    ) -> Result<usize>
+    where
+        W: io::Write,
+    {
+        if depth > RECURSION_LIMIT {
+            return err_at!(LimitExceeded, msg: "encode recursion limit exceeded");
+        }
+
+        let major = self.to_major_val();
+        let n = match self {
+            Cbor::Major2(info, byts) | Cbor::Major3(info, byts)
+                if *info != Info::Indefinite =>
+            {
+                match ctx.string_ref(major, byts) {
+                    Some(index) => {
+                        let val: Cbor =
+                            Tag::StringRef(Box::new(index.into_cbor()?)).into();
+                        val.do_encode(w, depth)?
+                    }
+                    None => self.do_encode(w, depth)?,
+                }
+            }
+            Cbor::Major4(info, list) => {
+                let mut n = encode_hdr(major, *info, w)?;
+                if *info != Info::Indefinite {
+                    let len = err_at!(OutOfRange, u64::try_from(list.len()))?;
+                    n += encode_addnl(*info, len, w)?;
+                }
+                for x in list.iter() {
+                    n += x.do_encode_packed(w, depth + 1, ctx)?;
+                }
+                if *info == Info::Indefinite {
+                    n += encode_hdr(7, Info::Indefinite, w)?;
+                }
+                n
+            }
+            Cbor::Major5(info, map) => {
+                let mut n = encode_hdr(major, *info, w)?;
+                if *info != Info::Indefinite {
+                    let len = err_at!(OutOfRange, u64::try_from(map.len()))?;
+                    n += encode_addnl(*info, len, w)?;
+                }
+                for (key, val) in map.iter() {
+                    let key = key.clone().into_cbor()?;
+                    let in_key = std::mem::replace(&mut ctx.in_key, true);
+                    let res = key.do_encode_packed(w, depth + 1, ctx);
+                    ctx.in_key = in_key;
+                    n += res?;
+                    n += val.do_encode_packed(w, depth + 1, ctx)?;
+                }
+                if *info == Info::Indefinite {
+                    n += encode_hdr(7, Info::Indefinite, w)?;
+                }
+                n
+            }
+            Cbor::Major6(info, tag) => {
+                let num = tag.to_tag_value();
+                let item = tag.as_item();
+                let shared = match tag {
+                    Tag::Shareable(item) => ctx.shared_ref(item, true)?,
+                    _ => Shared::No,
+                };
+                match (shared, tag) {
+                    (Shared::Ref(index), _) => encode_shared_ref(index, w, depth)?,
+                    (_, Tag::StringRefNamespace(_)) if ctx.opts.string_refs => {
+                        let n =
+                            encode_hdr(major, *info, w)? + encode_addnl(*info, num, w)?;
+                        let outer = ctx.strings.replace(HashMap::default());
+                        let res = item.do_encode_packed(w, depth + 1, ctx);
+                        ctx.strings = outer;
+                        n + res?
+                    }
+                    (_, Tag::Shareable(_)) => {
+                        let n =
+                            encode_hdr(major, *info, w)? + encode_addnl(*info, num, w)?;
+                        n + item.do_encode_item_packed(w, depth + 1, ctx)?
+                    }
+                    (_, _) => {
+                        let n =
+                            encode_hdr(major, *info, w)? + encode_addnl(*info, num, w)?;
+                        n + item.do_encode_packed(w, depth + 1, ctx)?
+                    }
+                }
+            }
+            Cbor::Binary(data) => {
+                let (val, _) = Cbor::decode(&mut data.as_slice())?;
+                val.do_encode_packed(w, depth, ctx)?
+            }
+            _ => self.do_encode(w, depth)?,
+        };
+
+        Ok(n)
+    }
+
+    // with `strict` as false, values that cannot be encoded deterministically
+    // are encoded as close to it as possible, refer canonical_bytes.
+    fn do_encode_deterministic<W>(
+        &self,
+        w: &mut W,
+        depth: u32,
+        strict: bool,
+    ) -> Result<usize>
+    where
+        W: io::Write,
+    {
+        if depth > RECURSION_LIMIT {
+            return err_at!(LimitExceeded, msg: "encode recursion limit exceeded");
+        }
+
+        let major = self.to_major_val();
+        let n = match self {
+            Cbor::Major0(_, num) | Cbor::Major1(_, num) => {
+                encode_hdr(major, (*num).into(), w)?
+                    + encode_addnl((*num).into(), *num, w)?
+            }
+            Cbor::Major2(Info::Indefinite, _)
+            | Cbor::Major3(Info::Indefinite, _)
+            | Cbor::Major4(Info::Indefinite, _)
+            | Cbor::Major5(Info::Indefinite, _)
+                if strict =>
+            {
+                err_at!(Unsupported, msg: "indefinite length in deterministic encoding")?
+            }
+            Cbor::Major2(_, byts) | Cbor::Major3(_, byts) => {
+                let len = err_at!(OutOfRange, u64::try_from(byts.len()))?;
+                let n =
+                    encode_hdr(major, len.into(), w)? + encode_addnl(len.into(), len, w)?;
+                write_w!(w, byts);
+                n + byts.len()
+            }
+            Cbor::Major4(_, list) => {
+                let len = err_at!(OutOfRange, u64::try_from(list.len()))?;
+                let mut n =
+                    encode_hdr(major, len.into(), w)? + encode_addnl(len.into(), len, w)?;
+                for x in list.iter() {
+                    n += x.do_encode_deterministic(w, depth + 1, strict)?;
+                }
+                n
+            }
+            Cbor::Major5(_, map) => {
+                let mut items: Vec<(Vec<u8>, Vec<u8>)> = Vec::with_capacity(map.len());
+                for (key, val) in map.iter() {
+                    let (mut kbuf, mut vbuf) = (vec![], vec![]);
+                    let key = key.clone().into_cbor()?;
+                    key.do_encode_deterministic(&mut kbuf, depth + 1, strict)?;
+                    val.do_encode_deterministic(&mut vbuf, depth + 1, strict)?;
+                    items.push((kbuf, vbuf));
+                }
+                items.sort_by(|a, b| a.0.cmp(&b.0));
+
+                let len = err_at!(OutOfRange, u64::try_from(items.len()))?;
+                let mut n =
+                    encode_hdr(major, len.into(), w)? + encode_addnl(len.into(), len, w)?;
+                for (i, (key, val)) in items.iter().enumerate() {
+                    if strict && i > 0 && items[i - 1].0 == *key {
+                        err_at!(Unsupported, msg: "duplicate key in deterministic encoding")?
+                    }
+                    write_w!(w, key);
+                    write_w!(w, val);
+                    n += key.len() + val.len();
+                }
+                n
+            }
+            Cbor::Major6(_, Tag::UBigNum(val))
+                if strict || matches!(val.as_ref(), Cbor::Major2(_, _)) =>
+            {
+                encode_bignum_deterministic(0, val, w)?
+            }
+            Cbor::Major6(_, Tag::SBigNum(val))
+                if strict || matches!(val.as_ref(), Cbor::Major2(_, _)) =>
+            {
+                encode_bignum_deterministic(1, val, w)?
+            }
+            Cbor::Major6(_, tag) => {
+                let num = tag.to_tag_value();
+                let n =
+                    encode_hdr(major, num.into(), w)? + encode_addnl(num.into(), num, w)?;
+                match tag {
+                    Tag::Set(val) => {
+                        n + encode_set_deterministic(val, w, depth + 1, strict)?
+                    }
+                    tag => {
+                        n + tag.as_item().do_encode_deterministic(w, depth + 1, strict)?
+                    }
+                }
+            }
+            Cbor::Major7(_, SimpleValue::F16(f)) => {
+                let f = f64::from(f16_to_f32(*f));
+                shortest_float(f).into_cbor()?.do_encode(w, depth)?
+            }
+            Cbor::Major7(_, SimpleValue::F32(f)) => {
+                shortest_float(f64::from(*f)).into_cbor()?.do_encode(w, depth)?
+            }
+            Cbor::Major7(_, SimpleValue::F64(f)) => {
+                shortest_float(*f).into_cbor()?.do_encode(w, depth)?
+            }
+            Cbor::Major7(_, SimpleValue::Break) if strict => {
+                err_at!(Unsupported, msg: "break in deterministic encoding")?
+            }
+            Cbor::Major7(_, _) if !strict => self.do_encode(w, depth)?,
+            Cbor::Major7(_, sval) => {
+                let info = match sval.to_info() {
+                    Some(info) => info,
+                    None => {
+                        err_at!(Unsupported, msg: "{:?} in deterministic encoding", sval)?
+                    }
+                };
+                encode_hdr(major, info, w)? + SimpleValue::encode(sval, w)?
+            }
+            Cbor::Binary(data) => {
+                let (val, _) = Cbor::decode(&mut data.as_slice())?;
+                val.do_encode_deterministic(w, depth, strict)?
+            }
+        };
+
+        Ok(n)
     }
 
     fn do_encode<W>(&self, w: &mut W, depth: u32) -> Result<usize>
@@ -158,59 +995,76 @@ impl Cbor {
         W: io::Write,
     {
         if depth > RECURSION_LIMIT {
-            return err_at!(FailCbor, msg: "encode recursion limit exceeded");
+            return err_at!(LimitExceeded, msg: "encode recursion limit exceeded");
         }
 
         let major = self.to_major_val();
         let n = match self {
-            Cbor::Major0(info, num) => {
-                let n = encode_hdr(major, *info, w)?;
-                n + encode_addnl(*num, w)?
+            Cbor::Major0(info, num) | Cbor::Major1(info, num) => {
+                encode_hdr(major, *info, w)? + encode_addnl(*info, *num, w)?
             }
-            Cbor::Major1(info, num) => {
-                let n = encode_hdr(major, *info, w)?;
-                n + encode_addnl(*num, w)?
+            Cbor::Major2(Info::Indefinite, byts)
+            | Cbor::Major3(Info::Indefinite, byts) => {
+                // encoded as a single chunk, followed by break.
+                let mut n = encode_hdr(major, Info::Indefinite, w)?;
+                if !byts.is_empty() {
+                    let len = err_at!(OutOfRange, u64::try_from(byts.len()))?;
+                    n += encode_hdr(major, len.into(), w)?;
+                    n += encode_addnl(len.into(), len, w)?;
+                    write_w!(w, byts);
+                    n += byts.len();
+                }
+                n + encode_hdr(7, Info::Indefinite, w)?
             }
-            Cbor::Major2(info, byts) => {
+            Cbor::Major2(info, byts) | Cbor::Major3(info, byts) => {
                 let n = encode_hdr(major, *info, w)?;
-                let m =
-                    encode_addnl(err_at!(FailConvert, u64::try_from(byts.len()))?, w)?;
+                let len = err_at!(OutOfRange, u64::try_from(byts.len()))?;
+                let m = encode_addnl(*info, len, w)?;
                 write_w!(w, byts);
                 n + m + byts.len()
             }
-            Cbor::Major3(info, text) => {
-                let n = encode_hdr(major, *info, w)?;
-                let m = encode_addnl(err_at!(FailCbor, u64::try_from(text.len()))?, w)?;
-                write_w!(w, text);
-                n + m + text.len()
-            }
             Cbor::Major4(info, list) => {
-                let n = encode_hdr(major, *info, w)?;
-                let m =
-                    encode_addnl(err_at!(FailConvert, u64::try_from(list.len()))?, w)?;
-                let mut acc = 0;
+                let mut n = encode_hdr(major, *info, w)?;
+                if *info != Info::Indefinite {
+                    let len = err_at!(OutOfRange, u64::try_from(list.len()))?;
+                    n += encode_addnl(*info, len, w)?;
+                }
                 for x in list.iter() {
-                    acc += x.do_encode(w, depth + 1)?;
+                    n += x.do_encode(w, depth + 1)?;
                 }
-                n + m + acc
+                if *info == Info::Indefinite {
+                    n += encode_hdr(7, Info::Indefinite, w)?;
+                }
+                n
             }
             Cbor::Major5(info, map) => {
-                let n = encode_hdr(major, *info, w)?;
-                let m = encode_addnl(err_at!(FailConvert, u64::try_from(map.len()))?, w)?;
-                let mut acc = 0;
+                let mut n = encode_hdr(major, *info, w)?;
+                if *info != Info::Indefinite {
+                    let len = err_at!(OutOfRange, u64::try_from(map.len()))?;
+                    n += encode_addnl(*info, len, w)?;
+                }
                 for (key, val) in map.iter() {
                     let key = key.clone().into_cbor()?;
-                    acc += key.do_encode(w, depth + 1)?;
-                    acc += val.do_encode(w, depth + 1)?;
+                    n += key.do_encode(w, depth + 1)?;
+                    n += val.do_encode(w, depth + 1)?;
                 }
-                n + m + acc
+                if *info == Info::Indefinite {
+                    n += encode_hdr(7, Info::Indefinite, w)?;
+                }
+                n
             }
             Cbor::Major6(info, tag) => {
                 let n = encode_hdr(major, *info, w)?;
-                let m = Tag::encode(tag, w)?;
+                let m = Tag::encode(*info, tag, w, depth)?;
                 n + m
             }
             Cbor::Major7(info, sval) => {
+                match sval.to_info() {
+                    Some(val) if val == *info => (),
+                    _ => {
+                        err_at!(InvalidHeader, msg: "info {:?} mismatch {:?}", info, sval)?
+                    }
+                }
                 let n = encode_hdr(major, *info, w)?;
                 let m = SimpleValue::encode(sval, w)?;
                 n + m
@@ -230,145 +1084,370 @@ impl Cbor {
     where
         R: io::Read,
     {
-        Cbor::do_decode(r, 1)
+        Cbor::decode_with(r, &DecodeOptions::default())
     }
 
-    fn do_decode<R>(reader: &mut R, depth: u32) -> Result<(Cbor, usize)>
+    /// Similar to [Cbor::decode], but arrays and maps nested deeper than
+    /// `depth` are not parsed into Cbor tree, instead they are returned as
+    /// [Cbor::Binary] holding the raw bytes of the sub-tree. With `depth`
+    /// as 1, only the top-level item is decoded. Use [Cbor::expand] to
+    /// decode the sub-tree when needed.
+    pub fn decode_lazy<R>(r: &mut R, depth: u32) -> Result<(Cbor, usize)>
     where
         R: io::Read,
     {
-        if depth > RECURSION_LIMIT {
-            return err_at!(FailCbor, msg: "decode recursion limt exceeded");
+        let opts = DecodeOptions {
+            lazy_depth: Some(depth),
+            ..DecodeOptions::default()
+        };
+        Cbor::decode_with(r, &opts)
+    }
+
+    /// Similar to [Cbor::decode], but fail if the input exceeds any of the
+    /// limits set in `opts`. Limits are checked before allocating memory for
+    /// strings and containers.
+    pub fn decode_with<R>(r: &mut R, opts: &DecodeOptions) -> Result<(Cbor, usize)>
+    where
+        R: io::Read,
+    {
+        Cbor::do_decode_with(r, opts).map_err(Error::with_backtrace)
+    }
+
+    fn do_decode_with<R>(r: &mut R, opts: &DecodeOptions) -> Result<(Cbor, usize)>
+    where
+        R: io::Read,
+    {
+        if opts.resolve_refs && opts.lazy_depth.is_some() {
+            err_at!(Fatal, msg: "resolve_refs with lazy_depth")?
+        }
+        let mut ctx = DecodeCtx {
+            opts,
+            n_bytes: 0,
+            strings: Vec::default(),
+            shared: Vec::default(),
+        };
+        // offset is approximate, bytes consumed before the failing read.
+        let (mut val, n) = Cbor::do_decode(r, 1, &mut ctx)
+            .map_err(|err| err.with_offset(ctx.n_bytes))?;
+        if !ctx.shared.is_empty() {
+            ctx.fill(&mut val, 1).map_err(|err| err.with_offset(ctx.n_bytes))?;
+        }
+        Ok((val, n))
+    }
+
+    fn do_decode<R>(
+        reader: &mut R,
+        depth: u32,
+        ctx: &mut DecodeCtx,
+    ) -> Result<(Cbor, usize)>
+    where
+        R: io::Read,
+    {
+        match Cbor::do_decode_or_break(reader, depth, ctx)? {
+            (Cbor::Major7(_, SimpleValue::Break), _) => {
+                err_at!(InvalidHeader, msg: "unexpected break")
+            }
+            res => Ok(res),
+        }
+    }
+
+    // break is returned as a value, only to end indefinite array and map.
+    fn do_decode_or_break<R>(
+        reader: &mut R,
+        depth: u32,
+        ctx: &mut DecodeCtx,
+    ) -> Result<(Cbor, usize)>
+    where
+        R: io::Read,
+    {
+        if depth > cmp::min(ctx.opts.max_depth, RECURSION_LIMIT) {
+            return err_at!(LimitExceeded, msg: "decode recursion limt exceeded");
         }
 
         let (major, info, n) = decode_hdr(reader)?;
+        ctx.consume(n)?;
 
         let (val, m) = match (major, info) {
+            (4, info) | (5, info) if depth > ctx.opts.lazy_depth.unwrap_or(u32::MAX) => {
+                let mut data: Vec<u8> = Vec::default();
+                encode_hdr(major, info, &mut data)?;
+                let limit = ctx.opts.max_bytes.saturating_sub(ctx.n_bytes);
+                let mut r = io::Read::take(&mut *reader, limit as u64);
+                let m = match copy_item_body(major, info, &mut r, &mut data, depth) {
+                    Ok(m) => m,
+                    Err(_) if r.limit() == 0 => {
+                        err_at!(LimitExceeded, msg: "max_bytes limit {} exceeded", limit)?
+                    }
+                    Err(err) => Err(err)?,
+                };
+                ctx.consume(m)?;
+                (Cbor::Binary(data), m)
+            }
             (0, info) => {
                 let (val, m) = decode_addnl(info, reader)?;
+                ctx.consume(m)?;
                 (Cbor::Major0(info, val), m)
             }
             (1, info) => {
                 let (val, m) = decode_addnl(info, reader)?;
+                ctx.consume(m)?;
                 (Cbor::Major1(info, val), m)
             }
             (2, Info::Indefinite) => {
-                let mut data: Vec<u8> = Vec::default();
-                let mut m = 0_usize;
-                loop {
-                    let (val, k) = Cbor::do_decode(reader, depth + 1)?;
-                    match val {
-                        Cbor::Major2(_, chunk) => data.extend_from_slice(&chunk),
-                        Cbor::Major7(_, SimpleValue::Break) => break,
-                        _ => err_at!(FailConvert, msg: "expected byte chunk")?,
-                    }
-                    m += k;
-                }
+                let (data, m) = ctx.read_chunks(2, reader)?;
                 (Cbor::Major2(info, data), m)
             }
             (2, info) => {
-                let (val, m) = decode_addnl(info, reader)?;
-                let len: usize = err_at!(FailConvert, val.try_into())?;
-                let mut data = vec![0; len];
-                read_r!(reader, &mut data);
-                (Cbor::Major2(info, data), m + len)
+                let (data, m) = ctx.read_string(info, reader)?;
+                let val = Cbor::Major2(info, data);
+                ctx.add_string(&val);
+                (val, m)
             }
             (3, Info::Indefinite) => {
-                let mut text: Vec<u8> = Vec::default();
-                let mut m = 0_usize;
-                loop {
-                    let (val, k) = Cbor::do_decode(reader, depth + 1)?;
-                    match val {
-                        Cbor::Major3(_, chunk) => text.extend_from_slice(&chunk),
-                        Cbor::Major7(_, SimpleValue::Break) => break,
-                        _ => err_at!(FailConvert, msg: "expected byte chunk")?,
-                    }
-                    m += k;
-                }
+                let (text, m) = ctx.read_chunks(3, reader)?;
                 (Cbor::Major3(info, text), m)
             }
             (3, info) => {
-                let (val, m) = decode_addnl(info, reader)?;
-                let len: usize = err_at!(FailConvert, val.try_into())?;
-                let mut text = vec![0; len];
-                read_r!(reader, &mut text);
-                (Cbor::Major3(info, text), m + len)
+                let (text, m) = ctx.read_string(info, reader)?;
+                let val = Cbor::Major3(info, text);
+                ctx.add_string(&val);
+                (val, m)
             }
             (4, Info::Indefinite) => {
                 let mut list: Vec<Cbor> = vec![];
                 let mut m = 0_usize;
                 loop {
-                    let (val, k) = Cbor::do_decode(reader, depth + 1)?;
+                    let (val, k) = Cbor::do_decode_or_break(reader, depth + 1, ctx)?;
+                    m += k;
                     match val {
                         Cbor::Major7(_, SimpleValue::Break) => break,
                         item => list.push(item),
                     }
-                    m += k;
+                    ctx.check_entries(list.len() as u64)?;
                 }
                 (Cbor::Major4(info, list), m)
             }
             (4, info) => {
                 let mut list: Vec<Cbor> = vec![];
                 let (len, mut m) = decode_addnl(info, reader)?;
+                ctx.consume(m)?;
+                ctx.check_entries(len)?;
                 for _ in 0..len {
-                    let (val, k) = Cbor::do_decode(reader, depth + 1)?;
+                    let (val, k) = Cbor::do_decode(reader, depth + 1, ctx)?;
                     list.push(val);
                     m += k;
                 }
                 (Cbor::Major4(info, list), m)
             }
-            (5, Info::Indefinite) => {
+            (5, mut info @ Info::Indefinite) => {
                 let mut map: Vec<(Key, Cbor)> = Vec::default();
                 let mut m = 0_usize;
                 loop {
-                    let (key, j) = Cbor::do_decode(reader, depth + 1)?;
-                    let (val, k) = Cbor::do_decode(reader, depth + 1)?;
-                    let val = match val {
+                    let (key, j) = Cbor::do_decode_or_break(reader, depth + 1, ctx)?;
+                    m += j;
+                    let key = match key {
                         Cbor::Major7(_, SimpleValue::Break) => break,
-                        val => val,
+                        key => key,
                     };
+                    let (val, k) = Cbor::do_decode(reader, depth + 1, ctx)?;
+                    m += k;
                     map.push((Key::from_cbor(key)?, val));
-                    m += j + k;
+                    ctx.check_entries(map.len() as u64)?;
                 }
+                dedup_map(&mut info, &mut map, ctx.opts.duplicate_keys)?;
                 (Cbor::Major5(info, map), m)
             }
-            (5, info) => {
+            (5, mut info) => {
                 let mut map: Vec<(Key, Cbor)> = Vec::default();
                 let (len, mut m) = decode_addnl(info, reader)?;
+                ctx.consume(m)?;
+                ctx.check_entries(len)?;
                 for _ in 0..len {
-                    let (key, j) = Cbor::do_decode(reader, depth + 1)?;
-                    let (val, k) = Cbor::do_decode(reader, depth + 1)?;
+                    let (key, j) = Cbor::do_decode(reader, depth + 1, ctx)?;
+                    let (val, k) = Cbor::do_decode(reader, depth + 1, ctx)?;
                     map.push((Key::from_cbor(key)?, val));
                     m += j + k;
                 }
+                dedup_map(&mut info, &mut map, ctx.opts.duplicate_keys)?;
                 (Cbor::Major5(info, map), m)
             }
             (6, info) => {
-                let (tag, m) = Tag::decode(info, reader)?;
-                (Cbor::Major6(info, tag), m)
+                let (tag, m) = Tag::decode(info, reader, depth, ctx)?;
+                match &tag {
+                    Tag::UBigNum(val) | Tag::SBigNum(val) => match val.as_ref() {
+                        Cbor::Major2(_, byts) if byts.len() > ctx.opts.max_bignum => {
+                            let limit = ctx.opts.max_bignum;
+                            err_at!(LimitExceeded, msg: "max_bignum limit {} exceeded", limit)?
+                        }
+                        _ => (),
+                    },
+                    Tag::Set(val) if ctx.opts.strict => check_set(val)?,
+                    _ => (),
+                }
+                match ctx.opts.resolve_refs {
+                    true => (ctx.resolve(info, tag)?, m),
+                    false => (Cbor::Major6(info, tag), m),
+                }
             }
             (7, info) => {
                 let (sval, m) = SimpleValue::decode(info, reader)?;
+                ctx.consume(m)?;
                 (Cbor::Major7(info, sval), m)
             }
-            _ => unreachable!(),
-        };
+            _ => unreachable!(),
+        };
+
+        Ok((val, (m + n)))
+    }
+
+    fn to_major_val(&self) -> u8 {
+        match self {
+            Cbor::Major0(_, _) => 0,
+            Cbor::Major1(_, _) => 1,
+            Cbor::Major2(_, _) => 2,
+            Cbor::Major3(_, _) => 3,
+            Cbor::Major4(_, _) => 4,
+            Cbor::Major5(_, _) => 5,
+            Cbor::Major6(_, _) => 6,
+            Cbor::Major7(_, _) => 7,
+            Cbor::Binary(data) => (data[0] & 0xe0) >> 5,
+        }
+    }
+
+    /// Move reader `r` past the next data item without decoding it, return
+    /// the number of bytes skipped. Skipping does not allocate memory for
+    /// strings and containers, useful for ignoring unknown fields and for
+    /// walking over a sequence of items.
+    pub fn skip<R>(r: &mut R) -> Result<usize>
+    where
+        R: io::Read,
+    {
+        Cbor::do_skip(r, 0).map_err(Error::with_backtrace)
+    }
+
+    /// Similar to [Cbor::skip], but for data item starting at `offset` in
+    /// `buf`. Return the byte range of the data item within `buf`. Error
+    /// offset is relative to `buf`.
+    pub fn skip_slice(buf: &[u8], offset: usize) -> Result<ops::Range<usize>> {
+        let mut data = match buf.get(offset..) {
+            Some(data) => data,
+            None => err_at!(OutOfRange, msg: "offset {} beyond {}", offset, buf.len())
+                .map_err(Error::with_backtrace)?,
+        };
+        let n = Cbor::do_skip(&mut data, offset).map_err(Error::with_backtrace)?;
+        Ok(offset..(offset + n))
+    }
+
+    // skip data item, on error the offset is `start` plus bytes skipped
+    // before the failing read.
+    fn do_skip<R>(r: &mut R, start: usize) -> Result<usize>
+    where
+        R: io::Read,
+    {
+        let mut w = Counter(0);
+        copy_item(r, &mut w, 1).map_err(|err| err.with_offset(start + w.0))
+    }
+
+    /// Decode a lazily decoded [Cbor::Binary] value into Cbor tree. Other
+    /// variants are returned as is.
+    pub fn expand(self) -> Result<Cbor> {
+        match self {
+            Cbor::Binary(data) => Ok(Cbor::decode(&mut data.as_slice())?.0),
+            val => Ok(val),
+        }
+    }
+
+    /// Check that the [Info] of this value, and all its nested values, is
+    /// consistent with its payload, so that the value can be encoded
+    /// faithfully. Useful for hand-built Cbor trees, values returned by
+    /// [Cbor::decode] are always consistent. Non-preferred widths, like
+    /// `Cbor::Major0(Info::U64, 5)`, are valid and encoded as such.
+    pub fn check(&self) -> Result<()> {
+        self.do_check(1)
+    }
+
+    fn do_check(&self, depth: u32) -> Result<()> {
+        if depth > RECURSION_LIMIT {
+            return err_at!(LimitExceeded, msg: "check recursion limit exceeded");
+        }
+
+        match self {
+            Cbor::Major0(info, num) | Cbor::Major1(info, num) => {
+                check_addnl(*info, *num)?
+            }
+            Cbor::Major2(info, byts) => check_len(*info, byts.len())?,
+            Cbor::Major3(info, text) => {
+                check_len(*info, text.len())?;
+                err_at!(Utf8, std::str::from_utf8(text))?;
+            }
+            Cbor::Major4(info, list) => {
+                check_len(*info, list.len())?;
+                for item in list.iter() {
+                    item.do_check(depth + 1)?;
+                }
+            }
+            Cbor::Major5(info, map) => {
+                check_len(*info, map.len())?;
+                for (key, val) in map.iter() {
+                    key.clone().into_cbor()?.do_check(depth + 1)?;
+                    val.do_check(depth + 1)?;
+                }
+            }
+            Cbor::Major6(info, tag) => {
+                check_addnl(*info, tag.to_tag_value())?;
+                match tag {
+                    Tag::UBigNum(val) | Tag::SBigNum(val) => match val.as_ref() {
+                        Cbor::Major2(_, _) => val.do_check(depth + 1)?,
+                        _ => err_at!(InvalidData, msg: "bignum not a byte string")?,
+                    },
+                    tag => tag.as_item().do_check(depth + 1)?,
+                }
+            }
+            Cbor::Major7(info, sval) => match sval.to_info() {
+                Some(val) if val == *info => (),
+                _ => err_at!(InvalidHeader, msg: "info {:?} mismatch {:?}", info, sval)?,
+            },
+            Cbor::Binary(data) => {
+                crate::validate(data)?;
+            }
+        }
 
-        Ok((val, (m + n)))
+        Ok(())
     }
 
-    fn to_major_val(&self) -> u8 {
+    /// Apply `policy` on duplicate keys in this map, and in all nested maps,
+    /// including maps within keys. Useful for hand-built Cbor trees, refer
+    /// [DecodeOptions::duplicate_keys] for decoded values. Lazily decoded
+    /// [Cbor::Binary] values are left as is.
+    pub fn dedup_keys(&mut self, policy: DuplicateKeys) -> Result<()> {
+        self.do_dedup_keys(policy, 1)
+    }
+
+    fn do_dedup_keys(&mut self, policy: DuplicateKeys, depth: u32) -> Result<()> {
+        if depth > RECURSION_LIMIT {
+            return err_at!(LimitExceeded, msg: "dedup recursion limit exceeded");
+        }
+
         match self {
-            Cbor::Major0(_, _) => 0,
-            Cbor::Major1(_, _) => 1,
-            Cbor::Major2(_, _) => 2,
-            Cbor::Major3(_, _) => 3,
-            Cbor::Major4(_, _) => 4,
-            Cbor::Major5(_, _) => 5,
-            Cbor::Major6(_, _) => 6,
-            Cbor::Major7(_, _) => 7,
-            Cbor::Binary(data) => (data[0] & 0xe0) >> 5,
+            Cbor::Major4(_, list) => {
+                for item in list.iter_mut() {
+                    item.do_dedup_keys(policy, depth + 1)?;
+                }
+            }
+            Cbor::Major5(info, map) => {
+                for (key, val) in map.iter_mut() {
+                    if let Key::Cbor(key) = key {
+                        key.do_dedup_keys(policy, depth + 1)?;
+                    }
+                    val.do_dedup_keys(policy, depth + 1)?;
+                }
+                dedup_map(info, map, policy)?;
+            }
+            Cbor::Major6(_, tag) => tag.as_item_mut().do_dedup_keys(policy, depth + 1)?,
+            _ => (),
         }
+
+        Ok(())
     }
 
     /// Convert bytes into Cbor major type-2 value. There is an ambiguity
@@ -379,7 +1458,7 @@ impl Cbor {
     /// into Cbor Major type-2, while using the [IntoCbor] trait shall convert
     /// it into Cbor Major type-4, a list of integer.
     pub fn from_bytes(val: Vec<u8>) -> Result<Self> {
-        let n = err_at!(FailConvert, u64::try_from(val.len()))?;
+        let n = err_at!(OutOfRange, u64::try_from(val.len()))?;
         Ok(Cbor::Major2(n.into(), val))
     }
 
@@ -387,9 +1466,190 @@ impl Cbor {
     pub fn into_bytes(self) -> Result<Vec<u8>> {
         match self {
             Cbor::Major2(_, val) => Ok(val),
-            _ => err_at!(FailConvert, msg: "not bytes"),
+            _ => err_at!(TypeMismatch, msg: "not bytes"),
+        }
+    }
+}
+
+impl Cbor {
+    /// Return the value for `key`, if this is a map, or a tagged map. When
+    /// the map has duplicate keys, value of the first entry is returned,
+    /// refer [DuplicateKeys].
+    pub fn get(&self, key: &Key) -> Option<&Cbor> {
+        let map = self.as_map()?;
+        map.iter().find(|(k, _)| k == key).map(|(_, val)| val)
+    }
+
+    /// Return the item at `index`, if this is an array, or a tagged array.
+    pub fn get_index(&self, index: usize) -> Option<&Cbor> {
+        self.as_array()?.get(index)
+    }
+
+    /// Return the number, if this is an integer or a bignum, within the
+    /// range of u64.
+    pub fn as_u64(&self) -> Option<u64> {
+        match self {
+            Cbor::Major0(_, num) => Some(*num),
+            val => u64::try_from(val.as_i128()?).ok(),
+        }
+    }
+
+    /// Return the number, if this is an integer or a bignum, within the
+    /// range of i128. Bignums with leading zeros, and those that fit
+    /// within major type 0 or 1, are also accepted.
+    pub fn as_i128(&self) -> Option<i128> {
+        use num_traits::cast::ToPrimitive;
+
+        match self {
+            Cbor::Major0(_, num) => Some(i128::from(*num)),
+            Cbor::Major1(_, num) => Some(-1 - i128::from(*num)),
+            Cbor::Major6(_, tag) => tag.to_bignum()?.to_i128(),
+            _ => None,
+        }
+    }
+
+    /// Return the text, if this is a text string with valid UTF-8.
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Cbor::Major3(_, text) => std::str::from_utf8(text).ok(),
+            _ => None,
+        }
+    }
+
+    /// Return the bytes, if this is a byte string.
+    pub fn as_bytes(&self) -> Option<&[u8]> {
+        match self {
+            Cbor::Major2(_, byts) => Some(byts),
+            _ => None,
+        }
+    }
+
+    /// Return the items, if this is an array. Tags, if any, are skipped,
+    /// like for [Cbor::get], [Cbor::pointer] and queries.
+    pub fn as_array(&self) -> Option<&[Cbor]> {
+        match untag(self) {
+            Cbor::Major4(_, items) => Some(items),
+            _ => None,
+        }
+    }
+
+    /// Return the entries, in input order, if this is a map. Tags, if any,
+    /// are skipped.
+    pub fn as_map(&self) -> Option<&[(Key, Cbor)]> {
+        match untag(self) {
+            Cbor::Major5(_, map) => Some(map),
+            _ => None,
+        }
+    }
+
+    /// Return true if this is null.
+    pub fn is_null(&self) -> bool {
+        matches!(self, Cbor::Major7(_, SimpleValue::Null))
+    }
+}
+
+/// Constructors, [Info] is derived from the value, so that the values
+/// encode correctly. Refer to [cbor!][crate::cbor!] macro for composing documents.
+impl Cbor {
+    /// Return null value.
+    pub fn null() -> Cbor {
+        NULL.clone()
+    }
+
+    /// Return true or false value.
+    pub fn bool(val: bool) -> Cbor {
+        match val {
+            true => Cbor::Major7(Info::Tiny(20), SimpleValue::True),
+            false => Cbor::Major7(Info::Tiny(21), SimpleValue::False),
+        }
+    }
+
+    /// Return integer value, as major type 0 or 1 if it fits within 64 bits,
+    /// else as bignum.
+    pub fn int(num: i128) -> Cbor {
+        match (u64::try_from(num), u64::try_from(-1 - num)) {
+            (Ok(num), _) => Cbor::Major0(num.into(), num),
+            (_, Ok(num)) => Cbor::Major1(num.into(), num),
+            _ if num > 0 => Cbor::bignum(Tag::UBigNum, num as u128),
+            _ => Cbor::bignum(Tag::SBigNum, (-1 - num) as u128),
         }
     }
+
+    fn bignum(tag: fn(Box<Cbor>) -> Tag, num: u128) -> Cbor {
+        let byts = num.to_be_bytes();
+        let n = byts.iter().take_while(|b| **b == 0).count();
+        tag(Box::new(Cbor::bytes(&byts[n..]))).into()
+    }
+
+    /// Return floating point value.
+    pub fn float(val: f64) -> Cbor {
+        Cbor::Major7(Info::U64, SimpleValue::F64(val))
+    }
+
+    /// Return byte string value.
+    pub fn bytes<B: Into<Vec<u8>>>(byts: B) -> Cbor {
+        let byts = byts.into();
+        Cbor::Major2(len_info(byts.len()), byts)
+    }
+
+    /// Return text string value.
+    pub fn text<S: Into<String>>(text: S) -> Cbor {
+        let text = text.into().into_bytes();
+        Cbor::Major3(len_info(text.len()), text)
+    }
+
+    /// Return array value of `items`.
+    pub fn array<I>(items: I) -> Cbor
+    where
+        I: IntoIterator<Item = Cbor>,
+    {
+        let items: Vec<Cbor> = items.into_iter().collect();
+        Cbor::Major4(len_info(items.len()), items)
+    }
+
+    /// Return map value of `entries`, in the given order.
+    pub fn map<I>(entries: I) -> Cbor
+    where
+        I: IntoIterator<Item = (Key, Cbor)>,
+    {
+        let map: Vec<(Key, Cbor)> = entries.into_iter().collect();
+        Cbor::Major5(len_info(map.len()), map)
+    }
+
+    /// Return `value` tagged with tag-number `num`. Tag-numbers known to
+    /// this implementation are mapped to their [Tag] variants.
+    pub fn tagged(num: u64, value: Cbor) -> Cbor {
+        Tag::from_value(num, value).into()
+    }
+}
+
+// usize is not wider than u64 on supported targets.
+fn len_info(len: usize) -> Info {
+    (len as u64).into()
+}
+
+static NULL: Cbor = Cbor::Major7(Info::Tiny(22), SimpleValue::Null);
+
+/// Index a map by text key. Return null if this is not a map or if key is
+/// missing, so that lookups can be chained, like `val["items"][3]["price"]`.
+impl<'a> ops::Index<&'a str> for Cbor {
+    type Output = Cbor;
+
+    fn index(&self, key: &'a str) -> &Cbor {
+        let map = self.as_map().unwrap_or(&[]);
+        let val = map.iter().find(|(k, _)| matches!(k, Key::Text(k) if k == key));
+        val.map(|(_, val)| val).unwrap_or(&NULL)
+    }
+}
+
+/// Index an array. Return null if this is not an array or if `index` is
+/// out of bounds.
+impl ops::Index<usize> for Cbor {
+    type Output = Cbor;
+
+    fn index(&self, index: usize) -> &Cbor {
+        self.get_index(index).unwrap_or(&NULL)
+    }
 }
 
 /// 5-bit value for additional info. Refer to Cbor [spec] for details.
@@ -469,7 +1729,7 @@ impl TryFrom<usize> for Info {
     type Error = Error;
 
     fn try_from(num: usize) -> Result<Info> {
-        Ok(err_at!(FailConvert, u64::try_from(num))?.into())
+        Ok(err_at!(OutOfRange, u64::try_from(num))?.into())
     }
 }
 
@@ -491,13 +1751,13 @@ impl Info {
     }
 }
 
-fn encode_hdr<W>(major: u8, info: Info, w: &mut W) -> Result<usize>
+pub(crate) fn encode_hdr<W>(major: u8, info: Info, w: &mut W) -> Result<usize>
 where
     W: io::Write,
 {
     let info = match info {
         Info::Tiny(val) if val <= 23 => val,
-        Info::Tiny(val) => err_at!(FailCbor, msg: "{} > 23", val)?,
+        Info::Tiny(val) => err_at!(InvalidHeader, msg: "{} > 23", val)?,
         Info::U8 => 24,
         Info::U16 => 25,
         Info::U32 => 26,
@@ -507,11 +1767,11 @@ where
         Info::Reserved30 => 30,
         Info::Indefinite => 31,
     };
-    write_w!(w, &[(major as u8) << 5 | info]);
+    write_w!(w, &[major << 5 | info]);
     Ok(1)
 }
 
-fn decode_hdr<R>(r: &mut R) -> Result<(u8, Info, usize)>
+pub(crate) fn decode_hdr<R>(r: &mut R) -> Result<(u8, Info, usize)>
 where
     R: io::Read,
 {
@@ -525,35 +1785,52 @@ where
     Ok((major, info.try_into()?, 1 /* only 1-byte read */))
 }
 
-fn encode_addnl<W>(num: u64, w: &mut W) -> Result<usize>
+/// Encode argument `num` using the width specified by `info`, fail if `num`
+/// does not fit within `info`. Use `num.into()` for preferred width.
+pub(crate) fn encode_addnl<W>(info: Info, num: u64, w: &mut W) -> Result<usize>
 where
     W: io::Write,
 {
-    let mut scratch = [0_u8; 8];
-    let n = match num {
-        0..=23 => 0,
-        n if n <= (u8::MAX as u64) => {
-            scratch[..1].copy_from_slice(&(n as u8).to_be_bytes());
-            1
-        }
-        n if n <= (u16::MAX as u64) => {
-            scratch[..2].copy_from_slice(&(n as u16).to_be_bytes());
-            2
-        }
-        n if n <= (u32::MAX as u64) => {
-            scratch[..4].copy_from_slice(&(n as u32).to_be_bytes());
-            4
-        }
-        n => {
-            scratch[..8].copy_from_slice(&n.to_be_bytes());
-            8
-        }
+    check_addnl(info, num)?;
+
+    let scratch = num.to_be_bytes();
+    let n = match info {
+        Info::U8 => 1,
+        Info::U16 => 2,
+        Info::U32 => 4,
+        Info::U64 => 8,
+        _ => 0,
     };
-    write_w!(w, &scratch[..n]);
+    write_w!(w, &scratch[(8 - n)..]);
     Ok(n)
 }
 
-fn decode_addnl<R>(info: Info, r: &mut R) -> Result<(u64, usize)>
+// Check that length `len` can be encoded with additional info `info`,
+// indefinite length is always valid.
+fn check_len(info: Info, len: usize) -> Result<()> {
+    match info {
+        Info::Indefinite => Ok(()),
+        info => check_addnl(info, err_at!(OutOfRange, u64::try_from(len))?),
+    }
+}
+
+// Check that argument `num` can be encoded with additional info `info`.
+fn check_addnl(info: Info, num: u64) -> Result<()> {
+    let ok = match info {
+        Info::Tiny(val) => val <= 23 && u64::from(val) == num,
+        Info::U8 => num <= u64::from(u8::MAX),
+        Info::U16 => num <= u64::from(u16::MAX),
+        Info::U32 => num <= u64::from(u32::MAX),
+        Info::U64 => true,
+        _ => false,
+    };
+    match ok {
+        true => Ok(()),
+        false => err_at!(InvalidHeader, msg: "info {:?} mismatch argument {}", info, num),
+    }
+}
+
+pub(crate) fn decode_addnl<R>(info: Info, r: &mut R) -> Result<(u64, usize)>
 where
     R: io::Read,
 {
@@ -574,14 +1851,220 @@ where
         }
         Info::U64 => {
             read_r!(r, &mut scratch[..8]);
-            (u64::from_be_bytes(scratch[..8].try_into().unwrap()) as u64, 8)
+            (u64::from_be_bytes(scratch[..8].try_into().unwrap()), 8)
         }
         Info::Indefinite => (0, 0),
-        _ => err_at!(FailCbor, msg: "no additional value")?,
+        _ => err_at!(InvalidHeader, msg: "no additional value")?,
+    };
+    Ok((num, n))
+}
+
+// Set items are sorted by the bytewise order of their encoding, like map keys.
+fn encode_set_deterministic<W>(
+    val: &Cbor,
+    w: &mut W,
+    depth: u32,
+    strict: bool,
+) -> Result<usize>
+where
+    W: io::Write,
+{
+    let list = match val {
+        Cbor::Major4(info, list) if !strict || *info != Info::Indefinite => list,
+        val => return val.do_encode_deterministic(w, depth, strict),
+    };
+    if depth > RECURSION_LIMIT {
+        return err_at!(LimitExceeded, msg: "encode recursion limit exceeded");
+    }
+
+    let mut items: Vec<Vec<u8>> = Vec::with_capacity(list.len());
+    for x in list.iter() {
+        let mut buf = vec![];
+        x.do_encode_deterministic(&mut buf, depth + 1, strict)?;
+        items.push(buf);
+    }
+    items.sort();
+
+    let len = err_at!(OutOfRange, u64::try_from(items.len()))?;
+    let mut n = encode_hdr(4, len.into(), w)? + encode_addnl(len.into(), len, w)?;
+    for (i, item) in items.iter().enumerate() {
+        if strict && i > 0 && items[i - 1] == *item {
+            err_at!(Unsupported, msg: "duplicate set item in deterministic encoding")?
+        }
+        write_w!(w, item);
+        n += item.len();
+    }
+    Ok(n)
+}
+
+// Bignums that fit within 64-bits are encoded as major type 0 or 1, others are
+// encoded without leading zeros.
+fn encode_bignum_deterministic<W>(major: u8, val: &Cbor, w: &mut W) -> Result<usize>
+where
+    W: io::Write,
+{
+    let byts = match val {
+        Cbor::Major2(_, byts) => byts,
+        _ => err_at!(InvalidData, msg: "bignum not a byte string")?,
+    };
+    let byts = match byts.iter().position(|b| *b != 0) {
+        Some(i) => &byts[i..],
+        None => &byts[byts.len()..],
+    };
+
+    let n = if byts.len() <= 8 {
+        let mut scratch = [0_u8; 8];
+        scratch[(8 - byts.len())..].copy_from_slice(byts);
+        let num = u64::from_be_bytes(scratch);
+        encode_hdr(major, num.into(), w)? + encode_addnl(num.into(), num, w)?
+    } else {
+        let num = match major {
+            0 => TagNum::UBigNum as u64,
+            _ => TagNum::SBigNum as u64,
+        };
+        let len = err_at!(OutOfRange, u64::try_from(byts.len()))?;
+        let mut n = encode_hdr(6, num.into(), w)? + encode_addnl(num.into(), num, w)?;
+        n += encode_hdr(2, len.into(), w)? + encode_addnl(len.into(), len, w)?;
+        write_w!(w, byts);
+        n + byts.len()
+    };
+
+    Ok(n)
+}
+
+// Copy one data item from `r` into `w` without decoding it into Cbor tree.
+pub(crate) fn copy_item<R, W>(r: &mut R, w: &mut W, depth: u32) -> Result<usize>
+where
+    R: io::Read,
+    W: io::Write,
+{
+    let (major, info, n) = decode_hdr(r)?;
+    encode_hdr(major, info, w)?;
+    Ok(n + copy_item_body(major, info, r, w, depth)?)
+}
+
+// Copy rest of the data item, whose header is already read, from `r` into `w`.
+pub(crate) fn copy_item_body<R, W>(
+    major: u8,
+    info: Info,
+    r: &mut R,
+    w: &mut W,
+    depth: u32,
+) -> Result<usize>
+where
+    R: io::Read,
+    W: io::Write,
+{
+    if depth > RECURSION_LIMIT {
+        return err_at!(LimitExceeded, msg: "copy recursion limit exceeded");
+    }
+
+    let n = match (major, info) {
+        (0, info) | (1, info) => copy_addnl(info, r, w)?.1,
+        (2, Info::Indefinite) | (3, Info::Indefinite) => {
+            let mut n = 0;
+            loop {
+                let (m, chunk_info, k) = decode_hdr(r)?;
+                encode_hdr(m, chunk_info, w)?;
+                n += k;
+                match (m, chunk_info) {
+                    (7, Info::Indefinite) => break n,
+                    (m, Info::Indefinite) if m == major => {
+                        err_at!(InvalidHeader, msg: "nested indefinite chunk")?
+                    }
+                    (m, chunk_info) if m == major => {
+                        n += copy_item_body(m, chunk_info, r, w, depth + 1)?
+                    }
+                    (m, _) => {
+                        err_at!(InvalidHeader, msg: "expected chunk of {} got {}", major, m)?
+                    }
+                }
+            }
+        }
+        (2, info) | (3, info) => {
+            let (len, n) = copy_addnl(info, r, w)?;
+            let m = err_at!(IOError, io::copy(&mut io::Read::take(&mut *r, len), w))?;
+            if m < len {
+                err_at!(UnexpectedEof, msg: "unexpected eof, need {} have {}", len, m)?
+            }
+            n + err_at!(OutOfRange, usize::try_from(len))?
+        }
+        (4, Info::Indefinite) | (5, Info::Indefinite) => {
+            let mut n = 0;
+            for i in 0.. {
+                let (m, item_info, k) = decode_hdr(r)?;
+                encode_hdr(m, item_info, w)?;
+                n += k;
+                match (m, item_info) {
+                    (7, Info::Indefinite) if major == 4 || i % 2 == 0 => break,
+                    (7, Info::Indefinite) => {
+                        err_at!(InvalidHeader, msg: "break after map key")?
+                    }
+                    (m, item_info) => n += copy_item_body(m, item_info, r, w, depth + 1)?,
+                }
+            }
+            n
+        }
+        (4, info) | (5, info) => {
+            let (len, mut n) = copy_addnl(info, r, w)?;
+            let len = if major == 5 { len.saturating_mul(2) } else { len };
+            for _ in 0..len {
+                n += copy_item(r, w, depth + 1)?;
+            }
+            n
+        }
+        (6, Info::Indefinite) => err_at!(InvalidHeader, msg: "indefinite tag")?,
+        (6, info) => copy_addnl(info, r, w)?.1 + copy_item(r, w, depth + 1)?,
+        // break is consumed by the enclosing indefinite length item.
+        (7, Info::Indefinite) => err_at!(InvalidHeader, msg: "unexpected break")?,
+        (7, info) => copy_addnl(info, r, w)?.1,
+        _ => unreachable!(),
     };
+
+    Ok(n)
+}
+
+// Sink that counts the bytes written into it.
+struct Counter(usize);
+
+impl io::Write for Counter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0 += buf.len();
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+// Copy the additional value following the header, using the same width.
+fn copy_addnl<R, W>(info: Info, r: &mut R, w: &mut W) -> Result<(u64, usize)>
+where
+    R: io::Read,
+    W: io::Write,
+{
+    let (num, n) = decode_addnl(info, r)?;
+    write_w!(w, &num.to_be_bytes()[(8 - n)..]);
     Ok((num, n))
 }
 
+// Read `len` bytes from `r`, buffer is grown as data arrives, so that a
+// bogus length cannot force a large allocation upfront.
+pub(crate) fn read_bytes<R>(r: &mut R, len: u64) -> Result<Vec<u8>>
+where
+    R: io::Read,
+{
+    use std::io::Read;
+
+    let mut data: Vec<u8> = Vec::default();
+    let n = err_at!(IOError, r.take(len).read_to_end(&mut data))?;
+    if err_at!(OutOfRange, u64::try_from(n))? < len {
+        err_at!(UnexpectedEof, msg: "unexpected eof, need {} have {}", len, n)?
+    }
+    Ok(data)
+}
+
 /// Major type 7, simple-value. Refer to Cbor [spec] for details.
 ///
 /// [spec]: https://tools.ietf.org/html/rfc7049
@@ -600,7 +2083,7 @@ pub enum SimpleValue {
     /// Reserved.
     Reserved24(u8), // 24, one-byte simple-value
     /// 16-bit floating point.
-    F16(u16), // 25, not-implemented
+    F16(u16), // 25, half-precision float, refer f16_to_f32
     /// 32-bit floating point.
     F32(f32), // 26, single-precision float
     /// 64-bit floating point.
@@ -612,6 +2095,7 @@ pub enum SimpleValue {
 #[cfg(any(feature = "arbitrary", test))]
 impl<'a> Arbitrary<'a> for SimpleValue {
     fn arbitrary(u: &mut Unstructured<'a>) -> arbitrary::Result<Self> {
+        let f2 = u.arbitrary::<u16>()?;
         let f4 = u.arbitrary::<f32>()?;
         let f8 = u.arbitrary::<f64>()?;
 
@@ -619,6 +2103,7 @@ impl<'a> Arbitrary<'a> for SimpleValue {
             SimpleValue::True,
             SimpleValue::False,
             SimpleValue::Null,
+            SimpleValue::F16(f2),
             SimpleValue::F32(f4),
             SimpleValue::F64(f8),
         ])?)
@@ -656,7 +2141,7 @@ impl SimpleValue {
             SimpleValue::Null => "Null".to_string(),
             SimpleValue::Undefined => "Undefined".to_string(),
             SimpleValue::Reserved24(val) => format!("Reserved24(0x{:x})", val),
-            SimpleValue::F16(val) => format!("F16({})", val),
+            SimpleValue::F16(val) => format!("F16({})", f16_to_f32(*val)),
             SimpleValue::F32(val) => format!("F32({})", val),
             SimpleValue::F64(val) => format!("F64({})", val),
             SimpleValue::Break => "Break".to_string(),
@@ -671,13 +2156,13 @@ impl IntoCbor for SimpleValue {
         use SimpleValue::*;
 
         let val = match self {
-            Unassigned => err_at!(FailConvert, msg: "simple-value-unassigned")?,
+            Unassigned => err_at!(InvalidHeader, msg: "simple-value-unassigned")?,
             val @ True => Cbor::Major7(Info::Tiny(20), val),
             val @ False => Cbor::Major7(Info::Tiny(21), val),
             val @ Null => Cbor::Major7(Info::Tiny(22), val),
-            Undefined => err_at!(FailConvert, msg: "simple-value-undefined")?,
-            Reserved24(_) => err_at!(FailConvert, msg: "simple-value-unassigned1")?,
-            F16(_) => err_at!(FailConvert, msg: "simple-value-f16")?,
+            Undefined => err_at!(InvalidHeader, msg: "simple-value-undefined")?,
+            Reserved24(_) => err_at!(InvalidHeader, msg: "simple-value-unassigned1")?,
+            val @ F16(_) => Cbor::Major7(Info::U16, val),
             val @ F32(_) => Cbor::Major7(Info::U32, val),
             val @ F64(_) => Cbor::Major7(Info::U64, val),
             val @ Break => Cbor::Major7(Info::Indefinite, val),
@@ -705,7 +2190,25 @@ impl SimpleValue {
         }
     }
 
-    fn encode<W>(sval: &SimpleValue, w: &mut W) -> Result<usize>
+    // Additional info for encoding this simple-value, None if it can't be encoded.
+    pub(crate) fn to_info(self) -> Option<Info> {
+        use SimpleValue::*;
+
+        match self {
+            True => Some(Info::Tiny(20)),
+            False => Some(Info::Tiny(21)),
+            Null => Some(Info::Tiny(22)),
+            Undefined => Some(Info::Tiny(23)),
+            Reserved24(_) => Some(Info::U8),
+            F16(_) => Some(Info::U16),
+            F32(_) => Some(Info::U32),
+            F64(_) => Some(Info::U64),
+            Break => Some(Info::Indefinite),
+            Unassigned => None,
+        }
+    }
+
+    pub(crate) fn encode<W>(sval: &SimpleValue, w: &mut W) -> Result<usize>
     where
         W: io::Write,
     {
@@ -735,7 +2238,7 @@ impl SimpleValue {
         Ok(n)
     }
 
-    fn decode<R>(info: Info, r: &mut R) -> Result<(SimpleValue, usize)>
+    pub(crate) fn decode<R>(info: Info, r: &mut R) -> Result<(SimpleValue, usize)>
     where
         R: io::Read,
     {
@@ -744,10 +2247,14 @@ impl SimpleValue {
             Info::Tiny(20) => (SimpleValue::True, 0),
             Info::Tiny(21) => (SimpleValue::False, 0),
             Info::Tiny(22) => (SimpleValue::Null, 0),
-            Info::Tiny(23) => err_at!(FailCbor, msg: "simple-value-undefined")?,
-            Info::Tiny(_) => err_at!(FailCbor, msg: "simple-value-unassigned")?,
-            Info::U8 => err_at!(FailCbor, msg: "simple-value-unassigned1")?,
-            Info::U16 => err_at!(FailCbor, msg: "simple-value-f16")?,
+            Info::Tiny(23) => err_at!(InvalidHeader, msg: "simple-value-undefined")?,
+            Info::Tiny(_) => err_at!(InvalidHeader, msg: "simple-value-unassigned")?,
+            Info::U8 => err_at!(InvalidHeader, msg: "simple-value-unassigned1")?,
+            Info::U16 => {
+                read_r!(r, &mut scratch[..2]);
+                let val = u16::from_be_bytes(scratch[..2].try_into().unwrap());
+                (SimpleValue::F16(val), 2)
+            }
             Info::U32 => {
                 read_r!(r, &mut scratch[..4]);
                 let val = f32::from_be_bytes(scratch[..4].try_into().unwrap());
@@ -758,9 +2265,9 @@ impl SimpleValue {
                 let val = f64::from_be_bytes(scratch[..8].try_into().unwrap());
                 (SimpleValue::F64(val), 8)
             }
-            Info::Reserved28 => err_at!(FailCbor, msg: "simple-value-reserved")?,
-            Info::Reserved29 => err_at!(FailCbor, msg: "simple-value-reserved")?,
-            Info::Reserved30 => err_at!(FailCbor, msg: "simple-value-reserved")?,
+            Info::Reserved28 => err_at!(InvalidHeader, msg: "simple-value-reserved")?,
+            Info::Reserved29 => err_at!(InvalidHeader, msg: "simple-value-reserved")?,
+            Info::Reserved30 => err_at!(InvalidHeader, msg: "simple-value-reserved")?,
             Info::Indefinite => (SimpleValue::Break, 0),
         };
         Ok((val, n))
@@ -768,19 +2275,48 @@ impl SimpleValue {
 }
 
 #[derive(Copy, Clone)]
-enum TagNum {
+pub(crate) enum TagNum {
+    DateTime = 0,
+    Epoch = 1,
     UBigNum = 2,
     SBigNum = 3,
+    Decimal = 4,
+    BigFloat = 5,
+    StringRef = 25,
+    Shareable = 28,
+    SharedRef = 29,
+    Rational = 30,
     Identifier = 39,
+    NdArray = 40,
+    TypedArray = 64, // 64..=87, except 76
+    StringRefNamespace = 256,
+    Set = 258,
+    ExtTime = 1001,
+    Duration = 1002,
     Any = 65535, // always invalid
 }
 
 impl From<u64> for TagNum {
     fn from(num: u64) -> TagNum {
         match num {
+            0 => TagNum::DateTime,
+            1 => TagNum::Epoch,
             2 => TagNum::UBigNum,
             3 => TagNum::SBigNum,
+            4 => TagNum::Decimal,
+            5 => TagNum::BigFloat,
+            25 => TagNum::StringRef,
+            28 => TagNum::Shareable,
+            29 => TagNum::SharedRef,
+            30 => TagNum::Rational,
             39 => TagNum::Identifier,
+            40 => TagNum::NdArray,
+            76 => TagNum::Any, // reserved
+            64..=87 => TagNum::TypedArray,
+            256 => TagNum::StringRefNamespace,
+            258 => TagNum::Set,
+            1001 => TagNum::ExtTime,
+            1002 => TagNum::Duration,
             _ => TagNum::Any,
         }
     }
@@ -793,22 +2329,79 @@ impl From<u64> for TagNum {
 pub enum Tag {
     /// Tag 2, arbitrarily sized positive integers, byte-string in network byte order.
     UBigNum(Box<Cbor>),
-    /// Tag 3, arbitrarily sized signed integers, byte-string in network byte order.
+    /// Tag 3, arbitrarily sized negative integers, byte-string in network byte
+    /// order holding `n`, for the value `-1 - n`.
     SBigNum(Box<Cbor>),
     /// Tag 39, used as identifier marker. This implementation shall
     /// treat them as literal values. Used by `Cborize` procedural
     /// macro to match values with types.
     Identifier(Box<Cbor>),
-    /// Catch all tag-value, follows the generic Tag specification
-    /// for Cbor.
-    Value(u64),
+    /// Tag 0, date-time as RFC 3339 text-string, like `2013-03-21T20:04:00Z`.
+    DateTime(Box<Cbor>),
+    /// Tag 1, seconds relative to UNIX epoch, as integer or floating point.
+    Epoch(Box<Cbor>),
+    /// Tag 1001, extended time as per RFC 9581. A map with key 1 for
+    /// seconds relative to UNIX epoch and, optionally, key -3, -6 or -9
+    /// for milli, micro or nano seconds.
+    ExtTime(Box<Cbor>),
+    /// Tag 1002, duration as per RFC 9581. A map with keys similar
+    /// to [Tag::ExtTime].
+    Duration(Box<Cbor>),
+    /// Tag 4, decimal fraction, an array of exponent and mantissa,
+    /// refer to [Decimal][crate::Decimal].
+    Decimal(Box<Cbor>),
+    /// Tag 5, bigfloat, an array of exponent and mantissa,
+    /// refer to [BigFloat][crate::BigFloat].
+    BigFloat(Box<Cbor>),
+    /// Tag 30, rational number, an array of numerator and denominator,
+    /// refer to [Rational][crate::Rational].
+    Rational(Box<Cbor>),
+    /// Tag 64 to 87, except 76, typed array as per RFC 8746. Tag-number
+    /// encodes element type and endianness, the item is a byte-string,
+    /// refer to [TypedArray][crate::TypedArray].
+    TypedArray(u64, Box<Cbor>),
+    /// Tag 40, multi-dimensional array in row-major order. An array of
+    /// dimensions and elements, refer to [NdArray][crate::NdArray].
+    NdArray(Box<Cbor>),
+    /// Tag 25, reference to a previously seen string, the item is the
+    /// index of the string within the enclosing [Tag::StringRefNamespace].
+    StringRef(Box<Cbor>),
+    /// Tag 256, namespace for [Tag::StringRef] references.
+    StringRefNamespace(Box<Cbor>),
+    /// Tag 28, marks the item as shareable, so that it can be referred to
+    /// later using [Tag::SharedRef].
+    Shareable(Box<Cbor>),
+    /// Tag 29, reference to a previously seen [Tag::Shareable] item, the
+    /// item is the index of the shareable item in order of appearance.
+    SharedRef(Box<Cbor>),
+    /// Tag 258, array of unique items, refer `IntoCbor` for `HashSet` and
+    /// `BTreeSet`. Duplicate items are rejected by [DecodeOptions::strict].
+    Set(Box<Cbor>),
+    /// Catch all tag, tag-number and the tagged item. The item is decoded
+    /// and encoded as is, interpreting it is left to the application.
+    Other(u64, Box<Cbor>),
 }
 
 #[cfg(any(feature = "arbitrary", test))]
 impl<'a> Arbitrary<'a> for Tag {
     fn arbitrary(u: &mut Unstructured<'a>) -> arbitrary::Result<Self> {
         let tag = *u
-            .choose(&[TagNum::UBigNum, TagNum::SBigNum, TagNum::Identifier, TagNum::Any])
+            .choose(&[
+                TagNum::DateTime,
+                TagNum::Epoch,
+                TagNum::UBigNum,
+                TagNum::SBigNum,
+                TagNum::Decimal,
+                TagNum::BigFloat,
+                TagNum::Rational,
+                TagNum::Identifier,
+                TagNum::NdArray,
+                TagNum::TypedArray,
+                TagNum::Set,
+                TagNum::ExtTime,
+                TagNum::Duration,
+                TagNum::Any,
+            ])
             .unwrap();
         match tag {
             TagNum::UBigNum | TagNum::SBigNum => {
@@ -820,13 +2413,17 @@ impl<'a> Arbitrary<'a> for Tag {
                     Sign::Minus => Ok(Tag::SBigNum(val)),
                 }
             }
-            TagNum::Identifier => {
+            TagNum::Any => {
+                let num = match u.arbitrary::<u64>()? {
+                    num if matches!(TagNum::from(num), TagNum::Any) => num,
+                    _ => TagNum::Any as u64,
+                };
                 let val: Cbor = u.arbitrary()?;
-                Ok(Tag::Identifier(Box::new(val)))
+                Ok(Tag::Other(num, Box::new(val)))
             }
-            TagNum::Any => {
-                let num: u64 = u.arbitrary()?;
-                Ok(Tag::Value(num))
+            tag => {
+                let val: Cbor = u.arbitrary()?;
+                Ok(Tag::from_value(tag as u64, val))
             }
         }
     }
@@ -840,9 +2437,39 @@ impl From<Tag> for Cbor {
 }
 
 impl Tag {
-    /// Construct a Tag value from u64 type.
-    pub fn from_value(value: u64) -> Tag {
-        Tag::Value(value)
+    /// Construct a Tag from tag-number and the tagged item. Tag-numbers
+    /// known to this implementation are mapped to their variants, others
+    /// are wrapped as [Tag::Other].
+    pub fn from_value(num: u64, value: Cbor) -> Tag {
+        let value = Box::new(value);
+        match TagNum::from(num) {
+            TagNum::UBigNum => Tag::UBigNum(value),
+            TagNum::SBigNum => Tag::SBigNum(value),
+            TagNum::Identifier => Tag::Identifier(value),
+            TagNum::DateTime => Tag::DateTime(value),
+            TagNum::Epoch => Tag::Epoch(value),
+            TagNum::ExtTime => Tag::ExtTime(value),
+            TagNum::Duration => Tag::Duration(value),
+            TagNum::Decimal => Tag::Decimal(value),
+            TagNum::BigFloat => Tag::BigFloat(value),
+            TagNum::Rational => Tag::Rational(value),
+            TagNum::TypedArray => Tag::TypedArray(num, value),
+            TagNum::NdArray => Tag::NdArray(value),
+            TagNum::StringRef => Tag::StringRef(value),
+            TagNum::StringRefNamespace => Tag::StringRefNamespace(value),
+            TagNum::Shareable => Tag::Shareable(value),
+            TagNum::SharedRef => Tag::SharedRef(value),
+            TagNum::Set => Tag::Set(value),
+            TagNum::Any => Tag::Other(num, value),
+        }
+    }
+
+    /// Wrap `t` with DateTime tag, as RFC 3339 text-string in UTC.
+    /// Note that [IntoCbor] for `SystemTime` uses the more compact
+    /// [Tag::Epoch] or [Tag::ExtTime] encoding.
+    pub fn from_system_time(t: std::time::SystemTime) -> Result<Tag> {
+        let val = crate::time::to_rfc3339(t)?.into_cbor()?;
+        Ok(Tag::DateTime(Box::new(val)))
     }
 
     /// Wrap value with Identifier tag.
@@ -856,47 +2483,136 @@ impl Tag {
             Tag::UBigNum(_) => TagNum::UBigNum as u64,
             Tag::SBigNum(_) => TagNum::SBigNum as u64,
             Tag::Identifier(_) => TagNum::Identifier as u64,
-            Tag::Value(val) => *val,
+            Tag::DateTime(_) => TagNum::DateTime as u64,
+            Tag::Epoch(_) => TagNum::Epoch as u64,
+            Tag::ExtTime(_) => TagNum::ExtTime as u64,
+            Tag::Duration(_) => TagNum::Duration as u64,
+            Tag::Decimal(_) => TagNum::Decimal as u64,
+            Tag::BigFloat(_) => TagNum::BigFloat as u64,
+            Tag::Rational(_) => TagNum::Rational as u64,
+            Tag::TypedArray(num, _) => *num,
+            Tag::NdArray(_) => TagNum::NdArray as u64,
+            Tag::StringRef(_) => TagNum::StringRef as u64,
+            Tag::StringRefNamespace(_) => TagNum::StringRefNamespace as u64,
+            Tag::Shareable(_) => TagNum::Shareable as u64,
+            Tag::SharedRef(_) => TagNum::SharedRef as u64,
+            Tag::Set(_) => TagNum::Set as u64,
+            Tag::Other(num, _) => *num,
+        }
+    }
+
+    /// Return the value of [Tag::UBigNum] and [Tag::SBigNum], as per
+    /// RFC-8949 tag 3 holds `-1 - n`.
+    pub(crate) fn to_bignum(&self) -> Option<BigInt> {
+        match self {
+            Tag::UBigNum(val) => match val.as_ref() {
+                Cbor::Major2(_, byts) => Some(BigInt::from_bytes_be(Sign::Plus, byts)),
+                _ => None,
+            },
+            Tag::SBigNum(val) => match val.as_ref() {
+                Cbor::Major2(_, byts) => {
+                    Some(-BigInt::from_bytes_be(Sign::Plus, byts) - 1_u32)
+                }
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// Return the tagged item.
+    pub(crate) fn as_item(&self) -> &Cbor {
+        match self {
+            Tag::UBigNum(val) => val,
+            Tag::SBigNum(val) => val,
+            Tag::Identifier(val) => val,
+            Tag::DateTime(val) => val,
+            Tag::Epoch(val) => val,
+            Tag::ExtTime(val) => val,
+            Tag::Duration(val) => val,
+            Tag::Decimal(val) => val,
+            Tag::BigFloat(val) => val,
+            Tag::Rational(val) => val,
+            Tag::TypedArray(_, val) => val,
+            Tag::NdArray(val) => val,
+            Tag::StringRef(val) => val,
+            Tag::StringRefNamespace(val) => val,
+            Tag::Shareable(val) => val,
+            Tag::SharedRef(val) => val,
+            Tag::Set(val) => val,
+            Tag::Other(_, val) => val,
+        }
+    }
+
+    /// Return the tagged item, for in-place updates.
+    pub(crate) fn as_item_mut(&mut self) -> &mut Cbor {
+        match self {
+            Tag::UBigNum(val) => val,
+            Tag::SBigNum(val) => val,
+            Tag::Identifier(val) => val,
+            Tag::DateTime(val) => val,
+            Tag::Epoch(val) => val,
+            Tag::ExtTime(val) => val,
+            Tag::Duration(val) => val,
+            Tag::Decimal(val) => val,
+            Tag::BigFloat(val) => val,
+            Tag::Rational(val) => val,
+            Tag::TypedArray(_, val) => val,
+            Tag::NdArray(val) => val,
+            Tag::StringRef(val) => val,
+            Tag::StringRefNamespace(val) => val,
+            Tag::Shareable(val) => val,
+            Tag::SharedRef(val) => val,
+            Tag::Set(val) => val,
+            Tag::Other(_, val) => val,
         }
     }
 
-    fn encode<W>(tag: &Tag, w: &mut W) -> Result<usize>
+    fn encode<W>(info: Info, tag: &Tag, w: &mut W, depth: u32) -> Result<usize>
     where
         W: io::Write,
     {
         let num = tag.to_tag_value();
-        let mut n = encode_addnl(num, w)?;
-        n += match tag {
-            Tag::UBigNum(val) => val.encode(w)?,
-            Tag::SBigNum(val) => val.encode(w)?,
-            Tag::Identifier(val) => val.encode(w)?,
-            Tag::Value(_) => 0,
-        };
-
-        Ok(n)
+        let n = encode_addnl(info, num, w)?;
+        Ok(n + tag.as_item().do_encode(w, depth + 1)?)
     }
 
-    fn decode<R>(info: Info, r: &mut R) -> Result<(Tag, usize)>
+    // tagged item is decoded at `depth + 1`, sharing the limits and
+    // byte count in `ctx` with the enclosing item.
+    fn decode<R>(
+        info: Info,
+        r: &mut R,
+        depth: u32,
+        ctx: &mut DecodeCtx,
+    ) -> Result<(Tag, usize)>
     where
         R: io::Read,
     {
-        let (tag, n) = decode_addnl(info, r)?;
-        let (tag, m) = match TagNum::from(tag) {
-            TagNum::UBigNum => {
-                let (val, m) = Cbor::decode(r)?;
-                (Tag::UBigNum(Box::new(val)), m)
-            }
-            TagNum::SBigNum => {
-                let (val, m) = Cbor::decode(r)?;
-                (Tag::SBigNum(Box::new(val)), m)
+        if let Info::Indefinite = info {
+            err_at!(InvalidHeader, msg: "indefinite tag")?
+        }
+        let (num, n) = decode_addnl(info, r)?;
+        ctx.consume(n)?;
+        let (val, m) = match TagNum::from(num) {
+            TagNum::StringRefNamespace if ctx.opts.resolve_refs => {
+                ctx.strings.push(Vec::default());
+                let res = Cbor::do_decode(r, depth + 1, ctx);
+                ctx.strings.pop();
+                res?
             }
-            TagNum::Identifier => {
-                let (val, m) = Cbor::decode(r)?;
-                (Tag::Identifier(Box::new(val)), m)
+            TagNum::Shareable if ctx.opts.resolve_refs => {
+                // numbered before decoding the item, nested shareable items
+                // follow their enclosing item. Item is kept in ctx and
+                // replaced by its index, refer DecodeCtx::resolve.
+                let (index, n_bytes) = (ctx.shared.len(), ctx.n_bytes);
+                ctx.shared.push(SharedItem::default());
+                let (val, m) = Cbor::do_decode(r, depth + 1, ctx)?;
+                ctx.shared[index].val = Some(val);
+                ctx.shared[index].size = ctx.n_bytes - n_bytes;
+                ((index as u64).into_cbor()?, m)
             }
-            _ => (Tag::Value(tag as u64), 0),
+            _ => Cbor::do_decode(r, depth + 1, ctx)?,
         };
-        Ok((tag, m + n))
+        Ok((Tag::from_value(num, val), m + n))
     }
 
     fn pretty_print(&self, p: &str) -> Result<String> {
@@ -906,16 +2622,34 @@ impl Tag {
                 format!("Tag::UBigNum(0x{:x})", val)
             }
             Tag::SBigNum(val) => {
-                let val = BigInt::from_bytes_be(Sign::Minus, &val.clone().into_bytes()?);
-                format!("Tag::SBigNum(0x{:x})", val)
+                let val = BigInt::from_bytes_be(Sign::Plus, &val.clone().into_bytes()?);
+                format!("Tag::SBigNum(-1-0x{:x})", val)
             }
-            Tag::Identifier(val) => {
-                let mut ss = vec!["Tag::Identifier".to_string()];
+            tag => {
+                let name = match tag {
+                    Tag::Identifier(_) => "Tag::Identifier".to_string(),
+                    Tag::DateTime(_) => "Tag::DateTime".to_string(),
+                    Tag::Epoch(_) => "Tag::Epoch".to_string(),
+                    Tag::ExtTime(_) => "Tag::ExtTime".to_string(),
+                    Tag::Duration(_) => "Tag::Duration".to_string(),
+                    Tag::Decimal(_) => "Tag::Decimal".to_string(),
+                    Tag::BigFloat(_) => "Tag::BigFloat".to_string(),
+                    Tag::Rational(_) => "Tag::Rational".to_string(),
+                    Tag::TypedArray(num, _) => format!("Tag::TypedArray({})", num),
+                    Tag::NdArray(_) => "Tag::NdArray".to_string(),
+                    Tag::StringRef(_) => "Tag::StringRef".to_string(),
+                    Tag::StringRefNamespace(_) => "Tag::StringRefNamespace".to_string(),
+                    Tag::Shareable(_) => "Tag::Shareable".to_string(),
+                    Tag::SharedRef(_) => "Tag::SharedRef".to_string(),
+                    Tag::Set(_) => "Tag::Set".to_string(),
+                    Tag::Other(num, _) => format!("Tag::Other(0x{:x})", num),
+                    Tag::UBigNum(_) | Tag::SBigNum(_) => unreachable!(),
+                };
+                let mut ss = vec![name];
                 let p = p.to_owned() + "  ";
-                ss.push(val.pretty_print(&p)?);
+                ss.push(tag.as_item().pretty_print(&p)?);
                 ss.join("\n")
             }
-            Tag::Value(val) => format!("Tag::Value(0x{:x})", val),
         };
 
         Ok(s)
@@ -932,6 +2666,11 @@ pub enum Key {
     F64(f64),
     Bytes(Vec<u8>),
     Text(String),
+    /// Any other data item, like arrays, maps, tagged values, null,
+    /// negative integers beyond i64 range and half-precision floats, which
+    /// keep their width. Shall not be used for items that can be represented
+    /// by other variants.
+    Cbor(Box<Cbor>),
 }
 
 #[cfg(any(feature = "arbitrary", test))]
@@ -961,6 +2700,8 @@ impl Key {
     /// * Key::F64, sort after 32-bit floating point numbers.
     /// * Key::Bytes, sort after 64-bit floating point numbers.
     /// * Key::Text, sort after bytes.
+    /// * Key::Cbor, sort after text, ordered and compared by their
+    ///   deterministic encoding, refer [EncodeOptions::deterministic].
     ///
     /// [spec]: https://tools.ietf.org/html/rfc7049
     pub fn to_type_order(&self) -> usize {
@@ -974,6 +2715,7 @@ impl Key {
             F64(_) => 16,
             Bytes(_) => 20,
             Text(_) => 24,
+            Cbor(_) => 28,
         }
     }
 
@@ -986,6 +2728,7 @@ impl Key {
             Key::F64(val) => format!("Key(D:{})", val),
             Key::Bytes(val) => format!("Key(B:{:?})", val),
             Key::Text(val) => format!("Key(T:{:?})", val),
+            Key::Cbor(val) => format!("Key(C:{:?})", canonical_bytes(val)?),
         };
 
         Ok(s)
@@ -1006,6 +2749,7 @@ impl PartialEq for Key {
             (F64(a), F64(b)) => a.total_cmp_stub(b) == cmp::Ordering::Equal,
             (Bytes(a), Bytes(b)) => a == b,
             (Text(a), Text(b)) => a == b,
+            (Cbor(a), Cbor(b)) => a == b || cmp_items(a, b) == cmp::Ordering::Equal,
             (_, _) => false,
         }
     }
@@ -1027,6 +2771,7 @@ impl Ord for Key {
                 (Text(a), Text(b)) => a.cmp(b),
                 (F32(a), F32(b)) => a.total_cmp_stub(b),
                 (F64(a), F64(b)) => a.total_cmp_stub(b),
+                (Cbor(a), Cbor(b)) => cmp_items(a, b),
                 (_, _) => unreachable!(),
             }
         } else {
@@ -1041,6 +2786,87 @@ impl PartialOrd for Key {
     }
 }
 
+// Return the tagged item, skipping nested tags, or `val` if not tagged.
+pub(crate) fn untag(mut val: &Cbor) -> &Cbor {
+    while let Cbor::Major6(_, tag) = val {
+        val = tag.as_item()
+    }
+    val
+}
+
+pub(crate) fn untag_mut(val: &mut Cbor) -> &mut Cbor {
+    match val {
+        Cbor::Major6(_, tag) => untag_mut(tag.as_item_mut()),
+        val => val,
+    }
+}
+
+// Items that fail to encode, sort before others and are compared among
+// themselves by their structure.
+pub(crate) fn cmp_items(a: &Cbor, b: &Cbor) -> cmp::Ordering {
+    match (canonical_bytes(a), canonical_bytes(b)) {
+        (Ok(x), Ok(y)) => x.cmp(&y),
+        (Ok(_), Err(_)) => cmp::Ordering::Greater,
+        (Err(_), Ok(_)) => cmp::Ordering::Less,
+        (Err(_), Err(_)) => cmp_structure(a, b),
+    }
+}
+
+// Compare variant, followed by value, ignoring Info. Nested items are
+// compared using cmp_items.
+fn cmp_structure(a: &Cbor, b: &Cbor) -> cmp::Ordering {
+    use Cbor::*;
+
+    let order = |val: &Cbor| match val {
+        Major0(_, _) => 0,
+        Major1(_, _) => 1,
+        Major2(_, _) => 2,
+        Major3(_, _) => 3,
+        Major4(_, _) => 4,
+        Major5(_, _) => 5,
+        Major6(_, _) => 6,
+        Major7(_, _) => 7,
+        Binary(_) => 8,
+    };
+
+    match (a, b) {
+        (Major0(_, a), Major0(_, b)) => a.cmp(b),
+        (Major1(_, a), Major1(_, b)) => a.cmp(b),
+        (Major2(_, a), Major2(_, b)) => a.cmp(b),
+        (Major3(_, a), Major3(_, b)) => a.cmp(b),
+        (Major4(_, x), Major4(_, y)) => {
+            let mut ords = x.iter().zip(y.iter()).map(|(a, b)| cmp_items(a, b));
+            let ord = ords.find(|ord| ord.is_ne());
+            ord.unwrap_or_else(|| x.len().cmp(&y.len()))
+        }
+        (Major5(_, x), Major5(_, y)) => {
+            let mut ords = x
+                .iter()
+                .zip(y.iter())
+                .map(|((k, a), (l, b))| k.cmp(l).then_with(|| cmp_items(a, b)));
+            let ord = ords.find(|ord| ord.is_ne());
+            ord.unwrap_or_else(|| x.len().cmp(&y.len()))
+        }
+        (Major6(_, a), Major6(_, b)) => {
+            let ord = a.to_tag_value().cmp(&b.to_tag_value());
+            ord.then_with(|| cmp_items(a.as_item(), b.as_item()))
+        }
+        (Major7(_, a), Major7(_, b)) => {
+            use SimpleValue::*;
+
+            match (a, b) {
+                (Reserved24(a), Reserved24(b)) => a.cmp(b),
+                (F16(a), F16(b)) => a.cmp(b),
+                (F32(a), F32(b)) => a.total_cmp_stub(b),
+                (F64(a), F64(b)) => a.total_cmp_stub(b),
+                (a, b) => a.to_type_order().cmp(&b.to_type_order()),
+            }
+        }
+        (Binary(a), Binary(b)) => a.cmp(b),
+        (a, b) => order(a).cmp(&order(b)),
+    }
+}
+
 /// Return pretty formated string representing `val`.
 ///
 /// Can be printed on terminal or log-file for eye-ball verification.
@@ -1074,6 +2900,61 @@ impl TotalCmpStub for f64 {
     }
 }
 
+/// Convert half-precision float, in IEEE 754 binary16 format, to f32.
+/// Conversion is always lossless.
+pub fn f16_to_f32(half: u16) -> f32 {
+    let sign = u32::from(half & 0x8000) << 16;
+    let exp = u32::from((half >> 10) & 0x1f);
+    let mant = u32::from(half & 0x3ff);
+    match exp {
+        0 => {
+            // zero and subnormals, mant * 2^-24 is exact in f32.
+            let val = (mant as f32) * f32::from_bits(0x3380_0000);
+            f32::from_bits(sign | val.to_bits())
+        }
+        0x1f => f32::from_bits(sign | 0x7f80_0000 | (mant << 13)),
+        exp => f32::from_bits(sign | ((exp + 112) << 23) | (mant << 13)),
+    }
+}
+
+/// Convert f32 to half-precision float, in IEEE 754 binary16 format.
+/// Return None if `val` can't be represented as half-precision float
+/// without loss, including the NaN payload.
+pub fn f32_to_f16(val: f32) -> Option<u16> {
+    let bits = val.to_bits();
+    let sign = ((bits >> 16) & 0x8000) as u16;
+    let exp = ((bits >> 23) & 0xff) as i32;
+    let mant = bits & 0x7f_ffff;
+
+    let half = match exp - 127 {
+        128 => sign | 0x7c00 | ((mant >> 13) as u16), // infinity and NaN
+        -127 if mant == 0 => sign,                    // zero
+        e @ -14..=15 => sign | (((e + 15) as u16) << 10) | ((mant >> 13) as u16),
+        -24..=-15 => sign | (((0x80_0000 | mant) >> (126 - exp)) as u16),
+        _ => return None,
+    };
+    match f16_to_f32(half).to_bits() == bits {
+        true => Some(half),
+        false => None,
+    }
+}
+
+// Shortest floating point representation of `val` without loss, NaN is
+// always represented as half-precision quiet NaN.
+fn shortest_float(val: f64) -> SimpleValue {
+    if val.is_nan() {
+        return SimpleValue::F16(0x7e00);
+    }
+    let single = val as f32;
+    if f64::from(single).to_bits() != val.to_bits() {
+        return SimpleValue::F64(val);
+    }
+    match f32_to_f16(single) {
+        Some(half) => SimpleValue::F16(half),
+        None => SimpleValue::F32(single),
+    }
+}
+
 #[cfg(test)]
 #[path = "cbor_test.rs"]
 mod cbor_test;
@@ -0,0 +1,196 @@
+use std::borrow::Cow;
+use std::convert::TryFrom;
+
+use crate::cbor::{decode_addnl, decode_hdr};
+use crate::{
+    Cbor, Error, FromCbor, Info, Key, Result, SimpleValue, Tag, RECURSION_LIMIT,
+};
+
+/// Borrowed view of a Cbor value, decoded from a byte slice.
+///
+/// Unlike [Cbor::decode], byte-strings and text-strings are not copied,
+/// instead they refer to the input buffer. Only indefinite-length strings,
+/// whose chunks are not contiguous in the input, are assembled into owned
+/// buffers. Use [CborRef::to_cbor] to convert the view into [Cbor] value.
+#[derive(Debug, Clone, PartialEq)]
+pub enum CborRef<'a> {
+    Major0(Info, u64),                             // uint 0-23,24,25,26,27
+    Major1(Info, u64),                             // nint 0-23,24,25,26,27
+    Major2(Info, Cow<'a, [u8]>),                   // byts 0-23,24,25,26,27,31
+    Major3(Info, Cow<'a, str>),                    // text 0-23,24,25,26,27,31
+    Major4(Info, Vec<CborRef<'a>>),                // list 0-23,24,25,26,27,31
+    Major5(Info, Vec<(CborRef<'a>, CborRef<'a>)>), // dict 0-23,24,25,26,27,31
+    Major6(Info, u64, Box<CborRef<'a>>),           // tag-number and tagged item
+    Major7(Info, SimpleValue),                     // type refer SimpleValue
+}
+
+impl<'a> CborRef<'a> {
+    /// Deserialize bytes from `buf` into a borrowed Cbor value, return the
+    /// value and number of bytes read to construct the value.
+    pub fn decode(buf: &'a [u8]) -> Result<(CborRef<'a>, usize)> {
+        let mut data = buf;
+        let val = CborRef::do_decode(&mut data, 1)
+            .map_err(|err| err.with_offset(buf.len() - data.len()).with_backtrace())?;
+        Ok((val, buf.len() - data.len()))
+    }
+
+    fn do_decode(data: &mut &'a [u8], depth: u32) -> Result<CborRef<'a>> {
+        match CborRef::do_decode_or_break(data, depth)? {
+            CborRef::Major7(_, SimpleValue::Break) => {
+                err_at!(InvalidHeader, msg: "unexpected break")
+            }
+            val => Ok(val),
+        }
+    }
+
+    // break is returned as a value, only to end indefinite array and map.
+    fn do_decode_or_break(data: &mut &'a [u8], depth: u32) -> Result<CborRef<'a>> {
+        use std::str::from_utf8;
+
+        if depth > RECURSION_LIMIT {
+            return err_at!(LimitExceeded, msg: "decode recursion limt exceeded");
+        }
+
+        let (major, info, _) = decode_hdr(data)?;
+
+        let val = match (major, info) {
+            (0, info) => CborRef::Major0(info, decode_addnl(info, data)?.0),
+            (1, info) => CborRef::Major1(info, decode_addnl(info, data)?.0),
+            (2, Info::Indefinite) => {
+                let mut byts: Vec<u8> = Vec::default();
+                while let Some(chunk) = decode_chunk(2, data)? {
+                    byts.extend_from_slice(chunk);
+                }
+                CborRef::Major2(info, Cow::Owned(byts))
+            }
+            (2, info) => {
+                let (len, _) = decode_addnl(info, data)?;
+                CborRef::Major2(info, Cow::Borrowed(read_slice(data, len)?))
+            }
+            (3, Info::Indefinite) => {
+                let mut text = String::default();
+                while let Some(chunk) = decode_chunk(3, data)? {
+                    text.push_str(err_at!(Utf8, from_utf8(chunk))?);
+                }
+                CborRef::Major3(info, Cow::Owned(text))
+            }
+            (3, info) => {
+                let (len, _) = decode_addnl(info, data)?;
+                let text = err_at!(Utf8, from_utf8(read_slice(data, len)?))?;
+                CborRef::Major3(info, Cow::Borrowed(text))
+            }
+            (4, Info::Indefinite) => {
+                let mut list: Vec<CborRef> = vec![];
+                loop {
+                    match CborRef::do_decode_or_break(data, depth + 1)? {
+                        CborRef::Major7(_, SimpleValue::Break) => break,
+                        item => list.push(item),
+                    }
+                }
+                CborRef::Major4(info, list)
+            }
+            (4, info) => {
+                let mut list: Vec<CborRef> = vec![];
+                let (len, _) = decode_addnl(info, data)?;
+                for _ in 0..len {
+                    list.push(CborRef::do_decode(data, depth + 1)?);
+                }
+                CborRef::Major4(info, list)
+            }
+            (5, Info::Indefinite) => {
+                let mut map: Vec<(CborRef, CborRef)> = Vec::default();
+                loop {
+                    let key = match CborRef::do_decode_or_break(data, depth + 1)? {
+                        CborRef::Major7(_, SimpleValue::Break) => break,
+                        key => key,
+                    };
+                    map.push((key, CborRef::do_decode(data, depth + 1)?));
+                }
+                CborRef::Major5(info, map)
+            }
+            (5, info) => {
+                let mut map: Vec<(CborRef, CborRef)> = Vec::default();
+                let (len, _) = decode_addnl(info, data)?;
+                for _ in 0..len {
+                    let key = CborRef::do_decode(data, depth + 1)?;
+                    map.push((key, CborRef::do_decode(data, depth + 1)?));
+                }
+                CborRef::Major5(info, map)
+            }
+            (6, info) => {
+                let (num, _) = decode_addnl(info, data)?;
+                let item = Box::new(CborRef::do_decode(data, depth + 1)?);
+                CborRef::Major6(info, num, item)
+            }
+            (7, info) => {
+                let (sval, _) = SimpleValue::decode(info, data)?;
+                CborRef::Major7(info, sval)
+            }
+            _ => unreachable!(),
+        };
+
+        Ok(val)
+    }
+
+    /// Convert the borrowed view into [Cbor] value, copying strings out
+    /// of the input buffer.
+    pub fn to_cbor(&self) -> Result<Cbor> {
+        let val = match self {
+            CborRef::Major0(info, val) => Cbor::Major0(*info, *val),
+            CborRef::Major1(info, val) => Cbor::Major1(*info, *val),
+            CborRef::Major2(info, val) => Cbor::Major2(*info, val.to_vec()),
+            CborRef::Major3(info, val) => Cbor::Major3(*info, val.as_bytes().to_vec()),
+            CborRef::Major4(info, list) => {
+                let mut items = Vec::with_capacity(list.len());
+                for item in list.iter() {
+                    items.push(item.to_cbor()?);
+                }
+                Cbor::Major4(*info, items)
+            }
+            CborRef::Major5(info, map) => {
+                let mut items = Vec::with_capacity(map.len());
+                for (key, val) in map.iter() {
+                    items.push((Key::from_cbor(key.to_cbor()?)?, val.to_cbor()?));
+                }
+                Cbor::Major5(*info, items)
+            }
+            CborRef::Major6(info, num, item) => {
+                Cbor::Major6(*info, Tag::from_value(*num, item.to_cbor()?))
+            }
+            CborRef::Major7(info, sval) => Cbor::Major7(*info, *sval),
+        };
+
+        Ok(val)
+    }
+}
+
+fn read_slice<'a>(data: &mut &'a [u8], len: u64) -> Result<&'a [u8]> {
+    let len = err_at!(OutOfRange, usize::try_from(len))?;
+    if len > data.len() {
+        err_at!(UnexpectedEof, msg: "unexpected eof, need {} have {}", len, data.len())?
+    }
+    let (item, rest) = data.split_at(len);
+    *data = rest;
+    Ok(item)
+}
+
+// Return the next chunk of an indefinite string of `major` type, None on break.
+fn decode_chunk<'a>(major: u8, data: &mut &'a [u8]) -> Result<Option<&'a [u8]>> {
+    match decode_hdr(data)? {
+        (7, Info::Indefinite, _) => Ok(None),
+        (m, Info::Indefinite, _) if m == major => {
+            err_at!(InvalidHeader, msg: "nested indefinite chunk")
+        }
+        (m, info, _) if m == major => {
+            let (len, _) = decode_addnl(info, data)?;
+            Ok(Some(read_slice(data, len)?))
+        }
+        (m, _, _) => {
+            err_at!(InvalidHeader, msg: "expected chunk of major {} got {}", major, m)
+        }
+    }
+}
+
+#[cfg(test)]
+#[path = "cbor_ref_test.rs"]
+mod cbor_ref_test;
@@ -0,0 +1,77 @@
+use arbitrary::Unstructured;
+use rand::{prelude::random, rngs::StdRng, Rng, SeedableRng};
+
+use super::*;
+use crate::{ErrorKind, IntoCbor};
+
+#[test]
+fn test_cbor_ref() {
+    let seed: u128 = random();
+    println!("test_cbor_ref seed:{}", seed);
+    let mut rng = {
+        let mut rng_seed = [0; 32];
+        rng_seed[0..16].copy_from_slice(&seed.to_le_bytes());
+        StdRng::from_seed(rng_seed)
+    };
+
+    for _i in 0..10000 {
+        let val: Cbor = {
+            let bytes: Vec<u8> =
+                (0..100).flat_map(|_| rng.gen::<[u8; 32]>().to_vec()).collect();
+            let mut uns = Unstructured::new(&bytes);
+            uns.arbitrary().unwrap()
+        };
+
+        let mut buf: Vec<u8> = vec![];
+        let n = val.encode(&mut buf).unwrap();
+        let (rval, m) = CborRef::decode(&buf).unwrap();
+        assert_eq!(n, m);
+        assert_eq!(val, rval.to_cbor().unwrap());
+    }
+}
+
+#[test]
+fn test_cbor_ref_borrowed() {
+    let val = vec![
+        "hello world".into_cbor().unwrap(),
+        Cbor::from_bytes(vec![1, 2, 3]).unwrap(),
+    ]
+    .into_cbor()
+    .unwrap();
+
+    let mut buf: Vec<u8> = vec![];
+    val.encode(&mut buf).unwrap();
+
+    match CborRef::decode(&buf).unwrap().0 {
+        CborRef::Major4(_, items) => match (&items[0], &items[1]) {
+            (
+                CborRef::Major3(_, Cow::Borrowed(s)),
+                CborRef::Major2(_, Cow::Borrowed(b)),
+            ) => {
+                assert_eq!(*s, "hello world");
+                assert_eq!(*b, &[1, 2, 3]);
+            }
+            (a, b) => panic!("{:?} {:?}", a, b),
+        },
+        val => panic!("{:?}", val),
+    }
+
+    // indefinite text, chunked as "ab" "c"
+    let buf = [0x7f, 0x62, 0x61, 0x62, 0x61, 0x63, 0xff];
+    let (val, n) = CborRef::decode(&buf).unwrap();
+    assert_eq!(n, buf.len());
+    assert_eq!(val, CborRef::Major3(Info::Indefinite, Cow::Owned("abc".to_string())));
+
+    // break only ends indefinite array and map.
+    let testcases: Vec<&[u8]> = vec![
+        &[0xff],
+        &[0x81, 0xff],
+        &[0xa1, 0x01, 0xff],
+        &[0xbf, 0x01, 0xff, 0xff],
+        &[0xc1, 0xff],
+    ];
+    for buf in testcases.into_iter() {
+        let err = CborRef::decode(buf).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidHeader, "{:?} {}", buf, err);
+    }
+}
@@ -2,6 +2,7 @@ use arbitrary::Unstructured;
 use rand::{prelude::random, rngs::StdRng, Rng, SeedableRng};
 
 use super::*;
+use crate::{validate, CborRef, ErrorKind};
 
 #[test]
 fn test_simple_value() {
@@ -118,4 +119,1295 @@ fn test_bigint() {
             assert_eq!(val, nval);
         }
     }
+
+    // tag 3 holds -1 - n, same as major type 1.
+    let val = (-256_i128).into_cbor().unwrap();
+    let mut buf: Vec<u8> = vec![];
+    val.encode(&mut buf).unwrap();
+    assert_eq!(buf, vec![0xc3, 0x41, 0xff]);
+    assert_eq!(i128::from_cbor(val).unwrap(), -256);
+}
+
+#[test]
+fn test_decode_lazy() {
+    let seed: u128 = random();
+    println!("test_decode_lazy seed:{}", seed);
+    let mut rng = {
+        let mut rng_seed = [0; 32];
+        rng_seed[0..16].copy_from_slice(&seed.to_le_bytes());
+        StdRng::from_seed(rng_seed)
+    };
+
+    for _i in 0..1000 {
+        let val: Cbor = {
+            let bytes: Vec<u8> =
+                (0..100).flat_map(|_| rng.gen::<[u8; 32]>().to_vec()).collect();
+            let mut uns = Unstructured::new(&bytes);
+            uns.arbitrary().unwrap()
+        };
+
+        let mut buf: Vec<u8> = vec![];
+        let n = val.encode(&mut buf).unwrap();
+        for depth in 0..3 {
+            let (lval, m) = Cbor::decode_lazy(&mut buf.as_slice(), depth).unwrap();
+            assert_eq!(n, m);
+
+            let mut lbuf: Vec<u8> = vec![];
+            assert_eq!(lval.encode(&mut lbuf).unwrap(), n);
+            assert_eq!(buf, lbuf);
+            assert_eq!(expand_all(lval), val);
+        }
+    }
+
+    let val = vec![vec![1_u64, 2], vec![3]].into_cbor().unwrap();
+    let mut buf: Vec<u8> = vec![];
+    val.encode(&mut buf).unwrap();
+    match Cbor::decode_lazy(&mut buf.as_slice(), 1).unwrap().0 {
+        Cbor::Major4(_, items) => {
+            assert_eq!(items[0], Cbor::Binary(vec![0x82, 0x01, 0x02]));
+            assert_eq!(items[1], Cbor::Binary(vec![0x81, 0x03]));
+        }
+        val => panic!("{:?}", val),
+    }
+}
+
+fn expand_all(val: Cbor) -> Cbor {
+    match val.expand().unwrap() {
+        Cbor::Major4(info, items) => {
+            Cbor::Major4(info, items.into_iter().map(expand_all).collect())
+        }
+        Cbor::Major5(info, items) => {
+            let items = items.into_iter().map(|(k, v)| (k, expand_all(v))).collect();
+            Cbor::Major5(info, items)
+        }
+        Cbor::Major6(info, tag) => {
+            let num = tag.to_tag_value();
+            Cbor::Major6(info, Tag::from_value(num, expand_all(tag.as_item().clone())))
+        }
+        val => val,
+    }
+}
+
+#[test]
+fn test_skip() {
+    let seed: u128 = random();
+    println!("test_skip seed:{}", seed);
+    let mut rng = {
+        let mut rng_seed = [0; 32];
+        rng_seed[0..16].copy_from_slice(&seed.to_le_bytes());
+        StdRng::from_seed(rng_seed)
+    };
+
+    let mut buf: Vec<u8> = vec![];
+    let mut ranges = vec![];
+    for _i in 0..1000 {
+        let val: Cbor = {
+            let bytes: Vec<u8> =
+                (0..100).flat_map(|_| rng.gen::<[u8; 32]>().to_vec()).collect();
+            let mut uns = Unstructured::new(&bytes);
+            uns.arbitrary().unwrap()
+        };
+        let start = buf.len();
+        let n = val.encode(&mut buf).unwrap();
+        ranges.push(start..(start + n));
+    }
+
+    let mut offset = 0;
+    let mut r = buf.as_slice();
+    for range in ranges.into_iter() {
+        assert_eq!(Cbor::skip_slice(&buf, offset).unwrap(), range);
+        assert_eq!(Cbor::skip(&mut r).unwrap(), range.len());
+        offset = range.end;
+    }
+    assert!(r.is_empty());
+    assert!(Cbor::skip_slice(&buf, offset).is_err());
+
+    // {_ "a": [_ 1, 2], "b": (_ h'01' h'02')}, "c"
+    let buf = [
+        0xbf, 0x61, 0x61, 0x9f, 0x01, 0x02, 0xff, 0x61, 0x62, 0x5f, 0x41, 0x01, 0x41,
+        0x02, 0xff, 0xff, 0x61, 0x63,
+    ];
+    assert_eq!(Cbor::skip_slice(&buf, 0).unwrap(), 0..16);
+    assert_eq!(Cbor::skip_slice(&buf, 16).unwrap(), 16..18);
+    let err = Cbor::skip_slice(&buf[..15], 0).unwrap_err();
+    assert_eq!(err.offset(), Some(15));
+    let err = Cbor::skip_slice(&buf[..17], 16).unwrap_err();
+    assert_eq!(err.offset(), Some(17));
+    let err = Cbor::skip(&mut &buf[..12]).unwrap_err();
+    assert_eq!(err.offset(), Some(12));
+    // mismatched chunk type in indefinite byte string.
+    assert!(Cbor::skip_slice(&[0x5f, 0x61, 0x61, 0xff], 0).is_err());
+    // break outside indefinite length item.
+    for buf in [vec![0xff], vec![0x81, 0xff], vec![0xa1, 0x01, 0xff]].iter() {
+        let err = Cbor::skip_slice(buf, 0).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidHeader, "{:?}", buf);
+        assert!(crate::validate(buf).is_err(), "{:?}", buf);
+    }
+}
+
+#[test]
+fn test_encode_deterministic() {
+    let seed: u128 = random();
+    println!("test_encode_deterministic seed:{}", seed);
+    let mut rng = {
+        let mut rng_seed = [0; 32];
+        rng_seed[0..16].copy_from_slice(&seed.to_le_bytes());
+        StdRng::from_seed(rng_seed)
+    };
+
+    let opts = EncodeOptions { deterministic: true, ..EncodeOptions::default() };
+    let mut n_ok = 0;
+    for _i in 0..1000 {
+        let val: Cbor = {
+            let bytes: Vec<u8> =
+                (0..100).flat_map(|_| rng.gen::<[u8; 32]>().to_vec()).collect();
+            let mut uns = Unstructured::new(&bytes);
+            uns.arbitrary().unwrap()
+        };
+
+        let mut buf: Vec<u8> = vec![];
+        match val.encode_with(&mut buf, &opts) {
+            Ok(_) => n_ok += 1,
+            Err(_) => continue, // duplicate keys
+        }
+
+        let mut rbuf: Vec<u8> = vec![];
+        reverse_maps(val).encode_with(&mut rbuf, &opts).unwrap();
+        assert_eq!(buf, rbuf);
+
+        let (nval, _) = Cbor::decode(&mut buf.as_slice()).unwrap();
+        let mut nbuf: Vec<u8> = vec![];
+        nval.encode_with(&mut nbuf, &opts).unwrap();
+        assert_eq!(buf, nbuf);
+    }
+    assert!(n_ok > 500, "{}", n_ok);
+
+    let val = vec![
+        (Key::from("b"), 1_u64.into_cbor().unwrap()),
+        (Key::from("a"), Cbor::Major0(Info::U64, 5)),
+        (Key::U64(10), BigInt::from(5).into_cbor().unwrap()),
+        (Key::N64(-1), 1.5_f64.into_cbor().unwrap()),
+    ]
+    .into_cbor()
+    .unwrap();
+    let mut buf: Vec<u8> = vec![];
+    val.encode_with(&mut buf, &opts).unwrap();
+    let refs = vec![
+        0xa4, 0x0a, 0x05, 0x20, 0xf9, 0x3e, 0x00, 0x61, 0x61, 0x05, 0x61, 0x62, 0x01,
+    ];
+    assert_eq!(buf, refs);
+
+    let val = Cbor::Major4(Info::Indefinite, vec![]);
+    assert!(val.encode_with(&mut vec![], &opts).is_err());
+    let val = vec![
+        (Key::from("a"), true.into_cbor().unwrap()),
+        (Key::from("a"), false.into_cbor().unwrap()),
+    ];
+    let val = val.into_cbor().unwrap();
+    assert!(val.encode_with(&mut vec![], &opts).is_err());
+
+    // set items are sorted by their encoding, 258(["b", "aa", 10])
+    let items = vec![Cbor::text("aa"), Cbor::int(10), Cbor::text("b")];
+    let mut buf: Vec<u8> = vec![];
+    for i in 0..items.len() {
+        let mut items = items.clone();
+        items.rotate_left(i);
+        let val: Cbor = Tag::Set(Box::new(Cbor::array(items))).into();
+        buf.truncate(0);
+        val.encode_with(&mut buf, &opts).unwrap();
+        let refs = vec![0xd9, 0x01, 0x02, 0x83, 0x0a, 0x61, 0x62, 0x62, 0x61, 0x61];
+        assert_eq!(buf, refs);
+    }
+    let set: std::collections::HashSet<String> =
+        (0..100).map(|i| i.to_string()).collect();
+    let mut items: Vec<String> = set.iter().cloned().collect();
+    items.reverse();
+    let (mut buf, mut rbuf) = (vec![], vec![]);
+    set.into_cbor().unwrap().encode_with(&mut buf, &opts).unwrap();
+    let val: Cbor = Tag::Set(Box::new(items.into_cbor().unwrap())).into();
+    val.encode_with(&mut rbuf, &opts).unwrap();
+    assert_eq!(buf, rbuf);
+
+    let val: Cbor =
+        Tag::Set(Box::new(Cbor::array(vec![Cbor::int(1), Cbor::int(1)]))).into();
+    assert!(val.encode_with(&mut vec![], &opts).is_err());
+}
+
+#[test]
+fn test_bignum_deterministic() {
+    use crate::{FromCbor, IntoCbor, LocalCborize};
+
+    #[derive(LocalCborize, Clone, Debug, PartialEq)]
+    struct Nums {
+        a: u128,
+        b: i128,
+    }
+
+    impl Nums {
+        const ID: u32 = 1;
+    }
+
+    let opts = EncodeOptions { deterministic: true, ..EncodeOptions::default() };
+    let encode = |val: Cbor| {
+        let mut buf: Vec<u8> = vec![];
+        val.encode_with(&mut buf, &opts).unwrap();
+        Cbor::decode(&mut buf.as_slice()).unwrap().0
+    };
+
+    let unums = [0, 5, u128::from(u64::MAX), u128::from(u64::MAX) + 1, u128::MAX];
+    for num in unums.iter() {
+        let val = encode(num.into_cbor().unwrap());
+        assert_eq!(u128::from_cbor(val.clone()).unwrap(), *num);
+        assert_eq!(BigInt::from_cbor(val).unwrap(), BigInt::from(*num));
+    }
+    assert_eq!(encode(5_u128.into_cbor().unwrap()), Cbor::Major0(Info::Tiny(5), 5));
+
+    let inums =
+        [-5, -1 - i128::from(u64::MAX), -2 - i128::from(u64::MAX), i128::MIN, i128::MAX];
+    for num in inums.iter() {
+        let val = encode(num.into_cbor().unwrap());
+        assert_eq!(i128::from_cbor(val.clone()).unwrap(), *num);
+        assert_eq!(BigInt::from_cbor(val).unwrap(), BigInt::from(*num));
+    }
+    assert_eq!(encode((-5_i128).into_cbor().unwrap()), Cbor::Major1(Info::Tiny(4), 4));
+
+    let nums = Nums { a: 5, b: -5 };
+    let val = encode(nums.clone().into_cbor().unwrap());
+    assert_eq!(Nums::from_cbor(val).unwrap(), nums);
+
+    let val = "5".into_cbor().unwrap();
+    assert_eq!(u128::from_cbor(val).unwrap_err().kind(), ErrorKind::TypeMismatch);
+}
+
+#[test]
+fn test_key_cbor() {
+    use crate::{FromCbor, IntoCbor};
+
+    // map keyed by array, tag, null and negative integer beyond i64.
+    let buf = vec![
+        0xa4, 0x82, 0x01, 0x02, 0x01, 0xc2, 0x41, 0x01, 0x02, 0xf6, 0x03, 0x3b, 0xff,
+        0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x04,
+    ];
+    let (val, n) = Cbor::decode(&mut buf.as_slice()).unwrap();
+    assert_eq!(n, buf.len());
+    let keys: Vec<Key> = match &val {
+        Cbor::Major5(_, items) => items.iter().map(|(k, _)| k.clone()).collect(),
+        _ => unreachable!(),
+    };
+    assert_eq!(keys[0], Key::Cbor(Box::new(vec![1_u64, 2].into_cbor().unwrap())));
+    assert_eq!(keys[1], Key::Cbor(Box::new(BigInt::from(1).into_cbor().unwrap())));
+    assert_eq!(keys[2], Key::Cbor(Box::new(SimpleValue::Null.into_cbor().unwrap())));
+    assert_eq!(keys[3], Key::Cbor(Box::new(Cbor::Major1(Info::U64, u64::MAX))));
+
+    let mut out: Vec<u8> = vec![];
+    val.encode(&mut out).unwrap();
+    assert_eq!(out, buf);
+
+    // i64 boundaries are still plain integer keys.
+    let key = Key::from_cbor(Cbor::Major1(Info::U64, i64::MAX as u64)).unwrap();
+    assert_eq!(key, Key::N64(i64::MIN));
+    assert_eq!(key.into_cbor().unwrap(), Cbor::Major1(Info::U64, i64::MAX as u64));
+
+    // compared by deterministic encoding, sort after text.
+    let a = Key::Cbor(Box::new(Cbor::Major4(1.into(), vec![Cbor::Major0(Info::U16, 1)])));
+    let b = Key::Cbor(Box::new(vec![1_u64].into_cbor().unwrap()));
+    let c = Key::Cbor(Box::new(vec![2_u64].into_cbor().unwrap()));
+    assert_eq!(a, b);
+    assert!(b < c);
+    assert!(Key::from("z") < a);
+    // indefinite length items compare equal to their definite form.
+    let d = Key::Cbor(Box::new(Cbor::Major4(Info::Indefinite, vec![Cbor::int(1)])));
+    assert_eq!(d, b);
+    assert!(d < c);
+
+    let opts = EncodeOptions { deterministic: true, ..EncodeOptions::default() };
+    let val = vec![(c, 1_u64.into_cbor().unwrap()), (a, 2_u64.into_cbor().unwrap())];
+    let mut out: Vec<u8> = vec![];
+    val.into_cbor().unwrap().encode_with(&mut out, &opts).unwrap();
+    assert_eq!(out, vec![0xa2, 0x81, 0x01, 0x02, 0x81, 0x02, 0x01]);
+
+    // items that fail to encode, like truncated binary, compared by structure.
+    let item = |hdr: u8| Cbor::array(vec![Cbor::Binary(vec![hdr])]);
+    let (a, b) = (Key::Cbor(Box::new(item(0x18))), Key::Cbor(Box::new(item(0x19))));
+    assert!(canonical_bytes(&item(0x18)).is_err());
+    assert_ne!(a, b);
+    assert!(a < b);
+    assert_eq!(a, Key::Cbor(Box::new(item(0x18))));
+    assert!(a < Key::Cbor(Box::new(vec![1_u64].into_cbor().unwrap())));
+    let mut val = Cbor::map(vec![(a, Cbor::int(1)), (b, Cbor::int(2))]);
+    val.dedup_keys(DuplicateKeys::Reject).unwrap();
+    assert_eq!(val.as_map().unwrap().len(), 2);
+
+    // half-precision float keys keep their width.
+    let buf = vec![0xa1, 0xf9, 0x3e, 0x00, 0x01];
+    let (val, _) = Cbor::decode(&mut buf.as_slice()).unwrap();
+    let key = Key::Cbor(Box::new(Cbor::Major7(Info::U16, SimpleValue::F16(0x3e00))));
+    assert_eq!(val.as_map().unwrap()[0].0, key);
+    let mut out: Vec<u8> = vec![];
+    val.encode(&mut out).unwrap();
+    assert_eq!(out, buf);
+}
+
+#[test]
+fn test_duplicate_keys() {
+    use crate::IntoCbor;
+
+    // {"a": 1, 1: 2, "a": 3, 0x01: 4, 1.0: 5}, integer 1 in two widths.
+    let buf = vec![
+        0xa5, 0x61, 0x61, 0x01, 0x01, 0x02, 0x61, 0x61, 0x03, 0x18, 0x01, 0x04, 0xf9,
+        0x3c, 0x00, 0x05,
+    ];
+    let decode = |buf: &[u8], policy: DuplicateKeys| {
+        let opts = DecodeOptions { duplicate_keys: policy, ..DecodeOptions::default() };
+        let (val, n) = Cbor::decode_with(&mut &buf[..], &opts)?;
+        assert_eq!(n, buf.len());
+        Ok::<Cbor, Error>(val)
+    };
+    let entries = |val: Cbor| match val {
+        Cbor::Major5(info, map) => {
+            assert_eq!(info, Info::try_from(map.len()).unwrap());
+            map.into_iter()
+                .map(|(k, v)| (k, u64::from_cbor(v).unwrap()))
+                .collect::<Vec<(Key, u64)>>()
+        }
+        _ => unreachable!(),
+    };
+    let f = Key::Cbor(Box::new(Cbor::Major7(Info::U16, SimpleValue::F16(0x3c00))));
+    let (a, one) = (Key::from("a"), Key::U64(1));
+
+    let val = decode(&buf, DuplicateKeys::Allow).unwrap();
+    assert_eq!(entries(val).len(), 5);
+
+    let err = decode(&buf, DuplicateKeys::Reject).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidData);
+
+    let val = decode(&buf, DuplicateKeys::KeepFirst).unwrap();
+    let refv = vec![(a.clone(), 1), (one.clone(), 2), (f.clone(), 5)];
+    assert_eq!(entries(val), refv);
+
+    let val = decode(&buf, DuplicateKeys::KeepLast).unwrap();
+    let refv = vec![(a.clone(), 3), (one.clone(), 4), (f.clone(), 5)];
+    assert_eq!(entries(val), refv);
+
+    // indefinite map nested in an array.
+    let buf = vec![0x81, 0xbf, 0x01, 0x02, 0x01, 0x03, 0xff];
+    let val = decode(&buf, DuplicateKeys::KeepLast).unwrap();
+    let map = vec![(one.clone(), 3_u64.into_cbor().unwrap())];
+    assert_eq!(val, Cbor::Major4(1.into(), vec![Cbor::Major5(Info::Indefinite, map)]));
+
+    // in-memory trees, through arrays, tags and keys.
+    let inner = || {
+        let entry = (a.clone(), 1_u64.into_cbor().unwrap());
+        Cbor::Major5(2.into(), vec![entry.clone(), entry])
+    };
+    let mut val = Cbor::Major4(
+        2.into(),
+        vec![
+            Tag::from_value(1000, inner()).into(),
+            Cbor::Major5(1.into(), vec![(Key::Cbor(Box::new(inner())), inner())]),
+        ],
+    );
+    assert!(val.clone().dedup_keys(DuplicateKeys::Reject).is_err());
+    val.dedup_keys(DuplicateKeys::KeepFirst).unwrap();
+    val.check().unwrap();
+
+    let inner = Cbor::Major5(1.into(), vec![(a, 1_u64.into_cbor().unwrap())]);
+    let refv = Cbor::Major4(
+        2.into(),
+        vec![
+            Tag::from_value(1000, inner.clone()).into(),
+            Cbor::Major5(1.into(), vec![(Key::Cbor(Box::new(inner.clone())), inner)]),
+        ],
+    );
+    assert_eq!(val, refv);
+    val.dedup_keys(DuplicateKeys::Reject).unwrap();
+}
+
+#[test]
+fn test_accessors() {
+    use crate::IntoCbor;
+
+    let items: Vec<Cbor> = vec![
+        10_u64.into_cbor().unwrap(),
+        (-10_i64).into_cbor().unwrap(),
+        u128::MAX.into_cbor().unwrap(),
+        (-(1_i128 << 100)).into_cbor().unwrap(),
+        Cbor::Major0(Info::U64, 5),
+        Tag::UBigNum(Box::new(Cbor::from_bytes(vec![0, 0, 1]).unwrap())).into(),
+        Tag::SBigNum(Box::new(Cbor::from_bytes(vec![]).unwrap())).into(),
+        Cbor::Major1(Info::U64, u64::MAX),
+    ];
+    let val = vec![
+        (Key::from("items"), items.clone().into_cbor().unwrap()),
+        (Key::from("name"), "pen".into_cbor().unwrap()),
+        (Key::from("blob"), Cbor::from_bytes(vec![1, 2]).unwrap()),
+        (Key::U64(1), SimpleValue::Null.into_cbor().unwrap()),
+        (Key::from("name"), "ink".into_cbor().unwrap()),
+    ]
+    .into_cbor()
+    .unwrap();
+
+    let nums: Vec<(Option<u64>, Option<i128>)> = val["items"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|v| (v.as_u64(), v.as_i128()))
+        .collect();
+    let refv = vec![
+        (Some(10), Some(10)),
+        (None, Some(-10)),
+        (None, None),
+        (None, Some(-(1 << 100))),
+        (Some(5), Some(5)),
+        (Some(1), Some(1)),
+        (None, Some(-1)),
+        (None, Some(-1 - i128::from(u64::MAX))),
+    ];
+    assert_eq!(nums, refv);
+
+    assert_eq!(val["name"].as_str(), Some("pen"));
+    assert_eq!(val.get(&Key::from("name")), Some(&"pen".into_cbor().unwrap()));
+    assert_eq!(val["blob"].as_bytes(), Some(&[1_u8, 2][..]));
+    assert_eq!(val["items"][4].as_u64(), Some(5));
+    assert_eq!(val["items"].get_index(0), Some(&items[0]));
+    assert_eq!(val.as_map().unwrap().len(), 5);
+    assert!(val.get(&Key::U64(1)).unwrap().is_null());
+
+    // missing entries index to null.
+    assert!(val["missing"].is_null());
+    assert!(val["items"][100].is_null());
+    assert!(val["name"]["x"][0].is_null());
+    assert_eq!(val.get(&Key::from("missing")), None);
+    assert_eq!(val.get_index(0), None);
+    assert_eq!(val["name"].as_u64(), None);
+    assert_eq!(val["items"].as_str(), None);
+
+    // tags are skipped for arrays and maps.
+    let tagged: Cbor = Tag::Other(1000, Box::new(val.clone())).into();
+    assert_eq!(tagged["name"].as_str(), Some("pen"));
+    assert_eq!(tagged.get(&Key::U64(1)), val.get(&Key::U64(1)));
+    let set: Cbor = Tag::Set(Box::new(items.clone().into_cbor().unwrap())).into();
+    assert_eq!(set.get_index(0), Some(&items[0]));
+    assert_eq!(set[4].as_u64(), Some(5));
+    assert_eq!(set.as_array().unwrap().len(), items.len());
+}
+
+#[test]
+fn test_constructors() {
+    use crate::IntoCbor;
+
+    let nums = [
+        0,
+        23,
+        24,
+        i128::from(u64::MAX),
+        i128::from(u64::MAX) + 1,
+        -1,
+        -25,
+        -1 - i128::from(u64::MAX),
+        -2 - i128::from(u64::MAX),
+        i128::MAX,
+        i128::MIN,
+    ];
+    for num in nums.iter() {
+        let val = Cbor::int(*num);
+        assert_eq!(val.as_i128(), Some(*num), "{}", num);
+        if !matches!(val, Cbor::Major0(_, _) | Cbor::Major1(_, _)) {
+            assert_eq!(val, num.into_cbor().unwrap(), "{}", num);
+        }
+    }
+    assert_eq!(Cbor::int(24), 24_u64.into_cbor().unwrap());
+    assert_eq!(Cbor::int(-25), (-25_i64).into_cbor().unwrap());
+    assert_eq!(Cbor::int(-1 - i128::from(u64::MAX)), Cbor::Major1(Info::U64, u64::MAX));
+
+    let text = "x".repeat(300);
+    let items: Vec<Cbor> = (0..24_i128).map(Cbor::int).collect();
+    let testcases = vec![
+        (Cbor::null(), SimpleValue::Null.into_cbor().unwrap()),
+        (Cbor::bool(true), true.into_cbor().unwrap()),
+        (Cbor::bool(false), false.into_cbor().unwrap()),
+        (Cbor::float(1.5), 1.5_f64.into_cbor().unwrap()),
+        (Cbor::bytes(vec![1, 2]), Cbor::from_bytes(vec![1, 2]).unwrap()),
+        (Cbor::text(text.as_str()), text.as_str().into_cbor().unwrap()),
+        (Cbor::array(items.clone()), items.clone().into_cbor().unwrap()),
+        (
+            Cbor::map(vec![(Key::from("a"), Cbor::text("b"))]),
+            vec![(Key::from("a"), "b".into_cbor().unwrap())].into_cbor().unwrap(),
+        ),
+        (
+            Cbor::tagged(1, Cbor::int(10)),
+            Tag::Epoch(Box::new(10_u64.into_cbor().unwrap())).into(),
+        ),
+        (
+            Cbor::tagged(1000, Cbor::null()),
+            Tag::Other(1000, Box::new(Cbor::null())).into(),
+        ),
+    ];
+    for (i, (val, refv)) in testcases.into_iter().enumerate() {
+        assert_eq!(val, refv, "case {}", i);
+        val.check().unwrap();
+
+        let mut buf: Vec<u8> = vec![];
+        let n = val.encode(&mut buf).unwrap();
+        assert_eq!(Cbor::decode(&mut buf.as_slice()).unwrap(), (val, n), "case {}", i);
+    }
+
+    assert!(matches!(Cbor::text(text), Cbor::Major3(Info::U16, _)));
+    assert!(matches!(Cbor::array(items), Cbor::Major4(Info::U8, _)));
+}
+
+fn reverse_maps(val: Cbor) -> Cbor {
+    match val {
+        Cbor::Major4(info, items) => {
+            Cbor::Major4(info, items.into_iter().map(reverse_maps).collect())
+        }
+        Cbor::Major5(info, items) => {
+            let items = items.into_iter().rev().map(|(k, v)| (k, reverse_maps(v)));
+            Cbor::Major5(info, items.collect())
+        }
+        val => val,
+    }
+}
+
+#[test]
+fn test_decode_with() {
+    let seed: u128 = random();
+    println!("test_decode_with seed:{}", seed);
+    let mut rng = {
+        let mut rng_seed = [0; 32];
+        rng_seed[0..16].copy_from_slice(&seed.to_le_bytes());
+        StdRng::from_seed(rng_seed)
+    };
+
+    // default options shall decode everything decode can.
+    let opts = DecodeOptions::default();
+    for _i in 0..1000 {
+        let val: Cbor = {
+            let bytes: Vec<u8> =
+                (0..100).flat_map(|_| rng.gen::<[u8; 32]>().to_vec()).collect();
+            let mut uns = Unstructured::new(&bytes);
+            uns.arbitrary().unwrap()
+        };
+        let mut buf: Vec<u8> = vec![];
+        let n = val.encode(&mut buf).unwrap();
+        let (dval, m) = Cbor::decode_with(&mut buf.as_slice(), &opts).unwrap();
+        assert_eq!(val, dval);
+        assert_eq!(n, m);
+    }
+
+    // a 4GB string announced in 9 bytes shall fail before allocation.
+    let buf = vec![0x5b, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00];
+    let opts = DecodeOptions { max_string: 1024, ..DecodeOptions::default() };
+    let err = Cbor::decode_with(&mut buf.as_slice(), &opts).unwrap_err();
+    assert!(err.to_string().contains("max_string"), "{}", err);
+    let opts = DecodeOptions { max_bytes: 1024, ..DecodeOptions::default() };
+    let err = Cbor::decode_with(&mut buf.as_slice(), &opts).unwrap_err();
+    assert!(err.to_string().contains("max_bytes"), "{}", err);
+
+    let val = vec![1_u64; 10].into_cbor().unwrap();
+    let mut buf: Vec<u8> = vec![];
+    val.encode(&mut buf).unwrap();
+    let opts = DecodeOptions { max_entries: 10, ..DecodeOptions::default() };
+    assert!(Cbor::decode_with(&mut buf.as_slice(), &opts).is_ok());
+    let opts = DecodeOptions { max_entries: 9, ..DecodeOptions::default() };
+    assert!(Cbor::decode_with(&mut buf.as_slice(), &opts).is_err());
+    let opts = DecodeOptions {
+        max_bytes: buf.len() - 1,
+        ..DecodeOptions::default()
+    };
+    assert!(Cbor::decode_with(&mut buf.as_slice(), &opts).is_err());
+    let opts = DecodeOptions { max_bytes: buf.len(), ..DecodeOptions::default() };
+    assert!(Cbor::decode_with(&mut buf.as_slice(), &opts).is_ok());
+
+    let val = vec![vec![vec![1_u64]]].into_cbor().unwrap();
+    let mut buf: Vec<u8> = vec![];
+    val.encode(&mut buf).unwrap();
+    let opts = DecodeOptions { max_depth: 4, ..DecodeOptions::default() };
+    assert!(Cbor::decode_with(&mut buf.as_slice(), &opts).is_ok());
+    let opts = DecodeOptions { max_depth: 3, ..DecodeOptions::default() };
+    assert!(Cbor::decode_with(&mut buf.as_slice(), &opts).is_err());
+
+    let val = BigInt::from(u128::MAX).into_cbor().unwrap();
+    let mut buf: Vec<u8> = vec![];
+    val.encode(&mut buf).unwrap();
+    let opts = DecodeOptions { max_bignum: 16, ..DecodeOptions::default() };
+    assert!(Cbor::decode_with(&mut buf.as_slice(), &opts).is_ok());
+    let opts = DecodeOptions { max_bignum: 15, ..DecodeOptions::default() };
+    assert!(Cbor::decode_with(&mut buf.as_slice(), &opts).is_err());
+
+    // indefinite length containers are bounded as they are read.
+    let mut buf = vec![0x9f];
+    buf.extend_from_slice(&[0x01; 100]);
+    buf.push(0xff);
+    let opts = DecodeOptions { max_entries: 10, ..DecodeOptions::default() };
+    assert!(Cbor::decode_with(&mut buf.as_slice(), &opts).is_err());
+    let mut buf = vec![0x5f];
+    for _i in 0..10 {
+        buf.extend_from_slice(&[0x43, 0x01, 0x02, 0x03]);
+    }
+    buf.push(0xff);
+    let opts = DecodeOptions { max_string: 29, ..DecodeOptions::default() };
+    assert!(Cbor::decode_with(&mut buf.as_slice(), &opts).is_err());
+    let opts = DecodeOptions { max_string: 30, ..DecodeOptions::default() };
+    let (val, _) = Cbor::decode_with(&mut buf.as_slice(), &opts).unwrap();
+    assert_eq!(val, Cbor::Major2(Info::Indefinite, [1, 2, 3].repeat(10)));
+}
+
+#[test]
+fn test_info_width() {
+    let seed: u128 = random();
+    println!("test_info_width seed:{}", seed);
+    let mut rng = {
+        let mut rng_seed = [0; 32];
+        rng_seed[0..16].copy_from_slice(&seed.to_le_bytes());
+        StdRng::from_seed(rng_seed)
+    };
+
+    for _i in 0..1000 {
+        let val: Cbor = {
+            let bytes: Vec<u8> =
+                (0..100).flat_map(|_| rng.gen::<[u8; 32]>().to_vec()).collect();
+            let mut uns = Unstructured::new(&bytes);
+            uns.arbitrary().unwrap()
+        };
+        let val = widen(val, &mut rng);
+        val.check().unwrap();
+
+        let mut buf: Vec<u8> = vec![];
+        let n = val.encode(&mut buf).unwrap();
+        let (nval, m) = Cbor::decode(&mut buf.as_slice()).unwrap();
+        assert_eq!(n, m);
+        assert_eq!(val, nval);
+        nval.check().unwrap();
+    }
+
+    let val = Cbor::Major0(Info::U64, 5);
+    let mut buf: Vec<u8> = vec![];
+    assert_eq!(val.encode(&mut buf).unwrap(), 9);
+    assert_eq!(buf, vec![0x1b, 0, 0, 0, 0, 0, 0, 0, 5]);
+
+    let val = Cbor::Major4(Info::Indefinite, vec![Cbor::Major2(Info::U8, vec![1])]);
+    let mut buf: Vec<u8> = vec![];
+    assert_eq!(val.encode(&mut buf).unwrap(), 5);
+    assert_eq!(buf, vec![0x9f, 0x58, 0x01, 0x01, 0xff]);
+
+    let val = Cbor::Major2(Info::Indefinite, vec![1, 2]);
+    let mut buf: Vec<u8> = vec![];
+    assert_eq!(val.encode(&mut buf).unwrap(), 5);
+    assert_eq!(buf, vec![0x5f, 0x42, 0x01, 0x02, 0xff]);
+
+    let invalids = vec![
+        Cbor::Major0(Info::Tiny(3), 5),
+        Cbor::Major1(Info::U8, 256),
+        Cbor::Major0(Info::Indefinite, 5),
+        Cbor::Major0(Info::Reserved28, 5),
+        Cbor::Major2(Info::Tiny(1), vec![1, 2]),
+        Cbor::Major3(Info::Tiny(2), vec![0xff, 0xfe]),
+        Cbor::Major4(Info::Tiny(0), vec![Cbor::Major0(Info::Tiny(1), 1)]),
+        Cbor::Major4(Info::Tiny(1), vec![Cbor::Major0(Info::Tiny(1), 2)]),
+        Cbor::Major7(Info::U64, SimpleValue::F32(1.0)),
+        Cbor::Major7(Info::Tiny(21), SimpleValue::True),
+        Cbor::Binary(vec![0x82, 0x01]),
+    ];
+    for val in invalids.into_iter() {
+        assert!(val.check().is_err(), "{:?}", val);
+        if let Cbor::Major3(_, _) | Cbor::Binary(_) = val {
+            continue; // encoded as is, but not well-formed
+        }
+        assert!(val.encode(&mut vec![]).is_err(), "{:?}", val);
+    }
+}
+
+#[test]
+fn test_decode_indefinite() {
+    // {_ "a": 1, "b": [_ ]}
+    let buf = [0xbf, 0x61, 0x61, 0x01, 0x61, 0x62, 0x9f, 0xff, 0xff];
+    let (val, n) = Cbor::decode(&mut buf.as_ref()).unwrap();
+    assert_eq!(n, buf.len());
+    let refv = Cbor::Major5(
+        Info::Indefinite,
+        vec![
+            (Key::from("a"), Cbor::Major0(Info::Tiny(1), 1)),
+            (Key::from("b"), Cbor::Major4(Info::Indefinite, vec![])),
+        ],
+    );
+    assert_eq!(val, refv);
+    let mut out: Vec<u8> = vec![];
+    assert_eq!(val.encode(&mut out).unwrap(), buf.len());
+    assert_eq!(out, buf.to_vec());
+
+    // (_ "ab" "c")
+    let buf = [0x7f, 0x62, 0x61, 0x62, 0x61, 0x63, 0xff];
+    let (val, n) = Cbor::decode(&mut buf.as_ref()).unwrap();
+    assert_eq!(n, buf.len());
+    assert_eq!(val, Cbor::Major3(Info::Indefinite, b"abc".to_vec()));
+
+    // break only ends indefinite array and map.
+    let testcases: Vec<&[u8]> = vec![
+        &[0xff],
+        &[0x81, 0xff],
+        &[0xa1, 0x01, 0xff],
+        &[0xbf, 0x01, 0xff, 0xff],
+        &[0xc1, 0xff],
+    ];
+    for buf in testcases.into_iter() {
+        let err = Cbor::decode(&mut &buf[..]).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidHeader, "{:?} {}", buf, err);
+        assert!(validate(buf).is_err(), "{:?}", buf);
+    }
+}
+
+// rewrite Info with non-preferred widths and indefinite lengths.
+fn widen(val: Cbor, rng: &mut StdRng) -> Cbor {
+    let wider = |info: Info, rng: &mut StdRng| -> Info {
+        let infos = [Info::U8, Info::U16, Info::U32, Info::U64];
+        let from = match info {
+            Info::Tiny(_) => 0,
+            Info::U8 => 0,
+            Info::U16 => 1,
+            Info::U32 => 2,
+            _ => 3,
+        };
+        match rng.gen::<u8>() % 3 {
+            0 => info,
+            _ => infos[rng.gen_range(from..4)],
+        }
+    };
+    let either = |info: Info, rng: &mut StdRng| -> Info {
+        match rng.gen::<u8>() % 4 {
+            0 => Info::Indefinite,
+            _ => wider(info, rng),
+        }
+    };
+
+    match val {
+        Cbor::Major0(info, num) => Cbor::Major0(wider(info, rng), num),
+        Cbor::Major1(info, num) => Cbor::Major1(wider(info, rng), num),
+        Cbor::Major2(info, byts) => Cbor::Major2(either(info, rng), byts),
+        Cbor::Major3(info, text) => Cbor::Major3(either(info, rng), text),
+        Cbor::Major4(info, list) => {
+            let list = list.into_iter().map(|x| widen(x, rng)).collect();
+            Cbor::Major4(either(info, rng), list)
+        }
+        Cbor::Major5(info, map) => {
+            let map = map.into_iter().map(|(k, v)| (k, widen(v, rng))).collect();
+            Cbor::Major5(either(info, rng), map)
+        }
+        Cbor::Major6(info, tag) => Cbor::Major6(wider(info, rng), tag),
+        val => val,
+    }
+}
+
+#[test]
+fn test_f16() {
+    for half in 0..=u16::MAX {
+        let val = f16_to_f32(half);
+        assert_eq!(f32_to_f16(val), Some(half), "{:x} {}", half, val);
+    }
+
+    let refs: Vec<(u16, f32)> = vec![
+        (0x0000, 0.0),
+        (0x8000, -0.0),
+        (0x3c00, 1.0),
+        (0x3e00, 1.5),
+        (0x7bff, 65504.0),
+        (0x0001, 5.960_464_5e-8),
+        (0x0400, 6.103_515_6e-5),
+        (0xc400, -4.0),
+        (0x7c00, f32::INFINITY),
+        (0xfc00, f32::NEG_INFINITY),
+    ];
+    for (half, val) in refs.into_iter() {
+        assert_eq!(f16_to_f32(half).to_bits(), val.to_bits(), "{:x}", half);
+        assert_eq!(f32_to_f16(val), Some(half), "{}", val);
+    }
+    assert!(f16_to_f32(0x7e00).is_nan());
+    for val in [0.1_f32, 65520.0, 1.0e-8, f32::MAX, f32::from_bits(1)].iter() {
+        assert_eq!(f32_to_f16(*val), None, "{}", val);
+    }
+
+    // decode half-precision floats produced by other implementations.
+    let (val, n) = Cbor::decode(&mut [0xf9, 0x3c, 0x00].as_ref()).unwrap();
+    assert_eq!(n, 3);
+    assert_eq!(val, Cbor::Major7(Info::U16, SimpleValue::F16(0x3c00)));
+    assert_eq!(f32::from_cbor(val.clone()).unwrap(), 1.0);
+    assert_eq!(f64::from_cbor(val.clone()).unwrap(), 1.0);
+    let mut buf: Vec<u8> = vec![];
+    assert_eq!(val.encode(&mut buf).unwrap(), 3);
+    assert_eq!(buf, vec![0xf9, 0x3c, 0x00]);
+    let val = Cbor::decode(&mut [0xfa, 0x3f, 0xc0, 0x00, 0x00].as_ref()).unwrap().0;
+    assert_eq!(f64::from_cbor(val).unwrap(), 1.5);
+
+    // deterministic encoding picks the shortest float without loss.
+    let opts = EncodeOptions { deterministic: true, ..EncodeOptions::default() };
+    let refs: Vec<(f64, Vec<u8>)> = vec![
+        (0.0, vec![0xf9, 0x00, 0x00]),
+        (-0.0, vec![0xf9, 0x80, 0x00]),
+        (1.5, vec![0xf9, 0x3e, 0x00]),
+        (100000.0, vec![0xfa, 0x47, 0xc3, 0x50, 0x00]),
+        (1.1, vec![0xfb, 0x3f, 0xf1, 0x99, 0x99, 0x99, 0x99, 0x99, 0x9a]),
+        (f64::INFINITY, vec![0xf9, 0x7c, 0x00]),
+        (f64::NAN, vec![0xf9, 0x7e, 0x00]),
+    ];
+    for (val, refb) in refs.into_iter() {
+        let mut buf: Vec<u8> = vec![];
+        val.into_cbor().unwrap().encode_with(&mut buf, &opts).unwrap();
+        assert_eq!(buf, refb, "{}", val);
+        let mut buf: Vec<u8> = vec![];
+        (val as f32).into_cbor().unwrap().encode_with(&mut buf, &opts).unwrap();
+        let val = f64::from_cbor(Cbor::decode(&mut buf.as_slice()).unwrap().0).unwrap();
+        assert!(val.is_nan() || val == f64::from(val as f32));
+    }
+}
+
+#[test]
+fn test_tag_other() {
+    // [32("a"), 1(2), 1]
+    let buf = [0x83, 0xd8, 0x20, 0x61, 0x61, 0xc1, 0x02, 0x01];
+    let (val, n) = Cbor::decode(&mut buf.as_ref()).unwrap();
+    assert_eq!(n, buf.len());
+    let refv = vec![
+        Tag::from_value(32, "a".into_cbor().unwrap()).into(),
+        Tag::from_value(1, 2_u64.into_cbor().unwrap()).into(),
+        1_u64.into_cbor().unwrap(),
+    ];
+    assert_eq!(val, refv.into_cbor().unwrap());
+    match &val {
+        Cbor::Major4(_, items) => match &items[0] {
+            Cbor::Major6(Info::U8, Tag::Other(32, item)) => {
+                assert_eq!(String::from_cbor(item.as_ref().clone()).unwrap(), "a")
+            }
+            item => panic!("{:?}", item),
+        },
+        val => panic!("{:?}", val),
+    }
+
+    let mut out: Vec<u8> = vec![];
+    assert_eq!(val.encode(&mut out).unwrap(), buf.len());
+    assert_eq!(out, buf.to_vec());
+
+    let val = Tag::from_value(2, Cbor::from_bytes(vec![1]).unwrap());
+    assert!(matches!(val, Tag::UBigNum(_)));
+}
+
+#[test]
+fn test_tag_recursion() {
+    // nesting upto RECURSION_LIMIT needs a large stack in debug builds.
+    let handle = std::thread::Builder::new().stack_size(32 * 1024 * 1024);
+    let handle = handle.spawn(|| {
+        // chain of identifier tags, 39(39(39(... 1)))
+        let mut buf = [0xd8, 0x27].repeat((RECURSION_LIMIT as usize) * 2);
+        buf.push(0x01);
+        let err = Cbor::decode(&mut buf.as_slice()).unwrap_err();
+        assert!(err.to_string().contains("recursion limt"), "{}", err);
+
+        // tags and arrays share the same depth count.
+        let mut buf = [0x81, 0xc1].repeat((RECURSION_LIMIT as usize) / 2 - 1);
+        buf.extend_from_slice(&[0x81, 0x01]);
+        assert!(Cbor::decode(&mut buf.as_slice()).is_ok());
+        buf.insert(0, 0xc1);
+        assert!(Cbor::decode(&mut buf.as_slice()).is_err());
+
+        let opts = DecodeOptions { max_depth: 10, ..DecodeOptions::default() };
+        let mut buf = [0xc1].repeat(10);
+        buf.push(0x01);
+        assert!(Cbor::decode_with(&mut buf.as_slice(), &opts).is_err());
+        let opts = DecodeOptions { max_bytes: 10, ..DecodeOptions::default() };
+        assert!(Cbor::decode_with(&mut buf.as_slice(), &opts).is_err());
+
+        // max_depth is capped at RECURSION_LIMIT.
+        let mut buf = [0x81].repeat((RECURSION_LIMIT as usize) + 10);
+        buf.push(0x01);
+        let opts = DecodeOptions { max_depth: u32::MAX, ..DecodeOptions::default() };
+        let err = Cbor::decode_with(&mut buf.as_slice(), &opts).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::LimitExceeded);
+
+        let mut val = 1_u64.into_cbor().unwrap();
+        for _i in 0..RECURSION_LIMIT {
+            val = Tag::from_identifier(val).into();
+        }
+        let err = val.encode(&mut vec![]).unwrap_err();
+        assert!(err.to_string().contains("recursion limit"), "{}", err);
+    });
+    handle.unwrap().join().unwrap();
+
+    assert!(Cbor::decode(&mut [0xdf, 0x01].as_ref()).is_err());
+}
+
+#[test]
+fn test_string_refs() {
+    let seed: u128 = random();
+    println!("test_string_refs seed:{}", seed);
+    let mut rng = {
+        let mut rng_seed = [0; 32];
+        rng_seed[0..16].copy_from_slice(&seed.to_le_bytes());
+        StdRng::from_seed(rng_seed)
+    };
+
+    let eopts = EncodeOptions { string_refs: true, ..EncodeOptions::default() };
+    let dopts = DecodeOptions { resolve_refs: true, ..DecodeOptions::default() };
+    for _i in 0..1000 {
+        let val: Cbor = {
+            let bytes: Vec<u8> =
+                (0..100).flat_map(|_| rng.gen::<[u8; 32]>().to_vec()).collect();
+            let mut uns = Unstructured::new(&bytes);
+            uns.arbitrary().unwrap()
+        };
+        let val = vec![val.clone(), val].into_cbor().unwrap();
+
+        let mut buf: Vec<u8> = vec![];
+        let n = val.encode_with(&mut buf, &eopts).unwrap();
+        let (nval, m) = Cbor::decode_with(&mut buf.as_slice(), &dopts).unwrap();
+        assert_eq!(n, m);
+        assert_eq!(val, nval);
+    }
+
+    // example from http://cbor.schmorp.de/stringref
+    let strs = [
+        "1", "222", "333", "4", "555", "666", "777", "888", "999", "aaa", "bbb", "ccc",
+        "ddd", "eee", "fff", "ggg", "hhh", "iii", "jjj", "kkk", "lll", "mmm", "nnn",
+        "ooo", "ppp", "qqq", "rrr", "333", "ssss", "qqq", "rrr", "ssss",
+    ];
+    let val = strs.iter().map(|s| s.to_string()).collect::<Vec<String>>();
+    let val = val.into_cbor().unwrap();
+    let mut buf: Vec<u8> = vec![];
+    val.encode_with(&mut buf, &eopts).unwrap();
+    assert_eq!(buf[..3].to_vec(), vec![0xd9, 0x01, 0x00]);
+    let items = match Cbor::decode(&mut buf.as_slice()).unwrap().0 {
+        Cbor::Major6(_, Tag::StringRefNamespace(item)) => match *item {
+            Cbor::Major4(_, items) => items,
+            item => panic!("{:?}", item),
+        },
+        val => panic!("{:?}", val),
+    };
+    let sref = |index: u64| -> Cbor {
+        Tag::StringRef(Box::new(index.into_cbor().unwrap())).into()
+    };
+    assert_eq!(items[27], sref(1));
+    assert_eq!(items[29], sref(23));
+    assert_eq!(items[30], "rrr".to_string().into_cbor().unwrap());
+    assert_eq!(items[31], sref(24));
+    assert_eq!(Cbor::decode_with(&mut buf.as_slice(), &dopts).unwrap().0, val);
+
+    // map keys are referred to as well.
+    let item = vec![(Key::from("name"), "name".to_string().into_cbor().unwrap())];
+    let val = vec![item.clone(), item].into_cbor().unwrap();
+    let mut buf: Vec<u8> = vec![];
+    val.encode_with(&mut buf, &eopts).unwrap();
+    let out = [
+        0xd9, 0x01, 0x00, 0x82, 0xa1, 0x64, 0x6e, 0x61, 0x6d, 0x65, 0xd8, 0x19, 0x00,
+        0xa1, 0xd8, 0x19, 0x00, 0xd8, 0x19, 0x00,
+    ];
+    assert_eq!(buf, out.to_vec());
+    assert_eq!(Cbor::decode_with(&mut buf.as_slice(), &dopts).unwrap().0, val);
+
+    // invalid references.
+    let invalids: Vec<&[u8]> = vec![
+        &[0xd8, 0x19, 0x00],                   // 25(0) outside namespace
+        &[0xd9, 0x01, 0x00, 0xd8, 0x19, 0x00], // 256(25(0))
+        &[0xd9, 0x01, 0x00, 0x82, 0x61, 0x61, 0xd8, 0x19, 0x00], // too short
+        &[0xd9, 0x01, 0x00, 0xd8, 0x19, 0x61, 0x61], // 256(25("a"))
+    ];
+    for buf in invalids.into_iter() {
+        assert!(Cbor::decode_with(&mut &buf[..], &dopts).is_err(), "{:?}", buf);
+        assert!(Cbor::decode(&mut &buf[..]).is_ok(), "{:?}", buf);
+    }
+
+    let opts = DecodeOptions { lazy_depth: Some(1), ..dopts.clone() };
+    assert!(Cbor::decode_with(&mut [0x01].as_ref(), &opts).is_err());
+    let opts = EncodeOptions { deterministic: true, ..eopts };
+    assert!(val.encode_with(&mut vec![], &opts).is_err());
+}
+
+#[test]
+fn test_shared_values() {
+    use std::sync::Arc;
+
+    let eopts = EncodeOptions { share_values: true, ..EncodeOptions::default() };
+    let dopts = DecodeOptions { resolve_refs: true, ..DecodeOptions::default() };
+
+    // Arc is encoded as its value, unless shared by the encoder.
+    let mut buf: Vec<u8> = vec![];
+    Arc::new(10_u64).into_cbor().unwrap().encode(&mut buf).unwrap();
+    assert_eq!(buf, vec![0x0a]);
+
+    let name = Arc::new("hello world".to_string());
+    let names = vec![name.clone(), Arc::new("other".to_string()), name];
+    let val = names.clone().into_cbor().unwrap();
+    let mut buf: Vec<u8> = vec![];
+    val.encode_with(&mut buf, &eopts).unwrap();
+    let mut out = vec![0x83, 0xd8, 0x1c, 0x6b];
+    out.extend_from_slice(b"hello world");
+    out.push(0x65);
+    out.extend_from_slice(b"other");
+    out.extend_from_slice(&[0xd8, 0x1d, 0x00]);
+    assert_eq!(buf, out);
+
+    let (nval, _) = Cbor::decode_with(&mut buf.as_slice(), &dopts).unwrap();
+    assert_eq!(nval, val);
+    let nnames = Vec::<Arc<String>>::from_cbor(nval).unwrap();
+    assert_eq!(nnames, names);
+
+    // only wire size is shared, each decoded Arc is a new allocation, for
+    // references within the data item and across data items.
+    assert!(Arc::ptr_eq(&names[0], &names[2]));
+    assert!(!Arc::ptr_eq(&nnames[0], &nnames[2]));
+    let (nval, _) = Cbor::decode_with(&mut buf.as_slice(), &dopts).unwrap();
+    let other = Vec::<Arc<String>>::from_cbor(nval).unwrap();
+    assert!(!Arc::ptr_eq(&nnames[0], &other[0]));
+
+    // without resolution, items and references are decoded as is.
+    let (nval, _) = Cbor::decode(&mut buf.as_slice()).unwrap();
+    match nval {
+        Cbor::Major4(_, items) => {
+            assert!(matches!(items[1], Cbor::Major3(_, _)));
+            assert!(matches!(items[2], Cbor::Major6(_, Tag::SharedRef(_))));
+            let name = Arc::<String>::from_cbor(items[0].clone()).unwrap();
+            assert_eq!(name, names[0]);
+            assert!(Arc::<String>::from_cbor(items[2].clone()).is_err());
+        }
+        nval => panic!("{:?}", nval),
+    }
+
+    // repeated sub-trees are shared, nested items and map keys are not.
+    let item = Cbor::map(vec![(Key::from("a"), Cbor::array((1..5).map(Cbor::int)))]);
+    let val = Cbor::array(vec![item.clone(), item.clone(), item]);
+    let mut buf: Vec<u8> = vec![];
+    val.encode_with(&mut buf, &eopts).unwrap();
+    let out = [
+        0x83, 0xd8, 0x1c, 0xa1, 0x61, 0x61, 0x84, 0x01, 0x02, 0x03, 0x04, 0xd8, 0x1d,
+        0x00, 0xd8, 0x1d, 0x00,
+    ];
+    assert_eq!(buf, out.to_vec());
+    assert_eq!(Cbor::decode_with(&mut buf.as_slice(), &dopts).unwrap().0, val);
+
+    // nested shareable items are numbered after the enclosing item.
+    let inner: Cbor = Tag::Shareable(Box::new(1_u64.into_cbor().unwrap())).into();
+    let outer: Cbor =
+        Tag::Shareable(Box::new(vec![inner.clone()].into_cbor().unwrap())).into();
+    let val = vec![outer.clone(), inner, outer].into_cbor().unwrap();
+    let mut buf: Vec<u8> = vec![];
+    val.encode_with(&mut buf, &eopts).unwrap();
+    let out =
+        [0x83, 0xd8, 0x1c, 0x81, 0xd8, 0x1c, 0x01, 0xd8, 0x1d, 0x01, 0xd8, 0x1d, 0x00];
+    assert_eq!(buf, out.to_vec());
+    let refv = Cbor::array(vec![
+        Cbor::array(vec![Cbor::int(1)]),
+        Cbor::int(1),
+        Cbor::array(vec![Cbor::int(1)]),
+    ]);
+    assert_eq!(Cbor::decode_with(&mut buf.as_slice(), &dopts).unwrap().0, refv);
+
+    // resolved values count towards max_bytes.
+    let opts = DecodeOptions { max_bytes: out.len(), ..dopts.clone() };
+    assert!(Cbor::decode_with(&mut out.as_ref(), &opts).is_err());
+
+    // resolved values count towards max_depth, [28([[[1]]]), [[29(0)]]]
+    let buf = [0x82, 0xd8, 0x1c, 0x81, 0x81, 0x81, 0x01, 0x81, 0x81, 0xd8, 0x1d, 0x00];
+    let opts = DecodeOptions { max_depth: 6, ..dopts.clone() };
+    assert!(Cbor::decode(&mut buf.as_ref()).is_ok());
+    assert!(Cbor::decode_with(&mut buf.as_ref(), &opts).is_err());
+    let opts = DecodeOptions { max_depth: 7, ..dopts.clone() };
+    assert!(Cbor::decode_with(&mut buf.as_ref(), &opts).is_ok());
+
+    let invalids: Vec<&[u8]> = vec![
+        &[0xd8, 0x1d, 0x00],                   // 29(0) without shareable
+        &[0xd8, 0x1c, 0x81, 0xd8, 0x1d, 0x00], // 28([29(0)]), cyclic
+        &[0x82, 0xd8, 0x1c, 0x01, 0xd8, 0x1d, 0x01],
+    ];
+    for buf in invalids.into_iter() {
+        assert!(Cbor::decode_with(&mut &buf[..], &dopts).is_err(), "{:?}", buf);
+    }
+}
+
+#[test]
+fn test_set() {
+    use std::collections::{BTreeSet, HashSet};
+
+    let seed: u128 = random();
+    println!("test_set seed:{}", seed);
+    let mut rng = {
+        let mut rng_seed = [0; 32];
+        rng_seed[0..16].copy_from_slice(&seed.to_le_bytes());
+        StdRng::from_seed(rng_seed)
+    };
+
+    let opts = DecodeOptions { strict: true, ..DecodeOptions::default() };
+    for _i in 0..1000 {
+        let bytes: Vec<u8> = (0..100).flat_map(|_| rng.gen::<[u8; 32]>()).collect();
+        let mut uns = Unstructured::new(&bytes);
+
+        let set: HashSet<String> = uns.arbitrary().unwrap();
+        let mut buf: Vec<u8> = vec![];
+        set.clone().into_cbor().unwrap().encode(&mut buf).unwrap();
+        let (val, _) = Cbor::decode_with(&mut buf.as_slice(), &opts).unwrap();
+        assert!(matches!(val, Cbor::Major6(_, Tag::Set(_))));
+        assert_eq!(HashSet::<String>::from_cbor(val).unwrap(), set);
+
+        let set: BTreeSet<i64> = uns.arbitrary().unwrap();
+        let mut buf: Vec<u8> = vec![];
+        set.clone().into_cbor().unwrap().encode(&mut buf).unwrap();
+        let (val, _) = Cbor::decode_with(&mut buf.as_slice(), &opts).unwrap();
+        assert_eq!(BTreeSet::<i64>::from_cbor(val).unwrap(), set);
+    }
+
+    // 258([1, 2])
+    let buf = [0xd9, 0x01, 0x02, 0x82, 0x01, 0x02];
+    let val = BTreeSet::from([1_u64, 2]).into_cbor().unwrap();
+    let mut out: Vec<u8> = vec![];
+    val.encode(&mut out).unwrap();
+    assert_eq!(out, buf.to_vec());
+
+    // array is accepted as a set, duplicates are collapsed unless strict.
+    let val = vec![1_u64, 2, 1].into_cbor().unwrap();
+    assert_eq!(BTreeSet::<u64>::from_cbor(val).unwrap(), BTreeSet::from([1, 2]));
+    let buf = [0xd9, 0x01, 0x02, 0x83, 0x01, 0x02, 0x01];
+    let (val, _) = Cbor::decode(&mut buf.as_ref()).unwrap();
+    assert_eq!(HashSet::<u64>::from_cbor(val).unwrap(), HashSet::from([1, 2]));
+
+    let invalids: Vec<&[u8]> = vec![
+        &[0xd9, 0x01, 0x02, 0x83, 0x01, 0x02, 0x01],
+        &[0xd9, 0x01, 0x02, 0x82, 0x01, 0x18, 0x01], // same value, different width
+        &[0xd9, 0x01, 0x02, 0x01],                   // not an array
+    ];
+    for buf in invalids.into_iter() {
+        assert!(Cbor::decode_with(&mut &buf[..], &opts).is_err(), "{:?}", buf);
+        assert!(Cbor::decode(&mut &buf[..]).is_ok(), "{:?}", buf);
+    }
+    assert!(BTreeSet::<u64>::from_cbor(1_u64.into_cbor().unwrap()).is_err());
+}
+
+#[test]
+fn test_error() {
+    use std::error::Error as _;
+
+    // truncated argument, io error is chained as source.
+    let err = Cbor::decode(&mut [0x82, 0x01, 0x19, 0x01].as_ref()).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::UnexpectedEof, "{}", err);
+    assert_eq!(err.offset(), Some(3), "{}", err);
+    let source = err.source().unwrap();
+    assert!(source.downcast_ref::<io::Error>().is_some(), "{}", source);
+
+    let testcases: Vec<(Vec<u8>, ErrorKind, usize)> = vec![
+        (vec![0x82, 0x01, 0x63, 0x61], ErrorKind::UnexpectedEof, 6),
+        (vec![0x81, 0x1c], ErrorKind::InvalidHeader, 2),
+        (vec![0x82, 0x01, 0xdf, 0x01], ErrorKind::InvalidHeader, 3),
+        (vec![0x82, 0x01, 0xf7], ErrorKind::InvalidHeader, 3),
+    ];
+    for (buf, kind, offset) in testcases.into_iter() {
+        let err = Cbor::decode(&mut buf.as_slice()).unwrap_err();
+        assert_eq!(err.kind(), kind, "{:?} {}", buf, err);
+        assert_eq!(err.offset(), Some(offset), "{:?} {}", buf, err);
+        assert!(err.to_string().ends_with(&format!("at offset {}", offset)), "{}", err);
+    }
+
+    let opts = DecodeOptions { max_string: 1, ..DecodeOptions::default() };
+    let err = Cbor::decode_with(&mut [0x62, 0x61, 0x61].as_ref(), &opts).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::LimitExceeded);
+
+    let err = u8::from_cbor(256_u64.into_cbor().unwrap()).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::OutOfRange);
+    assert!(err.source().is_some());
+    assert_eq!(err.offset(), None);
+    let err = i64::from_cbor(Cbor::Major1(Info::U64, u64::MAX)).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::OutOfRange);
+    assert_eq!(i8::from_cbor(Cbor::Major1(Info::U8, 127)).unwrap(), -128);
+    let err = String::from_cbor(Cbor::Major3(Info::Tiny(2), vec![0xc3, 0x28]));
+    assert_eq!(err.unwrap_err().kind(), ErrorKind::Utf8);
+    let err = String::from_cbor(1_u64.into_cbor().unwrap()).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::TypeMismatch);
+    assert!(err.message().contains("not utf8-string"), "{}", err);
+
+    let val = vec![1_u64].into_cbor().unwrap();
+    let err = CborRef::decode(&[0x82, 0x01]).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::UnexpectedEof);
+    assert_eq!(err.offset(), Some(2));
+    let opts = EncodeOptions {
+        deterministic: true,
+        string_refs: true,
+        ..EncodeOptions::default()
+    };
+    let err = val.encode_with(&mut vec![], &opts).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::Unsupported);
+}
+
+#[test]
+fn test_error_path() {
+    use crate::{FromCbor, IntoCbor, LocalCborize};
+
+    #[derive(LocalCborize, Clone, Debug, PartialEq)]
+    struct Item {
+        name: String,
+        price: u64,
+    }
+
+    impl Item {
+        const ID: u32 = 1;
+    }
+
+    #[derive(LocalCborize, Clone, Debug, PartialEq)]
+    struct Order {
+        id: u64,
+        items: Vec<Item>,
+        note: Option<[u16; 2]>,
+    }
+
+    impl Order {
+        const ID: u32 = 2;
+    }
+
+    #[derive(LocalCborize, Clone, Debug, PartialEq)]
+    enum Shape {
+        Circle { radius: u8 },
+        Square(u8, u8),
+    }
+
+    impl Shape {
+        const ID: u32 = 3;
+    }
+
+    let item = |price: u64| Item { name: "pen".to_string(), price };
+    let mut order = Order {
+        id: 10,
+        items: (0..5).map(item).collect(),
+        note: None,
+    };
+    let val = order.clone().into_cbor().unwrap();
+    assert_eq!(Order::from_cbor(val).unwrap(), order);
+
+    // replace price of items[3] with a string.
+    let patch = |val: Cbor, path: &[usize]| -> Cbor {
+        fn patch(val: &mut Cbor, path: &[usize]) {
+            match (val, path) {
+                (val, []) => *val = "bad".into_cbor().unwrap(),
+                (Cbor::Major4(_, items), [i, rest @ ..]) => patch(&mut items[*i], rest),
+                _ => unreachable!(),
+            }
+        }
+        let mut val = val;
+        patch(&mut val, path);
+        val
+    };
+
+    let val = patch(order.clone().into_cbor().unwrap(), &[2, 3, 2]);
+    let err = Order::from_cbor(val).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::TypeMismatch);
+    assert_eq!(err.path(), Some("Order.items[3].price".to_string()));
+    assert!(err.to_string().ends_with(" in Order.items[3].price"), "{}", err);
+
+    order.note = Some([1, 2]);
+    let val = patch(order.into_cbor().unwrap(), &[3, 1]);
+    let err = Order::from_cbor(val).unwrap_err();
+    assert_eq!(err.path(), Some("Order.note[1]".to_string()));
+
+    let val = patch(Shape::Circle { radius: 1 }.into_cbor().unwrap(), &[2]);
+    let err = Shape::from_cbor(val).unwrap_err();
+    assert_eq!(err.path(), Some("Shape::Circle.radius".to_string()));
+
+    let val = patch(Shape::Square(1, 2).into_cbor().unwrap(), &[3]);
+    let err = Vec::<Shape>::from_cbor(vec![val].into_cbor().unwrap()).unwrap_err();
+    assert_eq!(err.path(), Some("[0].Shape::Square.1".to_string()));
+
+    let err = u8::from_cbor(300_u64.into_cbor().unwrap()).unwrap_err();
+    assert_eq!(err.path(), None);
 }
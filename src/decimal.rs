@@ -0,0 +1,163 @@
+use num_bigint::BigInt;
+use num_traits::{Signed, ToPrimitive};
+
+use crate::{Cbor, Error, FromCbor, IntoCbor, Result, Tag};
+
+/// Decimal fraction, `mantissa * 10^exponent`, encoded as tag 4.
+///
+/// Useful for exact values like money, `Decimal::new(12345, -2)` is `123.45`.
+/// Equality is structural, `12345e-2` and `123450e-3` are not equal.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Decimal {
+    pub mantissa: BigInt,
+    pub exponent: i64,
+}
+
+/// Binary floating point, `mantissa * 2^exponent`, encoded as tag 5.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct BigFloat {
+    pub mantissa: BigInt,
+    pub exponent: i64,
+}
+
+/// Rational number, `numerator / denominator`, encoded as tag 30.
+/// Denominator shall be positive.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Rational {
+    pub numerator: BigInt,
+    pub denominator: BigInt,
+}
+
+impl Decimal {
+    /// Construct decimal fraction `mantissa * 10^exponent`.
+    pub fn new<M: Into<BigInt>>(mantissa: M, exponent: i64) -> Decimal {
+        Decimal { mantissa: mantissa.into(), exponent }
+    }
+}
+
+impl BigFloat {
+    /// Construct bigfloat `mantissa * 2^exponent`.
+    pub fn new<M: Into<BigInt>>(mantissa: M, exponent: i64) -> BigFloat {
+        BigFloat { mantissa: mantissa.into(), exponent }
+    }
+}
+
+impl Rational {
+    /// Construct rational number `numerator / denominator`.
+    pub fn new<N: Into<BigInt>, D: Into<BigInt>>(
+        numerator: N,
+        denominator: D,
+    ) -> Rational {
+        Rational {
+            numerator: numerator.into(),
+            denominator: denominator.into(),
+        }
+    }
+}
+
+impl IntoCbor for Decimal {
+    fn into_cbor(self) -> Result<Cbor> {
+        let val = pair_into_cbor(self.exponent.into_cbor()?, self.mantissa)?;
+        Ok(Tag::Decimal(Box::new(val)).into())
+    }
+}
+
+impl FromCbor for Decimal {
+    fn from_cbor(val: Cbor) -> Result<Decimal> {
+        match val {
+            Cbor::Major6(_, Tag::Decimal(val)) => {
+                let (exponent, mantissa) = pair_from_cbor(*val)?;
+                let exponent = i64::from_cbor(exponent)?;
+                Ok(Decimal { mantissa: int_from_cbor(mantissa)?, exponent })
+            }
+            _ => err_at!(TypeMismatch, msg: "not a decimal"),
+        }
+    }
+}
+
+impl IntoCbor for BigFloat {
+    fn into_cbor(self) -> Result<Cbor> {
+        let val = pair_into_cbor(self.exponent.into_cbor()?, self.mantissa)?;
+        Ok(Tag::BigFloat(Box::new(val)).into())
+    }
+}
+
+impl FromCbor for BigFloat {
+    fn from_cbor(val: Cbor) -> Result<BigFloat> {
+        match val {
+            Cbor::Major6(_, Tag::BigFloat(val)) => {
+                let (exponent, mantissa) = pair_from_cbor(*val)?;
+                let exponent = i64::from_cbor(exponent)?;
+                Ok(BigFloat { mantissa: int_from_cbor(mantissa)?, exponent })
+            }
+            _ => err_at!(TypeMismatch, msg: "not a bigfloat"),
+        }
+    }
+}
+
+impl IntoCbor for Rational {
+    fn into_cbor(self) -> Result<Cbor> {
+        if !self.denominator.is_positive() {
+            let den = &self.denominator;
+            err_at!(InvalidData, msg: "rational denominator {} not positive", den)?
+        }
+        let val = pair_into_cbor(int_into_cbor(self.numerator)?, self.denominator)?;
+        Ok(Tag::Rational(Box::new(val)).into())
+    }
+}
+
+impl FromCbor for Rational {
+    fn from_cbor(val: Cbor) -> Result<Rational> {
+        match val {
+            Cbor::Major6(_, Tag::Rational(val)) => {
+                let (numerator, denominator) = pair_from_cbor(*val)?;
+                let val = Rational {
+                    numerator: int_from_cbor(numerator)?,
+                    denominator: int_from_cbor(denominator)?,
+                };
+                if !val.denominator.is_positive() {
+                    let den = &val.denominator;
+                    err_at!(InvalidData, msg: "rational denominator {} not positive", den)?
+                }
+                Ok(val)
+            }
+            _ => err_at!(TypeMismatch, msg: "not a rational"),
+        }
+    }
+}
+
+fn pair_into_cbor(first: Cbor, second: BigInt) -> Result<Cbor> {
+    vec![first, int_into_cbor(second)?].into_cbor()
+}
+
+fn pair_from_cbor(val: Cbor) -> Result<(Cbor, Cbor)> {
+    let mut items = Vec::<Cbor>::from_cbor(val)?.into_iter();
+    match (items.next(), items.next(), items.next()) {
+        (Some(first), Some(second), None) => Ok((first, second)),
+        _ => err_at!(TypeMismatch, msg: "expected array of two items"),
+    }
+}
+
+// Integers that fit within 64-bits are encoded as major type 0 or 1, others
+// as bignum.
+fn int_into_cbor(val: BigInt) -> Result<Cbor> {
+    if let Some(num) = val.to_u64() {
+        num.into_cbor()
+    } else if let Some(num) = (-&val - 1_u32).to_u64() {
+        Ok(Cbor::Major1(num.into(), num))
+    } else {
+        val.into_cbor()
+    }
+}
+
+fn int_from_cbor(val: Cbor) -> Result<BigInt> {
+    match val {
+        Cbor::Major0(_, num) => Ok(BigInt::from(num)),
+        Cbor::Major1(_, num) => Ok(-BigInt::from(num) - 1_u32),
+        val => BigInt::from_cbor(val),
+    }
+}
+
+#[cfg(test)]
+#[path = "decimal_test.rs"]
+mod decimal_test;
@@ -0,0 +1,101 @@
+use arbitrary::Unstructured;
+use rand::{prelude::random, rngs::StdRng, Rng, SeedableRng};
+
+use super::*;
+use crate::Info;
+
+#[test]
+fn test_decimal() {
+    let seed: u128 = random();
+    println!("test_decimal seed:{}", seed);
+    let mut rng = {
+        let mut rng_seed = [0; 32];
+        rng_seed[0..16].copy_from_slice(&seed.to_le_bytes());
+        StdRng::from_seed(rng_seed)
+    };
+
+    for _i in 0..10000 {
+        let bytes = rng.gen::<[u8; 32]>();
+        let mut uns = Unstructured::new(&bytes);
+        let mantissa: BigInt = uns.arbitrary().unwrap();
+        let other: BigInt = uns.arbitrary().unwrap();
+        let exponent: i64 = uns.arbitrary().unwrap();
+
+        let val = Decimal::new(mantissa.clone(), exponent);
+        assert_eq!(
+            Decimal::from_cbor(roundtrip(val.clone().into_cbor().unwrap())).unwrap(),
+            val
+        );
+
+        let val = BigFloat::new(mantissa.clone(), exponent);
+        assert_eq!(
+            BigFloat::from_cbor(roundtrip(val.clone().into_cbor().unwrap())).unwrap(),
+            val
+        );
+
+        let val = Rational::new(mantissa, other.abs() + 1_u32);
+        assert_eq!(
+            Rational::from_cbor(roundtrip(val.clone().into_cbor().unwrap())).unwrap(),
+            val
+        );
+    }
+}
+
+#[test]
+fn test_decimal_wire() {
+    // 4([-2, 27315]), RFC 8949 example for 273.15
+    let buf = [0xc4, 0x82, 0x21, 0x19, 0x6a, 0xb3];
+    let val = Cbor::decode(&mut buf.as_ref()).unwrap().0;
+    assert_eq!(Decimal::from_cbor(val).unwrap(), Decimal::new(27315, -2));
+    let mut out: Vec<u8> = vec![];
+    Decimal::new(27315, -2).into_cbor().unwrap().encode(&mut out).unwrap();
+    assert_eq!(out, buf.to_vec());
+
+    // 5([-1, 3]), RFC 8949 example for 1.5
+    let buf = [0xc5, 0x82, 0x20, 0x03];
+    let val = Cbor::decode(&mut buf.as_ref()).unwrap().0;
+    assert_eq!(BigFloat::from_cbor(val).unwrap(), BigFloat::new(3, -1));
+
+    // 30([1, 3])
+    let buf = [0xd8, 0x1e, 0x82, 0x01, 0x03];
+    let val = Cbor::decode(&mut buf.as_ref()).unwrap().0;
+    assert_eq!(Rational::from_cbor(val).unwrap(), Rational::new(1, 3));
+
+    // mantissa beyond 64-bits is encoded as bignum.
+    let mantissa = BigInt::from(u64::MAX) * 1000_u32;
+    let val = Decimal::new(mantissa.clone(), -3).into_cbor().unwrap();
+    match &val {
+        Cbor::Major6(_, Tag::Decimal(item)) => match item.as_ref() {
+            Cbor::Major4(_, items) => assert!(matches!(items[1], Cbor::Major6(_, _))),
+            item => panic!("{:?}", item),
+        },
+        val => panic!("{:?}", val),
+    }
+    assert_eq!(Decimal::from_cbor(val).unwrap(), Decimal::new(mantissa, -3));
+    let val = Decimal::new(-BigInt::from(u64::MAX) - 1, 0).into_cbor().unwrap();
+    assert_eq!(Decimal::from_cbor(val).unwrap().mantissa, -BigInt::from(u64::MAX) - 1);
+
+    assert!(Rational::new(1, 0).into_cbor().is_err());
+    assert!(Rational::new(1, -3).into_cbor().is_err());
+    let invalids: Vec<Cbor> = vec![
+        Tag::Rational(Box::new(vec![1_u64, 0].into_cbor().unwrap())).into(),
+        Tag::Decimal(Box::new(vec![1_u64].into_cbor().unwrap())).into(),
+        Tag::Decimal(Box::new(vec![1_u64, 2, 3].into_cbor().unwrap())).into(),
+        Tag::Decimal(Box::new(Cbor::Major0(Info::Tiny(1), 1))).into(),
+        Tag::BigFloat(Box::new(vec![1_u64, 2].into_cbor().unwrap())).into(),
+    ];
+    assert!(Rational::from_cbor(invalids[0].clone()).is_err());
+    assert!(Decimal::from_cbor(invalids[1].clone()).is_err());
+    assert!(Decimal::from_cbor(invalids[2].clone()).is_err());
+    assert!(Decimal::from_cbor(invalids[3].clone()).is_err());
+    assert!(Decimal::from_cbor(invalids[4].clone()).is_err());
+}
+
+fn roundtrip(val: Cbor) -> Cbor {
+    let mut buf: Vec<u8> = vec![];
+    let n = val.encode(&mut buf).unwrap();
+    let (nval, m) = Cbor::decode(&mut buf.as_slice()).unwrap();
+    assert_eq!(n, m);
+    assert_eq!(val, nval);
+    nval
+}
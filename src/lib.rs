@@ -15,7 +15,7 @@
 //! ========
 //!
 //! * **`arbitrary`** feature must be enabled, for [Cbor] and [Key] types to implement
-//! the `arbitrary::Arbitrary` trait.
+//!   the `arbitrary::Arbitrary` trait.
 //!
 //! [cbor]: https://tools.ietf.org/html/rfc7049
 
@@ -27,7 +27,7 @@ extern crate num_traits;
 #[cfg(test)]
 extern crate rand;
 
-use std::{error, fmt, result};
+use std::{backtrace::Backtrace, error, fmt, result};
 
 /// Short form to compose Error values.
 ///
@@ -35,31 +35,34 @@ use std::{error, fmt, result};
 ///
 /// ```ignore
 /// use crate::Error;
-/// err_at!(ParseError, msg: "bad argument");
+/// err_at!(TypeMismatch, msg: "bad argument");
 /// ```
 ///
 /// ```ignore
 /// use crate::Error;
-/// err_at!(ParseError, std::io::read(buf));
+/// err_at!(IOError, std::io::read(buf));
 /// ```
 ///
 /// ```ignore
 /// use crate::Error;
-/// err_at!(ParseError, std::fs::read(file_path), "read failed");
+/// err_at!(IOError, std::fs::read(file_path), "read failed");
 /// ```
 ///
+/// First argument is one of the [ErrorKind] variants. When composed from
+/// a failed result, the error is chained as [Error::source][error::Error::source].
 #[macro_export]
 macro_rules! err_at {
     ($v:ident, msg: $($arg:expr),+) => {{
         let prefix = format!("{}:{}", file!(), line!());
-        Err(Error::$v(prefix, format!($($arg),+)))
+        Err(Error::new($crate::ErrorKind::$v, prefix, format!($($arg),+)))
     }};
     ($v:ident, $e:expr) => {{
         match $e {
             Ok(val) => Ok(val),
             Err(err) => {
                 let prefix = format!("{}:{}", file!(), line!());
-                Err(Error::$v(prefix, format!("{}", err)))
+                let msg = format!("{}", err);
+                Err(Error::new($crate::ErrorKind::$v, prefix, msg).with_source(err))
             }
         }
     }};
@@ -68,52 +71,272 @@ macro_rules! err_at {
             Ok(val) => Ok(val),
             Err(err) => {
                 let prefix = format!("{}:{}", file!(), line!());
-                let msg = format!($($arg),+);
-                Err(Error::$v(prefix, format!("{} {}", err, msg)))
+                let msg = format!("{} {}", err, format!($($arg),+));
+                Err(Error::new($crate::ErrorKind::$v, prefix, msg).with_source(err))
             }
         }
     }};
 }
 
-/// Error variants that can be returned by this package's API.
+/// Category of [Error], applications can branch on this.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+#[non_exhaustive]
+pub enum ErrorKind {
+    /// Input ended before the data item was complete.
+    UnexpectedEof,
+    /// Error from the underlying reader or writer.
+    IOError,
+    /// Invalid header, like reserved additional-info, unassigned simple
+    /// value, misplaced break or indefinite length where not allowed.
+    InvalidHeader,
+    /// Text string is not valid UTF-8.
+    Utf8,
+    /// Recursion limit, or one of the [DecodeOptions] limits, exceeded.
+    LimitExceeded,
+    /// Cbor value is not of the type expected by the conversion.
+    TypeMismatch,
+    /// Value does not fit within the target type.
+    OutOfRange,
+    /// Data item is well-formed but not valid, like malformed tag content,
+    /// duplicate items or unresolved references.
+    InvalidData,
+    /// Key or index in a path is not found, refer [Cbor::pointer_mut].
+    NotFound,
+    /// Value cannot be encoded with the requested [EncodeOptions].
+    Unsupported,
+    /// Invalid use of the API, or an internal error.
+    Fatal,
+}
+
+/// Error type returned by this package's API.
 ///
-/// Each variant carries a prefix, typically identifying the
-/// error location.
-pub enum Error {
-    Fatal(String, String),
-    FailConvert(String, String),
-    IOError(String, String),
-    FailCbor(String, String),
+/// Carries the [ErrorKind], the source location where the error was
+/// composed, a message and, for decode errors, the input byte offset.
+/// Backtrace, when enabled via `RUST_BACKTRACE` or `RUST_LIB_BACKTRACE`, is
+/// captured as the error leaves the encode, decode, skip and validate entry
+/// points, and derived [FromCbor] conversions, so that errors handled within
+/// the package don't pay for it.
+pub struct Error {
+    inner: Box<ErrorInner>,
+}
+
+struct ErrorInner {
+    kind: ErrorKind,
+    prefix: String,
+    msg: String,
+    offset: Option<usize>,
+    root: Option<(String, usize)>, // type name and number of segments within it
+    path: Vec<PathSegment>,        // innermost segment first
+    backtrace: Option<Backtrace>,
+    source: Option<Box<dyn error::Error + Send + Sync>>,
+}
+
+impl Error {
+    /// Create a new error of `kind`, `prefix` typically identifies the
+    /// error location. Refer to [err_at] macro.
+    pub fn new(kind: ErrorKind, prefix: String, msg: String) -> Error {
+        let inner = ErrorInner {
+            kind,
+            prefix,
+            msg,
+            offset: None,
+            root: None,
+            path: Vec::default(),
+            backtrace: None,
+            source: None,
+        };
+        Error { inner: Box::new(inner) }
+    }
+
+    /// Chain the underlying cause of this error. [std::io::Error] of kind
+    /// `UnexpectedEof` turns an [ErrorKind::IOError] into
+    /// [ErrorKind::UnexpectedEof].
+    pub fn with_source<E>(mut self, err: E) -> Error
+    where
+        E: error::Error + Send + Sync + 'static,
+    {
+        let err: Box<dyn error::Error + Send + Sync> = Box::new(err);
+        if let Some(err) = err.downcast_ref::<std::io::Error>() {
+            if self.inner.kind == ErrorKind::IOError
+                && err.kind() == std::io::ErrorKind::UnexpectedEof
+            {
+                self.inner.kind = ErrorKind::UnexpectedEof
+            }
+        }
+        self.inner.source = Some(err);
+        self
+    }
+
+    /// Set the input byte offset, if not already set.
+    pub(crate) fn with_offset(mut self, offset: usize) -> Error {
+        self.inner.offset = self.inner.offset.or(Some(offset));
+        self
+    }
+
+    /// Capture backtrace, if not already captured.
+    pub(crate) fn with_backtrace(mut self) -> Error {
+        if self.inner.backtrace.is_none() {
+            self.inner.backtrace = Some(Backtrace::capture());
+        }
+        self
+    }
+
+    /// Prefix struct field `name` to the logical path of this error. Used
+    /// by `Cborize` generated code, as the error bubbles out of [FromCbor].
+    pub fn at_field(mut self, name: &str) -> Error {
+        self.inner.path.push(PathSegment::Field(name.to_string()));
+        self
+    }
+
+    /// Prefix array `index` to the logical path of this error. Used by
+    /// [FromCbor] implementation of arrays and vectors.
+    pub fn at_index(mut self, index: usize) -> Error {
+        self.inner.path.push(PathSegment::Index(index));
+        self
+    }
+
+    /// Set the type name at the root of the logical path, replacing the
+    /// name set by inner types. Segments prefixed after this, without a
+    /// type name of their own, are rendered before the type name, like
+    /// `[0].Shape::Square.1`. Backtrace is captured here, if not already.
+    pub fn at_type(mut self, name: &str) -> Error {
+        self.inner.root = Some((name.to_string(), self.inner.path.len()));
+        self.with_backtrace()
+    }
+
+    /// Return the logical path to the value that failed conversion, like
+    /// `Order.items[3].price`. None if error is not from within a struct,
+    /// enum, array or vector.
+    pub fn path(&self) -> Option<String> {
+        let inner = &self.inner;
+        if inner.root.is_none() && inner.path.is_empty() {
+            return None;
+        }
+
+        // segments outside the type are rendered before its name.
+        let (root, n) = match &inner.root {
+            Some((root, n)) => (Some(root), *n),
+            None => (None, 0),
+        };
+        let mut path = String::default();
+        for (i, segment) in inner.path.iter().enumerate().rev() {
+            if i + 1 == n {
+                push_field(&mut path, root.unwrap());
+            }
+            match segment {
+                PathSegment::Field(name) => push_field(&mut path, name),
+                PathSegment::Index(index) => path.push_str(&format!("[{}]", index)),
+            }
+        }
+        if let (Some(root), 0) = (root, n) {
+            push_field(&mut path, root);
+        }
+        Some(path)
+    }
+
+    /// Return the error category.
+    pub fn kind(&self) -> ErrorKind {
+        self.inner.kind
+    }
+
+    /// Return the error message.
+    pub fn message(&self) -> &str {
+        &self.inner.msg
+    }
+
+    /// Return the source location where the error was composed.
+    pub fn location(&self) -> &str {
+        &self.inner.prefix
+    }
+
+    /// Return the input byte offset, near which decoding failed.
+    pub fn offset(&self) -> Option<usize> {
+        self.inner.offset
+    }
+
+    /// Return the backtrace, if captured.
+    pub fn backtrace(&self) -> Option<&Backtrace> {
+        match &self.inner.backtrace {
+            Some(bt) if bt.status() == std::backtrace::BacktraceStatus::Captured => {
+                Some(bt)
+            }
+            _ => None,
+        }
+    }
+}
+
+enum PathSegment {
+    Field(String),
+    Index(usize),
+}
+
+fn push_field(path: &mut String, name: &str) {
+    if !path.is_empty() {
+        path.push('.');
+    }
+    path.push_str(name);
 }
 
 impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter) -> result::Result<(), fmt::Error> {
-        use Error::*;
-
-        match self {
-            Fatal(p, msg) => write!(f, "{} Fatal: {}", p, msg),
-            FailConvert(p, msg) => write!(f, "{} FailConvert: {}", p, msg),
-            IOError(p, msg) => write!(f, "{} IOError: {}", p, msg),
-            FailCbor(p, msg) => write!(f, "{} FailCbor: {}", p, msg),
+        let inner = &self.inner;
+        write!(f, "{} {:?}: {}", inner.prefix, inner.kind, inner.msg)?;
+        if let Some(path) = self.path() {
+            write!(f, " in {}", path)?;
+        }
+        match inner.offset {
+            Some(offset) => write!(f, " at offset {}", offset),
+            None => Ok(()),
         }
     }
 }
 
 impl fmt::Debug for Error {
     fn fmt(&self, f: &mut fmt::Formatter) -> result::Result<(), fmt::Error> {
-        write!(f, "{}", self)
+        write!(f, "{}", self)?;
+        match self.backtrace() {
+            Some(bt) => write!(f, "\n{}", bt),
+            None => Ok(()),
+        }
     }
 }
 
-impl error::Error for Error {}
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match &self.inner.source {
+            Some(err) => Some(err.as_ref()),
+            None => None,
+        }
+    }
+}
 
 #[doc(hidden)]
 pub use cbordata_derive::*;
 
+#[macro_use]
+mod macros;
+
 mod cbor;
+mod cbor_ref;
+mod decimal;
+mod pointer;
+pub mod query;
+pub mod stream;
+mod time;
+mod typed;
 mod types;
+mod validate;
 
-pub use cbor::{pretty_print, Cbor, Info, Key, SimpleValue, Tag, RECURSION_LIMIT};
+pub use cbor::{
+    f16_to_f32, f32_to_f16, pretty_print, Cbor, DecodeOptions, DuplicateKeys,
+    EncodeOptions, Info, Key, SimpleValue, Tag, RECURSION_LIMIT,
+};
+pub use cbor_ref::CborRef;
+pub use decimal::{BigFloat, Decimal, Rational};
+#[doc(hidden)]
+pub use macros::{cbor_hex, cbor_key, CborValue};
+pub use typed::{Endian, NdArray, TypedArray, TypedElement};
+pub use validate::{validate, ValidationReport};
 
 /// Get unique ID associated with user-defined type.
 ///
@@ -0,0 +1,168 @@
+/// Compose [Cbor][crate::Cbor] value from a JSON like literal.
+///
+/// ```
+/// use cbordata::{cbor, Cbor, Key};
+///
+/// let name = "x";
+/// let val = cbor!({ "name": name, "tags": [1, 2, null], 3: h"01ff", -1: 1.5 });
+///
+/// assert_eq!(val["name"].as_str(), Some("x"));
+/// assert_eq!(val["tags"][1].as_u64(), Some(2));
+/// assert!(val["tags"][2].is_null());
+/// assert_eq!(val.get(&Key::U64(3)).unwrap().as_bytes(), Some(&[1, 255][..]));
+/// assert_eq!(val.get(&Key::N64(-1)), Some(&Cbor::float(1.5)));
+/// ```
+///
+/// * `null`, array `[..]` and map `{..}` literals nest to any depth.
+/// * `h"01ff"` is byte string, given in hex. CBOR diagnostic notation uses
+///   single quotes, like `h'01ff'`, which is not a valid Rust token. From
+///   Rust 2021 edition onwards, write it with a space, like `h "01ff"`.
+/// * Map keys are literals, like `"name"`, `3`, `-1`, `true` and `h"01"`,
+///   or any value that converts to [Key][crate::Key], array and map values
+///   are keyed as [Key::Cbor][crate::Key::Cbor].
+/// * Any other expression is converted using [IntoCbor][crate::IntoCbor],
+///   [Cbor][crate::Cbor] values are taken as is. Note that integer literals
+///   default to `i32`, use suffix, like `5_000_000_000_u64`, or
+///   [Cbor::int][crate::Cbor::int] for larger numbers.
+///
+/// Arrays whose items, and maps whose keys and values, are single tokens,
+/// like literals, identifiers, `null`, nested `[..]` and `{..}`, expand in
+/// one step and can have any number of items. Items that span several
+/// tokens, like `-1`, `h"01"` or `a.b()`, are expanded one at a time and
+/// count towards the `recursion_limit`, wrap them in parentheses, like
+/// `(-1)`, to avoid that for large literals.
+///
+/// Panics if an expression fails conversion.
+#[macro_export]
+macro_rules! cbor {
+    // arrays, accumulate converted items until the closing bracket.
+    (@array [$($items:expr,)*]) => {
+        $crate::Cbor::array(vec![$($items,)*])
+    };
+    (@array [$($items:expr,)*] null $(, $($rest:tt)*)?) => {
+        $crate::cbor!(@array [$($items,)* $crate::cbor!(null),] $($($rest)*)?)
+    };
+    (@array [$($items:expr,)*] h $hex:literal $(, $($rest:tt)*)?) => {
+        $crate::cbor!(@array [$($items,)* $crate::cbor!(h $hex),] $($($rest)*)?)
+    };
+    (@array [$($items:expr,)*] [$($val:tt)*] $(, $($rest:tt)*)?) => {
+        $crate::cbor!(@array [$($items,)* $crate::cbor!([$($val)*]),] $($($rest)*)?)
+    };
+    (@array [$($items:expr,)*] {$($val:tt)*} $(, $($rest:tt)*)?) => {
+        $crate::cbor!(@array [$($items,)* $crate::cbor!({$($val)*}),] $($($rest)*)?)
+    };
+    (@array [$($items:expr,)*] $val:expr $(, $($rest:tt)*)?) => {
+        $crate::cbor!(@array [$($items,)* $crate::cbor!($val),] $($($rest)*)?)
+    };
+
+    // maps, accumulate key tokens until colon, and then the value.
+    (@map [$($entries:expr,)*] ()) => {
+        $crate::Cbor::map(vec![$($entries,)*])
+    };
+    (@map [$($entries:expr,)*] ($($key:tt)+) : null $(, $($rest:tt)*)?) => {
+        $crate::cbor!(
+            @map [$($entries,)* $crate::cbor!(@entry ($($key)+) null),] ()
+            $($($rest)*)?
+        )
+    };
+    (@map [$($entries:expr,)*] ($($key:tt)+) : h $hex:literal $(, $($rest:tt)*)?) => {
+        $crate::cbor!(
+            @map [$($entries,)* $crate::cbor!(@entry ($($key)+) h $hex),] ()
+            $($($rest)*)?
+        )
+    };
+    (@map [$($entries:expr,)*] ($($key:tt)+) : [$($val:tt)*] $(, $($rest:tt)*)?) => {
+        $crate::cbor!(
+            @map [$($entries,)* $crate::cbor!(@entry ($($key)+) [$($val)*]),] ()
+            $($($rest)*)?
+        )
+    };
+    (@map [$($entries:expr,)*] ($($key:tt)+) : {$($val:tt)*} $(, $($rest:tt)*)?) => {
+        $crate::cbor!(
+            @map [$($entries,)* $crate::cbor!(@entry ($($key)+) {$($val)*}),] ()
+            $($($rest)*)?
+        )
+    };
+    (@map [$($entries:expr,)*] ($($key:tt)+) : $val:expr $(, $($rest:tt)*)?) => {
+        $crate::cbor!(
+            @map [$($entries,)* $crate::cbor!(@entry ($($key)+) $val),] ()
+            $($($rest)*)?
+        )
+    };
+    (@map [$($entries:expr,)*] ($($key:tt)*) $next:tt $($rest:tt)*) => {
+        $crate::cbor!(@map [$($entries,)*] ($($key)* $next) $($rest)*)
+    };
+    (@entry ($($key:tt)+) $($val:tt)+) => {
+        ($crate::cbor_key($crate::cbor!($($key)+)), $crate::cbor!($($val)+))
+    };
+
+    (null) => {
+        $crate::Cbor::null()
+    };
+    (h $hex:literal) => {
+        $crate::cbor_hex($hex)
+    };
+    ([$($item:tt),* $(,)?]) => {
+        $crate::Cbor::array(vec![$($crate::cbor!($item),)*])
+    };
+    ([$($items:tt)*]) => {
+        $crate::cbor!(@array [] $($items)*)
+    };
+    ({$($key:tt : $val:tt),* $(,)?}) => {
+        $crate::Cbor::map(vec![$($crate::cbor!(@entry ($key) $val),)*])
+    };
+    ({$($entries:tt)*}) => {
+        $crate::cbor!(@map [] () $($entries)*)
+    };
+    ($val:expr) => {
+        $crate::CborValue::into_value($val)
+    };
+}
+
+use crate::{Cbor, FromCbor, IntoCbor, Key};
+
+/// Conversion used by [cbor!] macro for interpolated expressions.
+#[doc(hidden)]
+pub trait CborValue {
+    fn into_value(self) -> Cbor;
+}
+
+impl<T: IntoCbor> CborValue for T {
+    fn into_value(self) -> Cbor {
+        match self.into_cbor() {
+            Ok(val) => val,
+            Err(err) => panic!("cbor! {}", err),
+        }
+    }
+}
+
+impl CborValue for Cbor {
+    fn into_value(self) -> Cbor {
+        self
+    }
+}
+
+#[doc(hidden)]
+pub fn cbor_key(val: Cbor) -> Key {
+    match Key::from_cbor(val) {
+        Ok(key) => key,
+        Err(err) => panic!("cbor! {}", err),
+    }
+}
+
+#[doc(hidden)]
+pub fn cbor_hex(hex: &str) -> Cbor {
+    let digits: Option<Vec<u8>> =
+        hex.chars().map(|ch| ch.to_digit(16).map(|d| d as u8)).collect();
+    match digits {
+        Some(digits) if digits.chunks_exact(2).remainder().is_empty() => {
+            let byts = digits.chunks_exact(2).map(|d| (d[0] << 4) | d[1]);
+            Cbor::bytes(byts.collect::<Vec<u8>>())
+        }
+        _ => panic!("cbor! invalid hex {:?}", hex),
+    }
+}
+
+#[cfg(test)]
+#[path = "macros_test.rs"]
+mod macros_test;
@@ -0,0 +1,88 @@
+use crate::{Cbor, Info, IntoCbor, Key, SimpleValue, Tag};
+
+#[test]
+fn test_cbor_macro() {
+    let name = "x";
+    let tags = vec![1_u64, 2];
+    let val = cbor!({
+        "name": name,
+        "tags": [1, 2, null],
+        3: h"01ff",
+        -1: 1.5,
+        true: [],
+        h"02": {},
+        [1, 2]: {"a": [{"b": null}]},
+        (Key::from("key")): tags.clone(),
+        "nums": [-1, u64::MAX, Cbor::int(i128::MAX), true, false],
+        "tag": Cbor::tagged(1000, cbor!(h""))
+    });
+
+    let refv = Cbor::map(vec![
+        (Key::from("name"), Cbor::text("x")),
+        (Key::from("tags"), Cbor::array(vec![Cbor::int(1), Cbor::int(2), Cbor::null()])),
+        (Key::U64(3), Cbor::bytes(vec![1, 255])),
+        (Key::N64(-1), Cbor::float(1.5)),
+        (Key::Bool(true), Cbor::array(vec![])),
+        (Key::Bytes(vec![2]), Cbor::map(vec![])),
+        (
+            Key::Cbor(Box::new(Cbor::array(vec![Cbor::int(1), Cbor::int(2)]))),
+            Cbor::map(vec![(
+                Key::from("a"),
+                Cbor::array(vec![Cbor::map(vec![(Key::from("b"), Cbor::null())])]),
+            )]),
+        ),
+        (Key::from("key"), tags.into_cbor().unwrap()),
+        (
+            Key::from("nums"),
+            Cbor::array(vec![
+                Cbor::int(-1),
+                Cbor::int(u64::MAX.into()),
+                Cbor::int(i128::MAX),
+                Cbor::bool(true),
+                Cbor::bool(false),
+            ]),
+        ),
+        (Key::from("tag"), Tag::Other(1000, Box::new(Cbor::bytes(vec![]))).into()),
+    ]);
+    assert_eq!(val, refv);
+    val.check().unwrap();
+
+    assert_eq!(cbor!(null), SimpleValue::Null.into_cbor().unwrap());
+    assert_eq!(cbor!("text"), Cbor::text("text"));
+    assert_eq!(cbor!(h "0A0b"), Cbor::bytes(vec![10, 11]));
+    assert_eq!(cbor!([1, [2, [null,],],]), cbor!([1, [2, [null]]]));
+    assert_eq!(cbor!({"a": 1,}), cbor!({"a": 1}));
+    assert!(matches!(cbor!(vec![0; 24]), Cbor::Major4(Info::U8, _)));
+}
+
+#[test]
+fn test_cbor_macro_large() {
+    // single token items expand in one step, beyond the recursion limit.
+    let val = cbor!([
+        0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20, 21, 22,
+        23, 24, 25, 26, 27, 28, 29, 30, 31, 32, 33, 34, 35, 36, 37, 38, 39, 40, 41, 42,
+        43, 44, 45, 46, 47, 48, 49, 50, 51, 52, 53, 54, 55, 56, 57, 58, 59, 60, 61, 62,
+        63, 64, 65, 66, 67, 68, 69, 70, 71, 72, 73, 74, 75, 76, 77, 78, 79, 80, 81, 82,
+        83, 84, 85, 86, 87, 88, 89, 90, 91, 92, 93, 94, 95, 96, 97, 98, 99, 100, 101,
+        102, 103, 104, 105, 106, 107, 108, 109, 110, 111, 112, 113, 114, 115, 116, 117,
+        118, 119, 120, 121, 122, 123, 124, 125, 126, 127, 128, 129
+    ]);
+    assert_eq!(val, Cbor::array((0..130).map(Cbor::int)));
+
+    let val = cbor!({
+        "k0": 0, "k1": 1, "k2": 2, "k3": 3, "k4": 4, "k5": 5, "k6": 6, "k7": 7, "k8": 8, "k9": 9, "k10": 10, "k11": 11, "k12": 12, "k13": 13, "k14": 14, "k15": 15, "k16": 16, "k17": 17, "k18": 18, "k19": 19, "k20": 20, "k21": 21, "k22": 22, "k23": 23, "k24": 24, "k25": 25, "k26": 26, "k27": 27, "k28": 28, "k29": 29, "k30": 30, "k31": 31, "k32": 32, "k33": 33, "k34": 34, "k35": 35, "k36": 36, "k37": 37, "k38": 38, "k39": 39, "k40": 40, "k41": 41, "k42": 42, "k43": 43, "k44": 44, "k45": 45, "k46": 46, "k47": 47, "k48": 48, "k49": 49, "k50": 50, "k51": 51, "k52": 52, "k53": 53, "k54": 54, "k55": 55, "k56": 56, "k57": 57, "k58": 58, "k59": 59, "k60": 60, "k61": 61, "k62": 62, "k63": 63, "k64": 64, "k65": 65, "k66": 66, "k67": 67, "k68": 68, "k69": 69
+    });
+    let refv = (0..70).map(|i| (Key::from(format!("k{}", i)), Cbor::int(i)));
+    assert_eq!(val, Cbor::map(refv));
+
+    // same value with either expansion, -1 spans two tokens.
+    let x = 2_u64;
+    let val = cbor!([[null, {}], {"a": [true]}, (-1), x]);
+    assert_eq!(val, cbor!([[null, {}], {"a": [true]}, -1, x]));
+}
+
+#[test]
+#[should_panic]
+fn test_cbor_macro_hex() {
+    cbor!(h"123");
+}
@@ -0,0 +1,205 @@
+//! In-place updates of [Cbor] values, addressed by JSON-Pointer like paths.
+//!
+//! Path is either empty, referring to the value itself, or a sequence of
+//! `/` prefixed tokens, like `/orders/0/items`, with `~1` and `~0` escaping
+//! `/` and `~` within a token, refer RFC-6901. Tokens select items in an
+//! array by index, and values in a map by text key, or else by integer key.
+//! Tagged values are transparent, tokens apply on the tagged item.
+
+use std::convert::TryFrom;
+use std::mem;
+
+use crate::cbor::{untag, untag_mut};
+use crate::{Cbor, Error, Info, Key, Result};
+
+impl Cbor {
+    /// Return the value at `path`, None if `path` is not found.
+    pub fn pointer(&self, path: &str) -> Option<&Cbor> {
+        let mut val = self;
+        for token in tokens(path).ok()?.iter() {
+            val = match untag(val) {
+                Cbor::Major4(_, items) => items.get(parse_index(token)?)?,
+                Cbor::Major5(_, map) => &map[find_key(map, token)?].1,
+                _ => return None,
+            };
+        }
+        Some(val)
+    }
+
+    /// Return the value at `path` for in-place update, None if `path` is
+    /// not found. Updating the length of arrays and maps, via the returned
+    /// reference, shall leave [Info] inconsistent, use [Cbor::insert_at],
+    /// [Cbor::remove_at] and [Cbor::append_at] instead.
+    pub fn pointer_mut(&mut self, path: &str) -> Option<&mut Cbor> {
+        lookup_mut(self, &tokens(path).ok()?, path).ok()
+    }
+
+    /// Replace the value at `path` with `value`, and return the old value.
+    /// If `path` refers to a missing key in a map, a new entry is added with
+    /// text key, and None is returned.
+    pub fn set_at(&mut self, path: &str, value: Cbor) -> Result<Option<Cbor>> {
+        let tokens = tokens(path)?;
+        let (last, parent) = match tokens.split_last() {
+            Some((last, parent)) => (last, parent),
+            None => return Ok(Some(mem::replace(self, value))),
+        };
+
+        match untag_mut(lookup_mut(self, parent, path)?) {
+            Cbor::Major4(_, items) => match parse_index(last) {
+                Some(i) if i < items.len() => {
+                    Ok(Some(mem::replace(&mut items[i], value)))
+                }
+                _ => err_at!(NotFound, msg: "index {:?} in {:?}", last, path),
+            },
+            Cbor::Major5(info, map) => match find_key(map, last) {
+                Some(i) => Ok(Some(mem::replace(&mut map[i].1, value))),
+                None => {
+                    map.push((Key::Text(last.to_string()), value));
+                    update_info(info, map.len())?;
+                    Ok(None)
+                }
+            },
+            _ => err_at!(TypeMismatch, msg: "parent of {:?} not a container", path),
+        }
+    }
+
+    /// Insert `value` at `path`. For arrays, items from the index onwards
+    /// are shifted right, index `-` refers to the end of the array. For
+    /// maps, new entry is added with text key, fail if key already exists.
+    pub fn insert_at(&mut self, path: &str, value: Cbor) -> Result<()> {
+        let tokens = tokens(path)?;
+        let (last, parent) = match tokens.split_last() {
+            Some((last, parent)) => (last, parent),
+            None => err_at!(InvalidData, msg: "cannot insert at root")?,
+        };
+
+        match untag_mut(lookup_mut(self, parent, path)?) {
+            Cbor::Major4(info, items) => {
+                match parse_index(last) {
+                    _ if last == "-" => items.push(value),
+                    Some(i) if i <= items.len() => items.insert(i, value),
+                    _ => err_at!(OutOfRange, msg: "index {:?} in {:?}", last, path)?,
+                }
+                update_info(info, items.len())
+            }
+            Cbor::Major5(info, map) => match find_key(map, last) {
+                Some(_) => {
+                    err_at!(InvalidData, msg: "key {:?} exists in {:?}", last, path)
+                }
+                None => {
+                    map.push((Key::Text(last.to_string()), value));
+                    update_info(info, map.len())
+                }
+            },
+            _ => err_at!(TypeMismatch, msg: "parent of {:?} not a container", path),
+        }
+    }
+
+    /// Remove the value at `path` and return it. For arrays, items after
+    /// the index are shifted left.
+    pub fn remove_at(&mut self, path: &str) -> Result<Cbor> {
+        let tokens = tokens(path)?;
+        let (last, parent) = match tokens.split_last() {
+            Some((last, parent)) => (last, parent),
+            None => err_at!(InvalidData, msg: "cannot remove root")?,
+        };
+
+        match untag_mut(lookup_mut(self, parent, path)?) {
+            Cbor::Major4(info, items) => match parse_index(last) {
+                Some(i) if i < items.len() => {
+                    let val = items.remove(i);
+                    update_info(info, items.len())?;
+                    Ok(val)
+                }
+                _ => err_at!(NotFound, msg: "index {:?} in {:?}", last, path),
+            },
+            Cbor::Major5(info, map) => match find_key(map, last) {
+                Some(i) => {
+                    let (_, val) = map.remove(i);
+                    update_info(info, map.len())?;
+                    Ok(val)
+                }
+                None => err_at!(NotFound, msg: "key {:?} in {:?}", last, path),
+            },
+            _ => err_at!(TypeMismatch, msg: "parent of {:?} not a container", path),
+        }
+    }
+
+    /// Append `value` to the array at `path`.
+    pub fn append_at(&mut self, path: &str, value: Cbor) -> Result<()> {
+        let tokens = tokens(path)?;
+        match untag_mut(lookup_mut(self, &tokens, path)?) {
+            Cbor::Major4(info, items) => {
+                items.push(value);
+                update_info(info, items.len())
+            }
+            _ => err_at!(TypeMismatch, msg: "{:?} not an array", path),
+        }
+    }
+}
+
+fn tokens(path: &str) -> Result<Vec<String>> {
+    match path {
+        "" => Ok(vec![]),
+        path if path.starts_with('/') => {
+            let tokens = path[1..].split('/');
+            Ok(tokens.map(|t| t.replace("~1", "/").replace("~0", "~")).collect())
+        }
+        path => err_at!(InvalidData, msg: "path {:?} shall start with /", path),
+    }
+}
+
+// array index is a decimal number without leading zeros.
+fn parse_index(token: &str) -> Option<usize> {
+    match token.bytes().all(|b| b.is_ascii_digit()) {
+        true if token == "0" || !token.starts_with('0') => token.parse().ok(),
+        _ => None,
+    }
+}
+
+// first entry with text key `token`, else with integer key `token`.
+fn find_key(map: &[(Key, Cbor)], token: &str) -> Option<usize> {
+    let text = map.iter().position(|(key, _)| matches!(key, Key::Text(k) if k == token));
+    text.or_else(|| {
+        let num: i128 = token.parse().ok()?;
+        map.iter().position(|(key, _)| match key {
+            Key::U64(key) => i128::from(*key) == num,
+            Key::N64(key) => i128::from(*key) == num,
+            _ => false,
+        })
+    })
+}
+
+fn lookup_mut<'a>(
+    val: &'a mut Cbor,
+    tokens: &[String],
+    path: &str,
+) -> Result<&'a mut Cbor> {
+    let mut val = val;
+    for token in tokens.iter() {
+        val = match untag_mut(val) {
+            Cbor::Major4(_, items) => match parse_index(token) {
+                Some(i) if i < items.len() => &mut items[i],
+                _ => err_at!(NotFound, msg: "index {:?} in {:?}", token, path)?,
+            },
+            Cbor::Major5(_, map) => match find_key(map, token) {
+                Some(i) => &mut map[i].1,
+                None => err_at!(NotFound, msg: "key {:?} in {:?}", token, path)?,
+            },
+            _ => err_at!(TypeMismatch, msg: "{:?} in {:?} not a container", token, path)?,
+        };
+    }
+    Ok(val)
+}
+
+// indefinite length is retained.
+fn update_info(info: &mut Info, len: usize) -> Result<()> {
+    if *info != Info::Indefinite {
+        *info = Info::try_from(len)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+#[path = "pointer_test.rs"]
+mod pointer_test;
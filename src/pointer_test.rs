@@ -0,0 +1,126 @@
+use super::*;
+use crate::{ErrorKind, IntoCbor, SimpleValue, Tag};
+
+fn document() -> Cbor {
+    let items: Vec<Cbor> = (0..23_u64).map(|i| i.into_cbor().unwrap()).collect();
+    let misc = vec![
+        (Key::U64(7), "seven".into_cbor().unwrap()),
+        (Key::N64(-2), "minus two".into_cbor().unwrap()),
+        (Key::from("a/b"), "slash".into_cbor().unwrap()),
+        (Key::from("m~n"), "tilde".into_cbor().unwrap()),
+    ];
+    let set = Tag::Set(Box::new(vec![1_u64].into_cbor().unwrap()));
+    vec![
+        (Key::from("items"), items.into_cbor().unwrap()),
+        (Key::from("misc"), misc.into_cbor().unwrap()),
+        (Key::from("set"), set.into()),
+        (Key::from("stream"), Cbor::Major4(Info::Indefinite, vec![])),
+    ]
+    .into_cbor()
+    .unwrap()
+}
+
+#[test]
+fn test_pointer() {
+    let mut doc = document();
+
+    assert_eq!(doc.pointer(""), Some(&doc));
+    assert_eq!(doc.pointer("/items/22"), Some(&22_u64.into_cbor().unwrap()));
+    assert_eq!(doc.pointer("/misc/7").unwrap().as_str(), Some("seven"));
+    assert_eq!(doc.pointer("/misc/-2").unwrap().as_str(), Some("minus two"));
+    assert_eq!(doc.pointer("/misc/a~1b").unwrap().as_str(), Some("slash"));
+    assert_eq!(doc.pointer("/misc/m~0n").unwrap().as_str(), Some("tilde"));
+    assert_eq!(doc.pointer("/set/0").unwrap().as_u64(), Some(1));
+    assert_eq!(doc.pointer("/items/23"), None);
+    assert_eq!(doc.pointer("/items/01"), None);
+    assert_eq!(doc.pointer("/items/0/x"), None);
+    assert_eq!(doc.pointer("items"), None);
+
+    *doc.pointer_mut("/items/3").unwrap() = "three".into_cbor().unwrap();
+    assert_eq!(doc["items"][3].as_str(), Some("three"));
+    assert_eq!(doc.pointer_mut("/missing"), None);
+    doc.check().unwrap();
+}
+
+#[test]
+fn test_pointer_update() {
+    let mut doc = document();
+
+    // set, replace existing values and add new map entries.
+    let old = doc.set_at("/items/0", "zero".into_cbor().unwrap()).unwrap();
+    assert_eq!(old, Some(0_u64.into_cbor().unwrap()));
+    let old = doc.set_at("/misc/7", 7_u64.into_cbor().unwrap()).unwrap();
+    assert_eq!(old.unwrap().as_str(), Some("seven"));
+    assert_eq!(doc.set_at("/misc/new", true.into_cbor().unwrap()).unwrap(), None);
+    assert_eq!(doc["misc"]["new"], true.into_cbor().unwrap());
+    assert_eq!(doc["misc"].as_map().unwrap().len(), 5);
+    doc.check().unwrap();
+
+    // insert, crossing 23 items changes the width of array length.
+    doc.insert_at("/items/1", "one".into_cbor().unwrap()).unwrap();
+    assert!(matches!(doc["items"], Cbor::Major4(Info::U8, _)));
+    doc.insert_at("/items/-", "last".into_cbor().unwrap()).unwrap();
+    doc.insert_at("/items/25", "end".into_cbor().unwrap()).unwrap();
+    let n = doc["items"].as_array().unwrap().len();
+    assert_eq!(n, 26);
+    assert_eq!(doc["items"][1].as_str(), Some("one"));
+    assert_eq!(doc["items"][2].as_u64(), Some(1));
+    assert_eq!(doc["items"][24].as_str(), Some("last"));
+    doc.insert_at("/misc/other", 1_u64.into_cbor().unwrap()).unwrap();
+    doc.insert_at("/set/0", 0_u64.into_cbor().unwrap()).unwrap();
+    doc.check().unwrap();
+
+    // remove, array length shrinks back to tiny info.
+    for _ in 0..3 {
+        doc.remove_at("/items/0").unwrap();
+    }
+    assert!(matches!(doc["items"], Cbor::Major4(Info::Tiny(23), _)));
+    let val = doc.remove_at("/misc/-2").unwrap();
+    assert_eq!(val.as_str(), Some("minus two"));
+    assert!(doc["misc"]["-2"].is_null());
+    doc.check().unwrap();
+
+    // append, indefinite length is retained.
+    doc.append_at("/stream", 1_u64.into_cbor().unwrap()).unwrap();
+    doc.append_at("/set", 2_u64.into_cbor().unwrap()).unwrap();
+    assert_eq!(
+        doc["stream"],
+        Cbor::Major4(Info::Indefinite, vec![1_u64.into_cbor().unwrap()])
+    );
+    assert_eq!(doc.pointer("/set/2").unwrap().as_u64(), Some(2));
+    doc.check().unwrap();
+
+    // root.
+    let old = doc.set_at("", SimpleValue::Null.into_cbor().unwrap()).unwrap();
+    assert_eq!(old.unwrap()["items"].as_array().unwrap().len(), 23);
+    assert!(doc.is_null());
+}
+
+#[test]
+fn test_pointer_error() {
+    let mut doc = document();
+    let val = || 1_u64.into_cbor().unwrap();
+
+    let testcases: Vec<(Result<()>, ErrorKind)> = vec![
+        (doc.clone().set_at("items", val()).map(|_| ()), ErrorKind::InvalidData),
+        (doc.clone().set_at("/items/23", val()).map(|_| ()), ErrorKind::NotFound),
+        (doc.clone().set_at("/missing/x", val()).map(|_| ()), ErrorKind::NotFound),
+        (doc.clone().set_at("/items/0/x", val()).map(|_| ()), ErrorKind::TypeMismatch),
+        (doc.clone().insert_at("", val()), ErrorKind::InvalidData),
+        (doc.clone().insert_at("/items/24", val()), ErrorKind::OutOfRange),
+        (doc.clone().insert_at("/misc/7", val()), ErrorKind::InvalidData),
+        (doc.clone().remove_at("").map(|_| ()), ErrorKind::InvalidData),
+        (doc.clone().remove_at("/items/x").map(|_| ()), ErrorKind::NotFound),
+        (doc.clone().remove_at("/misc/8").map(|_| ()), ErrorKind::NotFound),
+        (doc.clone().append_at("/misc", val()), ErrorKind::TypeMismatch),
+        (doc.clone().append_at("/none", val()), ErrorKind::NotFound),
+    ];
+    for (i, (res, kind)) in testcases.into_iter().enumerate() {
+        assert_eq!(res.unwrap_err().kind(), kind, "case {}", i);
+    }
+
+    // failed updates leave the document as is.
+    let refv = doc.clone();
+    assert!(doc.insert_at("/items/99", val()).is_err());
+    assert_eq!(doc, refv);
+}
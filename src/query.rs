@@ -0,0 +1,929 @@
+//! Module implement JSONPath style queries over [Cbor] documents.
+//!
+//! A [Query] is parsed once and can be applied on any number of documents,
+//! [Query::select] returns the matching values, borrowed from the document,
+//! along with their path from the root.
+//!
+//! ```
+//! use cbordata::{query::Query, Cbor, IntoCbor, Key};
+//!
+//! let item = |sku: &str, qty: u64| {
+//!     let map = vec![(Key::from("sku"), sku.into_cbor()?), (Key::from("qty"), qty.into_cbor()?)];
+//!     map.into_cbor()
+//! };
+//! let items = vec![item("pen", 1)?, item("ink", 3)?].into_cbor()?;
+//! let order = vec![(Key::from("items"), items)].into_cbor()?;
+//! let doc = vec![(Key::from("orders"), vec![order].into_cbor()?)].into_cbor()?;
+//!
+//! let query = Query::parse("$.orders[*].items[?(@.qty > 2)].sku")?;
+//! let matches = query.select(&doc);
+//! assert_eq!(matches.len(), 1);
+//! assert_eq!(matches[0].value.as_str(), Some("ink"));
+//! assert_eq!(matches[0].to_path(), "$['orders'][0]['items'][1]['sku']");
+//! # Ok::<(), cbordata::Error>(())
+//! ```
+//!
+//! Syntax
+//! ======
+//!
+//! * `$`, root of the document, can be skipped, like `orders[0].sku`.
+//! * `.name`, `['name']`, `["name"]`, value for text key in a map.
+//! * `[1]`, `[-1]`, item at index in an array, counted from the end when
+//!   negative. On maps, value for the integer key.
+//! * `[true]`, `[1.5]`, `[null]`, `[h'0102']`, value for boolean, floating
+//!   point, null and byte-string key in a map.
+//! * `[c'820102']`, value for key of any type, given as hex of its CBOR
+//!   encoding, like the array `[1, 2]`.
+//! * `.*`, `[*]`, all items in an array or all values in a map.
+//! * `..`, recursive descent, like `..name`, `..*` or `..[0]`, apply the
+//!   selector on a value and all its descendants.
+//! * `[start:end:step]`, slice of an array, any of them can be skipped or
+//!   negative.
+//! * `['a', 1, 2:4]`, union of selectors.
+//! * `[?(expr)]`, items in an array or values in a map, for which `expr`
+//!   is true. `expr` is composed of relative paths like `@.qty`, absolute
+//!   paths like `$.limit`, literals, comparisons `==`, `!=`, `<`, `<=`,
+//!   `>`, `>=`, logical `&&`, `||`, `!` and parentheses. A path without
+//!   comparison is true when it selects a value. Paths in a comparison
+//!   shall select a single value, numbers are compared by value across
+//!   integers, bignums and floats.
+//!
+//! Tagged values are transparent, selectors apply on the tagged item. Lazily
+//! decoded [Cbor::Binary] values are not traversed. Parse errors are
+//! [ErrorKind::InvalidData][crate::ErrorKind], with offset into the query
+//! expression, refer [Error::offset]. Filters nested beyond
+//! [RECURSION_LIMIT] fail with [ErrorKind::LimitExceeded][crate::ErrorKind].
+
+use std::convert::TryFrom;
+use std::{cmp, fmt, str::FromStr};
+
+use crate::cbor::{cmp_items, untag};
+use crate::{
+    f16_to_f32, Cbor, Error, FromCbor, IntoCbor, Key, Result, SimpleValue,
+    RECURSION_LIMIT,
+};
+
+// Similar to err_at!, with offset `off` into the query expression.
+macro_rules! err_off {
+    ($v:ident, $off:expr, $($arg:expr),+) => {
+        err_at!($v, msg: $($arg),+).map_err(|err: Error| err.with_offset($off))
+    };
+}
+
+/// Parsed query expression, refer to [module][self] documentation for
+/// syntax.
+#[derive(Debug, Clone)]
+pub struct Query {
+    segments: Vec<Segment>,
+}
+
+/// Step from a value to one of its children, part of [Match::path].
+#[derive(Debug, Clone, PartialEq)]
+pub enum Step {
+    /// Item at index in an array.
+    Index(usize),
+    /// Value for key in a map.
+    Key(Key),
+}
+
+/// Value selected by a [Query].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Match<'a> {
+    /// Steps from the root of the document to `value`.
+    pub path: Vec<Step>,
+    /// Selected value.
+    pub value: &'a Cbor,
+}
+
+#[derive(Debug, Clone)]
+enum Segment {
+    Child(Vec<Selector>),
+    Descendant(Vec<Selector>),
+}
+
+#[derive(Debug, Clone)]
+enum Selector {
+    Wildcard,
+    Key(Key),
+    Float(f64),
+    Index(i64),
+    Slice(Option<i64>, Option<i64>, Option<i64>),
+    Filter(Expr),
+}
+
+#[derive(Debug, Clone)]
+enum Expr {
+    Or(Box<Expr>, Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Not(Box<Expr>),
+    Exists(Path),
+    Cmp(CmpOp, Operand, Operand),
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+enum CmpOp {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+#[derive(Debug, Clone)]
+enum Operand {
+    Path(Path),
+    Lit(Cbor),
+}
+
+// Path within filter expression, relative to `@` or absolute from `$`.
+#[derive(Debug, Clone)]
+struct Path {
+    absolute: bool,
+    segments: Vec<Segment>,
+}
+
+enum Num {
+    Int(i128),
+    Float(f64),
+}
+
+type Node<'a> = (Vec<Step>, &'a Cbor);
+
+impl Query {
+    /// Parse query expression `expr`.
+    pub fn parse(expr: &str) -> Result<Query> {
+        let mut p = Parser { src: expr.as_bytes(), off: 0, depth: 0 };
+        p.skip_ws();
+        let mut segments = vec![];
+        if !p.eat("$") && p.peek().map(is_name_byte) == Some(true) {
+            segments.push(Segment::Child(vec![p.name()?]));
+        }
+        segments.extend(p.segments()?);
+        p.skip_ws();
+        if p.off < p.src.len() {
+            err_off!(InvalidData, p.off, "unexpected {:?}", p.rest())?
+        }
+        Ok(Query { segments })
+    }
+
+    /// Apply query on `doc`, return matching values in document order.
+    pub fn select<'a>(&self, doc: &'a Cbor) -> Vec<Match<'a>> {
+        select_segments(&self.segments, doc, doc)
+            .into_iter()
+            .map(|(path, value)| Match { path, value })
+            .collect()
+    }
+}
+
+impl FromStr for Query {
+    type Err = Error;
+
+    fn from_str(expr: &str) -> Result<Query> {
+        Query::parse(expr)
+    }
+}
+
+/// Parse `expr` and apply it on `doc`, refer [Query::select].
+pub fn select<'a>(doc: &'a Cbor, expr: &str) -> Result<Vec<Match<'a>>> {
+    Ok(Query::parse(expr)?.select(doc))
+}
+
+impl<'a> Match<'a> {
+    /// Return the path in normalized form, like `$['orders'][0]['sku']`.
+    /// Normalized path is also a valid query, selecting this value.
+    pub fn to_path(&self) -> String {
+        let mut s = "$".to_string();
+        for step in self.path.iter() {
+            s.push_str(&step.to_string());
+        }
+        s
+    }
+}
+
+impl fmt::Display for Step {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Step::Index(index) => write!(f, "[{}]", index),
+            Step::Key(Key::Text(s)) => {
+                let s = s.replace('\\', "\\\\").replace('\'', "\\'");
+                write!(f, "['{}']", s)
+            }
+            Step::Key(Key::U64(num)) => write!(f, "[{}]", num),
+            Step::Key(Key::N64(num)) => write!(f, "[{}]", num),
+            Step::Key(Key::Bool(val)) => write!(f, "[{}]", val),
+            Step::Key(Key::F32(val)) if val.is_finite() => write!(f, "[{:?}]", val),
+            Step::Key(Key::F64(val)) if val.is_finite() => write!(f, "[{:?}]", val),
+            Step::Key(Key::Bytes(byts)) => write!(f, "[h'{}']", to_hex(byts)),
+            Step::Key(Key::Cbor(val)) if val.is_null() => write!(f, "[null]"),
+            Step::Key(key) => {
+                let mut buf: Vec<u8> = vec![];
+                match key.clone().into_cbor().and_then(|val| val.encode(&mut buf)) {
+                    Ok(_) => write!(f, "[c'{}']", to_hex(&buf)),
+                    Err(_) => Err(fmt::Error),
+                }
+            }
+        }
+    }
+}
+
+fn to_hex(byts: &[u8]) -> String {
+    byts.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+struct Parser<'a> {
+    src: &'a [u8],
+    off: usize,
+    depth: u32,
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&self) -> Option<u8> {
+        self.src.get(self.off).copied()
+    }
+
+    fn rest(&self) -> String {
+        String::from_utf8_lossy(&self.src[self.off..]).to_string()
+    }
+
+    fn skip_ws(&mut self) {
+        while let Some(b' ') | Some(b'\t') | Some(b'\n') | Some(b'\r') = self.peek() {
+            self.off += 1
+        }
+    }
+
+    fn eat(&mut self, token: &str) -> bool {
+        match self.src[self.off..].starts_with(token.as_bytes()) {
+            true => {
+                self.off += token.len();
+                true
+            }
+            false => false,
+        }
+    }
+
+    // similar to eat, but `word` shall not be followed by a name character.
+    fn eat_word(&mut self, word: &str) -> bool {
+        let next = self.src.get(self.off + word.len()).copied();
+        match next {
+            Some(b) if is_name_byte(b) => false,
+            _ => self.eat(word),
+        }
+    }
+
+    fn expect(&mut self, token: &str) -> Result<()> {
+        match self.eat(token) {
+            true => Ok(()),
+            false => err_off!(InvalidData, self.off, "expected {:?}", token),
+        }
+    }
+
+    fn segments(&mut self) -> Result<Vec<Segment>> {
+        let mut segments = vec![];
+        loop {
+            let off = self.off;
+            self.skip_ws();
+            if self.eat("..") {
+                let selectors = match self.peek() {
+                    Some(b'[') => self.bracket()?,
+                    Some(b'*') => {
+                        self.off += 1;
+                        vec![Selector::Wildcard]
+                    }
+                    _ => vec![self.name()?],
+                };
+                segments.push(Segment::Descendant(selectors));
+            } else if self.eat(".") {
+                let selector = match self.peek() {
+                    Some(b'*') => {
+                        self.off += 1;
+                        Selector::Wildcard
+                    }
+                    _ => self.name()?,
+                };
+                segments.push(Segment::Child(vec![selector]));
+            } else if self.peek() == Some(b'[') {
+                segments.push(Segment::Child(self.bracket()?));
+            } else {
+                self.off = off;
+                break Ok(segments);
+            }
+        }
+    }
+
+    fn name(&mut self) -> Result<Selector> {
+        let start = self.off;
+        while let Some(b) = self.peek() {
+            match is_name_byte(b) {
+                true => self.off += 1,
+                false => break,
+            }
+        }
+        if start == self.off {
+            err_off!(InvalidData, start, "expected name")?
+        }
+        // stops only at ascii bytes, hence on a char boundary.
+        let name = err_at!(Utf8, std::str::from_utf8(&self.src[start..self.off]))?;
+        Ok(Selector::Key(Key::Text(name.to_string())))
+    }
+
+    fn bracket(&mut self) -> Result<Vec<Selector>> {
+        self.expect("[")?;
+        let mut selectors = vec![];
+        loop {
+            self.skip_ws();
+            selectors.push(self.selector()?);
+            self.skip_ws();
+            if self.eat("]") {
+                break Ok(selectors);
+            }
+            self.expect(",")?;
+        }
+    }
+
+    fn selector(&mut self) -> Result<Selector> {
+        let start = self.off;
+        match self.peek() {
+            Some(b'*') => {
+                self.off += 1;
+                Ok(Selector::Wildcard)
+            }
+            Some(b'?') => {
+                self.off += 1;
+                Ok(Selector::Filter(self.expr()?))
+            }
+            Some(b':') | Some(b'-') | Some(b'0'..=b'9') => {
+                let num = self.number()?;
+                self.skip_ws();
+                match num {
+                    _ if self.peek() == Some(b':') => {
+                        self.off = start;
+                        self.slice()
+                    }
+                    // integer keys beyond i64, like u64::MAX, are map keys.
+                    Some(Num::Int(num)) => match u64::try_from(num) {
+                        Ok(key) if num > i128::from(i64::MAX) => {
+                            Ok(Selector::Key(Key::U64(key)))
+                        }
+                        _ => Ok(Selector::Index(to_i64(num, start)?)),
+                    },
+                    Some(Num::Float(num)) => Ok(Selector::Float(num)),
+                    None => err_off!(InvalidData, start, "expected number"),
+                }
+            }
+            _ => {
+                let val = self.literal()?;
+                Ok(Selector::Key(Key::from_cbor(val)?))
+            }
+        }
+    }
+
+    fn slice(&mut self) -> Result<Selector> {
+        let start = self.int()?;
+        self.skip_ws();
+        self.expect(":")?;
+        let end = self.int()?;
+        self.skip_ws();
+        let step = match self.eat(":") {
+            true => self.int()?,
+            false => None,
+        };
+        Ok(Selector::Slice(start, end, step))
+    }
+
+    fn int(&mut self) -> Result<Option<i64>> {
+        self.skip_ws();
+        let start = self.off;
+        match self.number()? {
+            Some(Num::Int(num)) => Ok(Some(to_i64(num, start)?)),
+            Some(Num::Float(_)) => err_off!(InvalidData, start, "expected integer"),
+            None => Ok(None),
+        }
+    }
+
+    fn number(&mut self) -> Result<Option<Num>> {
+        let start = self.off;
+        self.eat("-");
+        let digits = self.digits();
+        if digits == 0 {
+            self.off = start;
+            return Ok(None);
+        }
+
+        let mut float = false;
+        if self.peek() == Some(b'.')
+            && self.src.get(self.off + 1).map(u8::is_ascii_digit) == Some(true)
+        {
+            self.off += 1;
+            self.digits();
+            float = true;
+        }
+        if let Some(b'e') | Some(b'E') = self.peek() {
+            self.off += 1;
+            let _ = self.eat("+") || self.eat("-");
+            if self.digits() == 0 {
+                err_off!(InvalidData, self.off, "expected exponent")?
+            }
+            float = true;
+        }
+
+        let text = String::from_utf8_lossy(&self.src[start..self.off]).to_string();
+        let num = match float {
+            true => Num::Float(err_at!(InvalidData, text.parse::<f64>())?),
+            false => match text.parse::<i128>() {
+                Ok(num) => Num::Int(num),
+                Err(_) => err_off!(OutOfRange, start, "integer {} out of range", text)?,
+            },
+        };
+        Ok(Some(num))
+    }
+
+    fn digits(&mut self) -> usize {
+        let start = self.off;
+        while let Some(b'0'..=b'9') = self.peek() {
+            self.off += 1
+        }
+        self.off - start
+    }
+
+    fn literal(&mut self) -> Result<Cbor> {
+        let start = self.off;
+        match self.peek() {
+            Some(b'\'') | Some(b'"') => self.string()?.into_cbor(),
+            Some(b'-') | Some(b'0'..=b'9') => match self.number()? {
+                Some(Num::Int(num)) => int_into_cbor(num),
+                Some(Num::Float(num)) => num.into_cbor(),
+                None => err_off!(InvalidData, start, "expected number"),
+            },
+            _ if self.eat_word("true") => true.into_cbor(),
+            _ if self.eat_word("false") => false.into_cbor(),
+            _ if self.eat_word("null") => SimpleValue::Null.into_cbor(),
+            _ if self.eat("h'") => Cbor::from_bytes(self.hex()?),
+            _ if self.eat("c'") => {
+                let byts = self.hex()?;
+                match Cbor::decode(&mut byts.as_slice()) {
+                    Ok((val, n)) if n == byts.len() => Ok(val),
+                    _ => err_off!(InvalidData, start, "invalid cbor literal"),
+                }
+            }
+            _ => err_off!(InvalidData, start, "expected literal"),
+        }
+    }
+
+    // hex digits, in pairs, until closing quote.
+    fn hex(&mut self) -> Result<Vec<u8>> {
+        let start = self.off;
+        while let Some(b) = self.peek() {
+            match b.is_ascii_hexdigit() {
+                true => self.off += 1,
+                false => break,
+            }
+        }
+        let digits = &self.src[start..self.off];
+        self.expect("'")?;
+        let pairs = digits.chunks_exact(2);
+        if !pairs.remainder().is_empty() {
+            err_off!(InvalidData, start, "odd number of hex digits")?
+        }
+        let byts = pairs
+            .map(|pair| {
+                let pair = std::str::from_utf8(pair).unwrap();
+                u8::from_str_radix(pair, 16).unwrap()
+            })
+            .collect();
+        Ok(byts)
+    }
+
+    // four hex digits of a unicode escape.
+    fn hex4(&mut self) -> Option<u32> {
+        let hex = self.src.get(self.off..(self.off + 4))?;
+        let hex = std::str::from_utf8(hex).ok()?;
+        let num = u32::from_str_radix(hex, 16).ok()?;
+        self.off += 4;
+        Some(num)
+    }
+
+    fn string(&mut self) -> Result<String> {
+        let start = self.off;
+        let quote = self.peek();
+        self.off += 1;
+
+        let mut out: Vec<u8> = vec![];
+        loop {
+            let off = self.off;
+            let b = match self.peek() {
+                None => err_off!(InvalidData, start, "unterminated string")?,
+                b if b == quote => {
+                    self.off += 1;
+                    break;
+                }
+                Some(b'\\') => {
+                    self.off += 1;
+                    let b = self.peek();
+                    self.off += 1;
+                    match b {
+                        Some(b'\\') | Some(b'/') | Some(b'\'') | Some(b'"') => b.unwrap(),
+                        Some(b'b') => 0x08,
+                        Some(b'f') => 0x0c,
+                        Some(b'n') => b'\n',
+                        Some(b'r') => b'\r',
+                        Some(b't') => b'\t',
+                        Some(b'u') => {
+                            let ch = match self.hex4() {
+                                // surrogate pair, like \uD83D\uDE00
+                                Some(hi @ 0xD800..=0xDBFF) if self.eat("\\u") => {
+                                    match self.hex4() {
+                                        Some(lo @ 0xDC00..=0xDFFF) => {
+                                            let ch =
+                                                ((hi - 0xD800) << 10) | (lo - 0xDC00);
+                                            char::from_u32(ch + 0x10000)
+                                        }
+                                        _ => None,
+                                    }
+                                }
+                                Some(ch) => char::from_u32(ch),
+                                None => None,
+                            };
+                            match ch {
+                                Some(ch) => {
+                                    let mut scratch = [0_u8; 4];
+                                    let s = ch.encode_utf8(&mut scratch);
+                                    out.extend_from_slice(s.as_bytes());
+                                    continue;
+                                }
+                                None => {
+                                    err_off!(InvalidData, off, "invalid unicode escape")?
+                                }
+                            }
+                        }
+                        _ => err_off!(InvalidData, off, "invalid escape")?,
+                    }
+                }
+                Some(b) => {
+                    self.off += 1;
+                    b
+                }
+            };
+            out.push(b);
+        }
+
+        err_at!(Utf8, String::from_utf8(out))
+    }
+
+    fn expr(&mut self) -> Result<Expr> {
+        let mut expr = self.and_expr()?;
+        loop {
+            self.skip_ws();
+            match self.eat("||") {
+                true => expr = Expr::Or(Box::new(expr), Box::new(self.and_expr()?)),
+                false => break Ok(expr),
+            }
+        }
+    }
+
+    fn and_expr(&mut self) -> Result<Expr> {
+        let mut expr = self.unary_expr()?;
+        loop {
+            self.skip_ws();
+            match self.eat("&&") {
+                true => expr = Expr::And(Box::new(expr), Box::new(self.unary_expr()?)),
+                false => break Ok(expr),
+            }
+        }
+    }
+
+    fn unary_expr(&mut self) -> Result<Expr> {
+        self.skip_ws();
+        // every nested filter, group and negation passes through here.
+        if self.depth >= RECURSION_LIMIT {
+            err_off!(LimitExceeded, self.off, "filter nesting limit exceeded")?
+        }
+        self.depth += 1;
+        let expr = self.do_unary_expr();
+        self.depth -= 1;
+        expr
+    }
+
+    fn do_unary_expr(&mut self) -> Result<Expr> {
+        if self.eat("!") {
+            Ok(Expr::Not(Box::new(self.unary_expr()?)))
+        } else if self.eat("(") {
+            let expr = self.expr()?;
+            self.skip_ws();
+            self.expect(")")?;
+            Ok(expr)
+        } else {
+            self.cmp_expr()
+        }
+    }
+
+    fn cmp_expr(&mut self) -> Result<Expr> {
+        let start = self.off;
+        let lhs = self.operand()?;
+        self.skip_ws();
+
+        let ops = [
+            ("==", CmpOp::Eq),
+            ("!=", CmpOp::Ne),
+            ("<=", CmpOp::Le),
+            (">=", CmpOp::Ge),
+            ("<", CmpOp::Lt),
+            (">", CmpOp::Gt),
+        ];
+        match ops.iter().find(|(token, _)| self.eat(token)) {
+            Some((_, op)) => Ok(Expr::Cmp(*op, lhs, self.operand()?)),
+            None => match lhs {
+                Operand::Path(path) => Ok(Expr::Exists(path)),
+                Operand::Lit(_) => {
+                    err_off!(InvalidData, start, "literal without comparison")
+                }
+            },
+        }
+    }
+
+    fn operand(&mut self) -> Result<Operand> {
+        self.skip_ws();
+        if self.eat("@") {
+            let segments = self.segments()?;
+            Ok(Operand::Path(Path { absolute: false, segments }))
+        } else if self.eat("$") {
+            let segments = self.segments()?;
+            Ok(Operand::Path(Path { absolute: true, segments }))
+        } else {
+            Ok(Operand::Lit(self.literal()?))
+        }
+    }
+}
+
+fn is_name_byte(b: u8) -> bool {
+    b.is_ascii_alphanumeric() || b == b'_' || b == b'-' || b >= 0x80
+}
+
+fn to_i64(num: i128, off: usize) -> Result<i64> {
+    match i64::try_from(num) {
+        Ok(num) => Ok(num),
+        Err(_) => err_off!(OutOfRange, off, "integer {} out of range", num),
+    }
+}
+
+fn int_into_cbor(num: i128) -> Result<Cbor> {
+    if let Ok(num) = u64::try_from(num) {
+        num.into_cbor()
+    } else if let Ok(num) = i64::try_from(num) {
+        num.into_cbor()
+    } else {
+        num.into_cbor()
+    }
+}
+
+fn select_segments<'a>(
+    segments: &[Segment],
+    cur: &'a Cbor,
+    root: &'a Cbor,
+) -> Vec<Node<'a>> {
+    let mut nodes: Vec<Node<'a>> = vec![(vec![], cur)];
+    for segment in segments.iter() {
+        let mut out = vec![];
+        for node in nodes.iter() {
+            match segment {
+                Segment::Child(selectors) => {
+                    for selector in selectors.iter() {
+                        select_child(selector, node, root, &mut out)
+                    }
+                }
+                Segment::Descendant(selectors) => {
+                    select_descendant(selectors, node, root, &mut out)
+                }
+            }
+        }
+        nodes = out;
+    }
+    nodes
+}
+
+// apply `selectors` on node and all its descendants, in document order.
+fn select_descendant<'a>(
+    selectors: &[Selector],
+    node: &Node<'a>,
+    root: &'a Cbor,
+    out: &mut Vec<Node<'a>>,
+) {
+    for selector in selectors.iter() {
+        select_child(selector, node, root, out)
+    }
+    for (step, val) in children(node.1).into_iter() {
+        let mut path = node.0.clone();
+        path.push(step);
+        select_descendant(selectors, &(path, val), root, out)
+    }
+}
+
+fn select_child<'a>(
+    selector: &Selector,
+    node: &Node<'a>,
+    root: &'a Cbor,
+    out: &mut Vec<Node<'a>>,
+) {
+    let mut push = |step: Step, val: &'a Cbor| {
+        let mut path = node.0.clone();
+        path.push(step);
+        out.push((path, val));
+    };
+
+    let val = untag(node.1);
+    match (selector, val) {
+        (Selector::Wildcard, _) => {
+            for (step, val) in children(val).into_iter() {
+                push(step, val)
+            }
+        }
+        (Selector::Filter(expr), _) => {
+            for (step, val) in children(val).into_iter() {
+                if expr.eval(val, root) {
+                    push(step, val)
+                }
+            }
+        }
+        (Selector::Index(index), Cbor::Major4(_, items)) => {
+            let index = match *index {
+                index if index < 0 => items.len() as i64 + index,
+                index => index,
+            };
+            if let Some(val) = usize::try_from(index).ok().and_then(|i| items.get(i)) {
+                push(Step::Index(index as usize), val)
+            }
+        }
+        (Selector::Slice(start, end, step), Cbor::Major4(_, items)) => {
+            for i in slice_indices(items.len() as i64, *start, *end, *step) {
+                push(Step::Index(i), &items[i])
+            }
+        }
+        (selector, Cbor::Major5(_, map)) => {
+            for (key, val) in map.iter() {
+                let ok = match (selector, key) {
+                    (Selector::Key(k), key) => k == key,
+                    (Selector::Index(n), Key::U64(key)) => *n >= 0 && *n as u64 == *key,
+                    (Selector::Index(n), Key::N64(key)) => n == key,
+                    (Selector::Float(f), Key::F32(key)) => *key == *f as f32,
+                    (Selector::Float(f), Key::F64(key)) => key == f,
+                    (Selector::Float(f), Key::Cbor(key)) => match key.as_ref() {
+                        Cbor::Major7(_, SimpleValue::F16(key)) => {
+                            f64::from(f16_to_f32(*key)) == *f
+                        }
+                        _ => false,
+                    },
+                    (_, _) => false,
+                };
+                if ok {
+                    push(Step::Key(key.clone()), val)
+                }
+            }
+        }
+        (_, _) => (),
+    }
+}
+
+// same as slice in RFC-9535, step defaults to 1 and step 0 selects nothing.
+fn slice_indices(
+    len: i64,
+    start: Option<i64>,
+    end: Option<i64>,
+    step: Option<i64>,
+) -> Vec<usize> {
+    let norm = |i: i64| if i >= 0 { i } else { len + i };
+    let step = step.unwrap_or(1);
+
+    let mut indices = vec![];
+    if step > 0 {
+        let lower = norm(start.unwrap_or(0)).clamp(0, len);
+        let upper = norm(end.unwrap_or(len)).clamp(0, len);
+        let mut i = lower;
+        while i < upper {
+            indices.push(i as usize);
+            i = match i.checked_add(step) {
+                Some(i) => i,
+                None => break,
+            };
+        }
+    } else if step < 0 {
+        let upper = norm(start.unwrap_or(len - 1)).clamp(-1, len - 1);
+        let lower = norm(end.unwrap_or(-len - 1)).clamp(-1, len - 1);
+        let mut i = upper;
+        while lower < i {
+            indices.push(i as usize);
+            i = match i.checked_add(step) {
+                Some(i) => i,
+                None => break,
+            };
+        }
+    }
+    indices
+}
+
+fn children(val: &Cbor) -> Vec<(Step, &Cbor)> {
+    match untag(val) {
+        Cbor::Major4(_, items) => {
+            items.iter().enumerate().map(|(i, val)| (Step::Index(i), val)).collect()
+        }
+        Cbor::Major5(_, map) => {
+            map.iter().map(|(key, val)| (Step::Key(key.clone()), val)).collect()
+        }
+        _ => vec![],
+    }
+}
+
+impl Expr {
+    fn eval(&self, cur: &Cbor, root: &Cbor) -> bool {
+        match self {
+            Expr::Or(a, b) => a.eval(cur, root) || b.eval(cur, root),
+            Expr::And(a, b) => a.eval(cur, root) && b.eval(cur, root),
+            Expr::Not(expr) => !expr.eval(cur, root),
+            Expr::Exists(path) => !path.select(cur, root).is_empty(),
+            Expr::Cmp(op, a, b) => compare(*op, a.value(cur, root), b.value(cur, root)),
+        }
+    }
+}
+
+impl Path {
+    fn select<'a>(&self, cur: &'a Cbor, root: &'a Cbor) -> Vec<Node<'a>> {
+        let start = if self.absolute { root } else { cur };
+        select_segments(&self.segments, start, root)
+    }
+}
+
+impl Operand {
+    // None, if path does not select exactly one value.
+    fn value<'a>(&'a self, cur: &'a Cbor, root: &'a Cbor) -> Option<&'a Cbor> {
+        match self {
+            Operand::Lit(val) => Some(val),
+            Operand::Path(path) => {
+                let mut nodes = path.select(cur, root);
+                match nodes.len() {
+                    1 => nodes.pop().map(|(_, val)| val),
+                    _ => None,
+                }
+            }
+        }
+    }
+}
+
+// comparison with a missing value is true only for `==`, `<=` and `>=`
+// on two missing values, as per RFC-9535.
+fn compare(op: CmpOp, a: Option<&Cbor>, b: Option<&Cbor>) -> bool {
+    let ord = match (a, b) {
+        (None, None) => Some(cmp::Ordering::Equal),
+        (Some(a), Some(b)) => order(a, b),
+        (_, _) => None,
+    };
+    match op {
+        CmpOp::Eq => ord == Some(cmp::Ordering::Equal),
+        CmpOp::Ne => ord != Some(cmp::Ordering::Equal),
+        CmpOp::Lt => ord == Some(cmp::Ordering::Less),
+        CmpOp::Le => {
+            matches!(ord, Some(cmp::Ordering::Less) | Some(cmp::Ordering::Equal))
+        }
+        CmpOp::Gt => ord == Some(cmp::Ordering::Greater),
+        CmpOp::Ge => {
+            matches!(ord, Some(cmp::Ordering::Greater) | Some(cmp::Ordering::Equal))
+        }
+    }
+}
+
+// numbers, text and bytes are ordered, other values are only compared for
+// equality.
+fn order(a: &Cbor, b: &Cbor) -> Option<cmp::Ordering> {
+    match (to_num(a), to_num(b)) {
+        (Some(Num::Int(x)), Some(Num::Int(y))) => Some(x.cmp(&y)),
+        (Some(x), Some(y)) => x.to_f64().partial_cmp(&y.to_f64()),
+        (_, _) => match (a, b) {
+            (Cbor::Major3(_, x), Cbor::Major3(_, y)) => Some(x.cmp(y)),
+            (Cbor::Major2(_, x), Cbor::Major2(_, y)) => Some(x.cmp(y)),
+            (a, b) if cmp_items(a, b) == cmp::Ordering::Equal => {
+                Some(cmp::Ordering::Equal)
+            }
+            (_, _) => None,
+        },
+    }
+}
+
+fn to_num(val: &Cbor) -> Option<Num> {
+    match val {
+        Cbor::Major7(_, SimpleValue::F16(f)) => Some(Num::Float(f16_to_f32(*f).into())),
+        Cbor::Major7(_, SimpleValue::F32(f)) => Some(Num::Float((*f).into())),
+        Cbor::Major7(_, SimpleValue::F64(f)) => Some(Num::Float(*f)),
+        val => val.as_i128().map(Num::Int),
+    }
+}
+
+impl Num {
+    fn to_f64(&self) -> f64 {
+        match self {
+            Num::Int(num) => *num as f64,
+            Num::Float(num) => *num,
+        }
+    }
+}
+
+#[cfg(test)]
+#[path = "query_test.rs"]
+mod query_test;
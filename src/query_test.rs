@@ -0,0 +1,228 @@
+use super::*;
+use crate::{ErrorKind, Tag};
+
+fn text_map(entries: Vec<(&str, Cbor)>) -> Cbor {
+    let map: Vec<(Key, Cbor)> =
+        entries.into_iter().map(|(k, v)| (Key::from(k), v)).collect();
+    map.into_cbor().unwrap()
+}
+
+fn item(sku: &str, qty: i64, price: f64) -> Cbor {
+    text_map(vec![
+        ("sku", sku.into_cbor().unwrap()),
+        ("qty", qty.into_cbor().unwrap()),
+        ("price", price.into_cbor().unwrap()),
+    ])
+}
+
+fn document() -> Cbor {
+    let order1 = text_map(vec![
+        ("id", 1_u64.into_cbor().unwrap()),
+        ("items", vec![item("pen", 1, 1.5), item("ink", 3, 4.0)].into_cbor().unwrap()),
+    ]);
+    let items = vec![item("pad", 5, 2.25), item("pin", 2, 0.5), item("cap", 10, 7.0)];
+    let order2 = text_map(vec![
+        ("id", 2_u64.into_cbor().unwrap()),
+        ("items", items.into_cbor().unwrap()),
+        ("note", SimpleValue::Null.into_cbor().unwrap()),
+    ]);
+    let misc: Vec<(Key, Cbor)> = vec![
+        (Key::U64(1), "one".into_cbor().unwrap()),
+        (Key::N64(-9), "minus nine".into_cbor().unwrap()),
+        (Key::Bool(true), "yes".into_cbor().unwrap()),
+        (Key::F64(1.5), "float".into_cbor().unwrap()),
+        (Key::Bytes(vec![1, 2]), "bytes".into_cbor().unwrap()),
+        (
+            Key::Cbor(Box::new(vec![1_u64, 2].into_cbor().unwrap())),
+            "array".into_cbor().unwrap(),
+        ),
+        (
+            Key::Cbor(Box::new(SimpleValue::Null.into_cbor().unwrap())),
+            "null".into_cbor().unwrap(),
+        ),
+        (Key::from("it's"), "quote".into_cbor().unwrap()),
+        (Key::U64(u64::MAX), "max".into_cbor().unwrap()),
+        (Key::from("\u{1F600}"), "smile".into_cbor().unwrap()),
+    ];
+    let set = Tag::Set(Box::new(vec![item("tag", 4, 1.0)].into_cbor().unwrap()));
+    text_map(vec![
+        ("orders", vec![order1, order2].into_cbor().unwrap()),
+        ("limit", 3_u64.into_cbor().unwrap()),
+        ("misc", misc.into_cbor().unwrap()),
+        ("set", set.into()),
+    ])
+}
+
+fn values(doc: &Cbor, expr: &str) -> Vec<String> {
+    let matches = select(doc, expr).unwrap();
+    matches
+        .iter()
+        .map(|m| match m.value {
+            Cbor::Major3(_, _) => m.value.as_str().unwrap().to_string(),
+            val => match val.as_i128() {
+                Some(num) => num.to_string(),
+                None => format!("{:?}", val),
+            },
+        })
+        .collect()
+}
+
+#[test]
+fn test_query_select() {
+    let doc = document();
+
+    let testcases: Vec<(&str, Vec<&str>)> = vec![
+        ("$.orders[*].items[?(@.qty > 2)].sku", vec!["ink", "pad", "cap"]),
+        ("$..sku", vec!["pen", "ink", "pad", "pin", "cap", "tag"]),
+        ("orders[0].items[-1].sku", vec!["ink"]),
+        ("$['orders'][1][\"items\"][0:2].sku", vec!["pad", "pin"]),
+        ("$.orders[1].items[::-1].sku", vec!["cap", "pin", "pad"]),
+        ("$.orders[1].items[-2:].sku", vec!["pin", "cap"]),
+        ("$.orders[1].items[5:].sku", vec![]),
+        ("$.orders[1].items[0:3:0].sku", vec![]),
+        ("$.orders[1].items[2, 0].sku", vec!["cap", "pad"]),
+        ("$.orders.*.id", vec!["1", "2"]),
+        ("$.orders[*]['id', 'missing']", vec!["1", "2"]),
+        ("$..items[?(@.price >= 4)].sku", vec!["ink", "cap"]),
+        ("$..items[?(@.price == 4)].sku", vec!["ink"]),
+        ("$..[?(@.qty > $.limit)].sku", vec!["pad", "cap", "tag"]),
+        ("$..items[?(@.qty > 1 && @.price < 3)].sku", vec!["pad", "pin"]),
+        ("$..items[?(@.qty == 1 || !(@.price < 7))].sku", vec!["pen", "cap"]),
+        ("$..items[?(@.sku >= 'p')].sku", vec!["pen", "pad", "pin"]),
+        ("$..items[?(@.sku == \"ink\")].qty", vec!["3"]),
+        ("$.orders[?(@.note)].id", vec!["2"]),
+        ("$.orders[?(!@.note)].id", vec!["1"]),
+        ("$.orders[?(@.note == null)].id", vec!["2"]),
+        ("$.orders[?(@.missing == @.other)].id", vec!["1", "2"]),
+        ("$.orders[?(@.missing != 1)].id", vec!["1", "2"]),
+        ("$.orders[?(@.items[*].qty > 1)].id", vec![]),
+        ("$.set[0].sku", vec!["tag"]),
+        ("$.misc[1]", vec!["one"]),
+        ("$.misc[-9]", vec!["minus nine"]),
+        ("$.misc[true]", vec!["yes"]),
+        ("$.misc[1.5]", vec!["float"]),
+        ("$.misc[h'0102']", vec!["bytes"]),
+        ("$.misc[c'820102']", vec!["array"]),
+        ("$.misc[null]", vec!["null"]),
+        ("$.misc['it\\'s']", vec!["quote"]),
+        ("$.misc[c'01', -9]", vec!["one", "minus nine"]),
+        ("$.misc[18446744073709551615]", vec!["max"]),
+        ("$.misc['\\uD83D\\uDE00']", vec!["smile"]),
+        ("$.misc[?(@ == 'yes')]", vec!["yes"]),
+        ("$.limit[0]", vec![]),
+        ("$.limit.*", vec![]),
+        ("$.missing..sku", vec![]),
+    ];
+
+    for (expr, refs) in testcases.into_iter() {
+        assert_eq!(values(&doc, expr), refs, "{}", expr);
+    }
+
+    let q: Query = "$".parse().unwrap();
+    assert_eq!(q.select(&doc), vec![Match { path: vec![], value: &doc }]);
+
+    let nums = cbor!([1, 2, 3, 4, 5]);
+    let testcases: Vec<(&str, Vec<&str>)> = vec![
+        ("$[1::9223372036854775807]", vec!["2"]),
+        ("$[3::-9223372036854775808]", vec!["4"]),
+        ("$[-9223372036854775808:9223372036854775807:2]", vec!["1", "3", "5"]),
+        ("$[9223372036854775807:-9223372036854775808:-2]", vec!["5", "3", "1"]),
+        ("$[-9223372036854775808::-1]", vec![]),
+        ("$[9223372036854775807:]", vec![]),
+    ];
+    for (expr, refs) in testcases.into_iter() {
+        assert_eq!(values(&nums, expr), refs, "{}", expr);
+    }
+
+    // half-precision float key, {1.5: "half"}
+    let buf = [0xa1, 0xf9, 0x3e, 0x00, 0x64, 0x68, 0x61, 0x6c, 0x66];
+    let (half, _) = Cbor::decode(&mut &buf[..]).unwrap();
+    assert_eq!(values(&half, "$[1.5]"), vec!["half"]);
+    let matches = select(&half, "$.*").unwrap();
+    assert_eq!(matches[0].to_path(), "$[c'f93e00']");
+    assert_eq!(select(&half, &matches[0].to_path()).unwrap(), matches);
+}
+
+#[test]
+fn test_query_path() {
+    let doc = document();
+
+    let matches = select(&doc, "$.orders[1].items[?(@.qty == 2)].price").unwrap();
+    let path = vec![
+        Step::Key(Key::from("orders")),
+        Step::Index(1),
+        Step::Key(Key::from("items")),
+        Step::Index(1),
+        Step::Key(Key::from("price")),
+    ];
+    assert_eq!(matches, vec![Match { path, value: &0.5_f64.into_cbor().unwrap() }]);
+    assert_eq!(matches[0].to_path(), "$['orders'][1]['items'][1]['price']");
+
+    let paths: Vec<String> =
+        select(&doc, "$.misc.*").unwrap().iter().map(|m| m.to_path()).collect();
+    let refs = vec![
+        "$['misc'][1]",
+        "$['misc'][-9]",
+        "$['misc'][true]",
+        "$['misc'][1.5]",
+        "$['misc'][h'0102']",
+        "$['misc'][c'820102']",
+        "$['misc'][null]",
+        "$['misc']['it\\'s']",
+        "$['misc'][18446744073709551615]",
+        "$['misc']['\u{1F600}']",
+    ];
+    assert_eq!(paths, refs);
+
+    // normalized path selects the same value.
+    for m in select(&doc, "$..*").unwrap().into_iter() {
+        let matches = select(&doc, &m.to_path()).unwrap();
+        assert_eq!(matches, vec![m.clone()], "{}", m.to_path());
+    }
+}
+
+#[test]
+fn test_query_parse_error() {
+    let testcases = vec![
+        ("$.", 2, ErrorKind::InvalidData),
+        ("$[", 2, ErrorKind::InvalidData),
+        ("$['a'", 5, ErrorKind::InvalidData),
+        ("$['a", 2, ErrorKind::InvalidData),
+        ("$.a b", 4, ErrorKind::InvalidData),
+        ("$[?(@.a == )]", 11, ErrorKind::InvalidData),
+        ("$[?(1)]", 4, ErrorKind::InvalidData),
+        ("$[?(@.a > 1]", 11, ErrorKind::InvalidData),
+        ("$[h'123']", 4, ErrorKind::InvalidData),
+        ("$[c'82']", 2, ErrorKind::InvalidData),
+        ("$[1.5:2]", 2, ErrorKind::InvalidData),
+        ("$['\\x']", 3, ErrorKind::InvalidData),
+        ("$['\\uD83D']", 3, ErrorKind::InvalidData),
+        ("$['\\uD83D\\u0041']", 3, ErrorKind::InvalidData),
+        ("$[99999999999999999999]", 2, ErrorKind::OutOfRange),
+    ];
+    for (expr, offset, kind) in testcases.into_iter() {
+        let err = Query::parse(expr).unwrap_err();
+        assert_eq!(err.kind(), kind, "{} {}", expr, err);
+        assert_eq!(err.offset(), Some(offset), "{} {}", expr, err);
+    }
+
+    // nesting upto RECURSION_LIMIT needs a large stack in debug builds.
+    let handle = std::thread::Builder::new().stack_size(32 * 1024 * 1024);
+    let handle = handle.spawn(|| {
+        // parenthesis of the filter selector counts as one level.
+        let n = RECURSION_LIMIT as usize;
+        let expr = format!("$[?({}@.a{})]", "(".repeat(n - 2), ")".repeat(n - 2));
+        Query::parse(&expr).unwrap();
+
+        let expr = format!("$[?({}@.a{})]", "(".repeat(n), ")".repeat(n));
+        let err = Query::parse(&expr).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::LimitExceeded, "{}", err);
+        let expr = format!("$[?({}@.a)]", "!".repeat(n * 100));
+        let err = Query::parse(&expr).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::LimitExceeded, "{}", err);
+        let expr = format!("$[?(@[?({}@.a)])]", "!".repeat(n - 1));
+        let err = Query::parse(&expr).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::LimitExceeded, "{}", err);
+    });
+    handle.unwrap().join().unwrap();
+}
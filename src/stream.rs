@@ -0,0 +1,454 @@
+//! Module implement streaming interface for CBOR.
+//!
+//! [Reader] is a pull parser that yields one [Event] at a time without
+//! materializing the whole [Cbor][crate::Cbor] value in memory. Use it to
+//! process large documents and long sequences of data items.
+//!
+//! [Writer] is the converse, a push encoder that writes data items as and
+//! when they are generated, arrays and maps are opened with `begin_*` calls
+//! and closed with [Writer::end].
+
+use std::convert::TryFrom;
+use std::io;
+
+use crate::cbor::{decode_addnl, encode_addnl, encode_hdr, read_bytes};
+use crate::{Cbor, Error, Info, IntoCbor, Result, SimpleValue, RECURSION_LIMIT};
+
+/// Events generated by [Reader].
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum Event {
+    /// Major type 0, unsigned integer.
+    Unsigned(u64),
+    /// Major type 1, negative integer, actual value is `-1 - n`.
+    Negative(u64),
+    /// Major type 2, indefinite-length chunks are concatenated.
+    Bytes(Vec<u8>),
+    /// Major type 3, indefinite-length chunks are concatenated.
+    Text(String),
+    /// Major type 4, start of array, None for indefinite-length array.
+    ArrayStart(Option<u64>),
+    /// Major type 5, start of map, None for indefinite-length map. Entries
+    /// follow as alternating key and value items.
+    MapStart(Option<u64>),
+    /// Major type 6, tag number, the next item is the tagged content.
+    Tag(u64),
+    /// Major type 7, simple values and floating point numbers.
+    Simple(SimpleValue),
+    /// End of indefinite-length array or map.
+    Break,
+    /// End of definite-length array or map.
+    End,
+}
+
+// Open array or map.
+struct Frame {
+    len: Option<u64>, // number of items, including keys, for definite length
+    n: u64,           // number of items read so far
+    map: bool,
+}
+
+/// Pull parser that yields CBOR [Event]s from an [io::Read] value.
+///
+/// Reader can also be used as an iterator over `Result<Event>`. Iteration
+/// stops when the underlying reader hits end-of-file in-between data items.
+pub struct Reader<R> {
+    r: R,
+    stack: Vec<Frame>,
+    tagged: bool,   // tag number is read, waiting for the tagged item
+    n_bytes: usize, // bytes read so far
+}
+
+impl<R> Reader<R>
+where
+    R: io::Read,
+{
+    /// Create a new reader that parses CBOR items from `r`.
+    pub fn new(r: R) -> Reader<R> {
+        Reader {
+            r,
+            stack: Vec::default(),
+            tagged: false,
+            n_bytes: 0,
+        }
+    }
+
+    /// Return the number of open arrays and maps.
+    pub fn depth(&self) -> usize {
+        self.stack.len()
+    }
+
+    /// Unwrap and return the underlying reader.
+    pub fn into_inner(self) -> R {
+        self.r
+    }
+
+    /// Read the next event, return None when end-of-file is reached
+    /// in-between data items. On failure, error carries the byte offset
+    /// from the start of the reader, refer [Error::offset].
+    pub fn next_event(&mut self) -> Result<Option<Event>> {
+        // offset is approximate, bytes read before the failing read.
+        self.do_next_event()
+            .map_err(|err| err.with_offset(self.n_bytes).with_backtrace())
+    }
+
+    fn do_next_event(&mut self) -> Result<Option<Event>> {
+        if let Some(Frame { len: Some(len), n, .. }) = self.stack.last() {
+            if len == n {
+                self.stack.pop();
+                self.item_done();
+                return Ok(Some(Event::End));
+            }
+        }
+
+        let tagged = self.tagged;
+        self.tagged = false;
+        let (major, info) = match self.read_hdr()? {
+            Some((7, Info::Indefinite)) if tagged => {
+                err_at!(InvalidHeader, msg: "tagged break")?
+            }
+            Some(hdr) => hdr,
+            None if self.stack.is_empty() && !tagged => return Ok(None),
+            None => {
+                err_at!(UnexpectedEof, msg: "unexpected eof at depth {}", self.depth())?
+            }
+        };
+
+        let event = match (major, info) {
+            (0, info) => Event::Unsigned(self.read_addnl(info)?),
+            (1, info) => Event::Negative(self.read_addnl(info)?),
+            (2, info) => Event::Bytes(self.read_string(2, info)?),
+            (3, info) => {
+                let text = self.read_string(3, info)?;
+                Event::Text(err_at!(Utf8, String::from_utf8(text))?)
+            }
+            (4, Info::Indefinite) => {
+                self.push(None, false)?;
+                return Ok(Some(Event::ArrayStart(None)));
+            }
+            (4, info) => {
+                let len = self.read_addnl(info)?;
+                self.push(Some(len), false)?;
+                return Ok(Some(Event::ArrayStart(Some(len))));
+            }
+            (5, Info::Indefinite) => {
+                self.push(None, true)?;
+                return Ok(Some(Event::MapStart(None)));
+            }
+            (5, info) => {
+                let len = self.read_addnl(info)?;
+                let n = match len.checked_mul(2) {
+                    Some(n) => n,
+                    None => err_at!(OutOfRange, msg: "map length {} overflow", len)?,
+                };
+                self.push(Some(n), true)?;
+                return Ok(Some(Event::MapStart(Some(len))));
+            }
+            (6, Info::Indefinite) => err_at!(InvalidHeader, msg: "indefinite tag")?,
+            (6, info) => {
+                self.tagged = true;
+                return Ok(Some(Event::Tag(self.read_addnl(info)?)));
+            }
+            (7, Info::Indefinite) => {
+                match self.stack.pop() {
+                    Some(Frame { len: None, n, map }) if !map || n % 2 == 0 => (),
+                    Some(Frame { len: None, .. }) => {
+                        err_at!(InvalidHeader, msg: "break after map key")?
+                    }
+                    _ => err_at!(InvalidHeader, msg: "unexpected break")?,
+                }
+                self.item_done();
+                return Ok(Some(Event::Break));
+            }
+            (7, info) => {
+                let (sval, n) = SimpleValue::decode(info, &mut self.r)?;
+                self.n_bytes += n;
+                Event::Simple(sval)
+            }
+            _ => unreachable!(),
+        };
+
+        self.item_done();
+        Ok(Some(event))
+    }
+
+    fn read_hdr(&mut self) -> Result<Option<(u8, Info)>> {
+        let mut scratch = [0_u8; 1];
+        loop {
+            match self.r.read(&mut scratch) {
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+                res => match err_at!(IOError, res)? {
+                    0 => break Ok(None),
+                    _ => {
+                        self.n_bytes += 1;
+                        let (major, info) = ((scratch[0] & 0xe0) >> 5, scratch[0] & 0x1f);
+                        break Ok(Some((major, Info::try_from(info)?)));
+                    }
+                },
+            }
+        }
+    }
+
+    fn read_string(&mut self, major: u8, info: Info) -> Result<Vec<u8>> {
+        match info {
+            Info::Indefinite => {
+                let mut data: Vec<u8> = Vec::default();
+                loop {
+                    match self.read_hdr()? {
+                        Some((7, Info::Indefinite)) => break Ok(data),
+                        Some((m, Info::Indefinite)) if m == major => {
+                            err_at!(InvalidHeader, msg: "nested indefinite chunk")?
+                        }
+                        Some((m, info)) if m == major => {
+                            let len = self.read_addnl(info)?;
+                            data.extend_from_slice(&self.read_bytes(len)?);
+                        }
+                        Some((m, _)) => {
+                            err_at!(InvalidHeader, msg: "expected chunk of major {} got {}", major, m)?
+                        }
+                        None => err_at!(UnexpectedEof, msg: "unexpected eof in chunks")?,
+                    }
+                }
+            }
+            info => {
+                let len = self.read_addnl(info)?;
+                self.read_bytes(len)
+            }
+        }
+    }
+
+    fn read_addnl(&mut self, info: Info) -> Result<u64> {
+        let (num, n) = decode_addnl(info, &mut self.r)?;
+        self.n_bytes += n;
+        Ok(num)
+    }
+
+    fn read_bytes(&mut self, len: u64) -> Result<Vec<u8>> {
+        let data = read_bytes(&mut self.r, len)?;
+        self.n_bytes += data.len();
+        Ok(data)
+    }
+
+    fn push(&mut self, len: Option<u64>, map: bool) -> Result<()> {
+        if self.stack.len() >= (RECURSION_LIMIT as usize) {
+            err_at!(LimitExceeded, msg: "stream recursion limit exceeded")?
+        }
+        self.stack.push(Frame { len, n: 0, map });
+        Ok(())
+    }
+
+    fn item_done(&mut self) {
+        if let Some(frame) = self.stack.last_mut() {
+            frame.n += 1;
+        }
+    }
+}
+
+impl<R> Iterator for Reader<R>
+where
+    R: io::Read,
+{
+    type Item = Result<Event>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_event().transpose()
+    }
+}
+
+/// Push encoder that writes CBOR items into an [io::Write] value.
+///
+/// Arrays and maps can be of definite length, when the number of items is
+/// known upfront, or of indefinite length. In either case they must be closed
+/// with [Writer::end], for definite length containers `end()` checks that
+/// the number of items written matches the declared length. Map entries are
+/// written as alternating key and value items.
+pub struct Writer<W> {
+    w: W,
+    stack: Vec<Frame>,
+    tagged: bool, // tag number is written, waiting for the tagged item
+}
+
+impl<W> Writer<W>
+where
+    W: io::Write,
+{
+    /// Create a new writer that encodes CBOR items into `w`.
+    pub fn new(w: W) -> Writer<W> {
+        Writer { w, stack: Vec::default(), tagged: false }
+    }
+
+    /// Return the number of open arrays and maps.
+    pub fn depth(&self) -> usize {
+        self.stack.len()
+    }
+
+    /// Unwrap and return the underlying writer. Fail if there are open
+    /// arrays, maps or a tag waiting for its item.
+    pub fn finish(self) -> Result<W> {
+        if !self.stack.is_empty() || self.tagged {
+            err_at!(Fatal, msg: "finish with {} open items", self.stack.len())?
+        }
+        Ok(self.w)
+    }
+
+    /// Start an array, `len` is None for indefinite length array.
+    pub fn begin_array(&mut self, len: Option<u64>) -> Result<usize> {
+        self.item_start()?;
+        let n = self.write_hdr(4, len)?;
+        self.push(len, false)?;
+        Ok(n)
+    }
+
+    /// Start a map, `len` is the number of entries, None for indefinite
+    /// length map.
+    pub fn begin_map(&mut self, len: Option<u64>) -> Result<usize> {
+        let items = match len.map(|len| len.checked_mul(2)) {
+            Some(None) => err_at!(OutOfRange, msg: "map length overflow")?,
+            Some(items) => items,
+            None => None,
+        };
+        self.item_start()?;
+        let n = self.write_hdr(5, len)?;
+        self.push(items, true)?;
+        Ok(n)
+    }
+
+    /// Close the last opened array or map.
+    pub fn end(&mut self) -> Result<usize> {
+        if self.tagged {
+            err_at!(Fatal, msg: "tag without item")?
+        }
+        let n = match self.stack.pop() {
+            Some(Frame { len: Some(len), n, .. }) if len == n => 0,
+            Some(Frame { len: Some(len), n, .. }) => {
+                err_at!(Fatal, msg: "expected {} items, wrote {}", len, n)?
+            }
+            Some(Frame { len: None, n, map: true }) if n % 2 == 1 => {
+                err_at!(Fatal, msg: "map key without value")?
+            }
+            Some(Frame { len: None, .. }) => {
+                err_at!(IOError, self.w.write_all(&[0xff]))?;
+                1
+            }
+            None => err_at!(Fatal, msg: "no open array or map")?,
+        };
+        self.item_done();
+        Ok(n)
+    }
+
+    /// Write a tag number, the next item written is the tagged content.
+    pub fn tag(&mut self, num: u64) -> Result<usize> {
+        self.item_start()?;
+        self.tagged = true;
+        self.write_hdr(6, Some(num))
+    }
+
+    /// Write an unsigned integer.
+    pub fn u64(&mut self, num: u64) -> Result<usize> {
+        self.item_start()?;
+        let n = self.write_hdr(0, Some(num))?;
+        self.item_done();
+        Ok(n)
+    }
+
+    /// Write a signed integer.
+    pub fn i64(&mut self, num: i64) -> Result<usize> {
+        match num {
+            num if num >= 0 => self.u64(num as u64),
+            num => {
+                self.item_start()?;
+                let n = self.write_hdr(1, Some(!(num as u64)))?;
+                self.item_done();
+                Ok(n)
+            }
+        }
+    }
+
+    /// Write a byte string.
+    pub fn bytes(&mut self, val: &[u8]) -> Result<usize> {
+        self.item_start()?;
+        let n =
+            self.write_hdr(2, Some(err_at!(OutOfRange, u64::try_from(val.len()))?))?;
+        err_at!(IOError, self.w.write_all(val))?;
+        self.item_done();
+        Ok(n + val.len())
+    }
+
+    /// Write a text string.
+    pub fn text(&mut self, val: &str) -> Result<usize> {
+        self.item_start()?;
+        let n =
+            self.write_hdr(3, Some(err_at!(OutOfRange, u64::try_from(val.len()))?))?;
+        err_at!(IOError, self.w.write_all(val.as_bytes()))?;
+        self.item_done();
+        Ok(n + val.len())
+    }
+
+    /// Write a boolean value.
+    pub fn bool(&mut self, val: bool) -> Result<usize> {
+        self.value(&val.into_cbor()?)
+    }
+
+    /// Write a null value.
+    pub fn null(&mut self) -> Result<usize> {
+        self.value(&SimpleValue::Null.into_cbor()?)
+    }
+
+    /// Write a 32-bit floating point number.
+    pub fn f32(&mut self, val: f32) -> Result<usize> {
+        self.value(&val.into_cbor()?)
+    }
+
+    /// Write a 64-bit floating point number.
+    pub fn f64(&mut self, val: f64) -> Result<usize> {
+        self.value(&val.into_cbor()?)
+    }
+
+    /// Write a complete Cbor value as a single item.
+    pub fn value(&mut self, val: &Cbor) -> Result<usize> {
+        self.item_start()?;
+        let n = val.encode(&mut self.w)?;
+        self.item_done();
+        Ok(n)
+    }
+
+    fn write_hdr(&mut self, major: u8, num: Option<u64>) -> Result<usize> {
+        match num {
+            Some(num) => {
+                let n = encode_hdr(major, num.into(), &mut self.w)?;
+                Ok(n + encode_addnl(num.into(), num, &mut self.w)?)
+            }
+            None => encode_hdr(major, Info::Indefinite, &mut self.w),
+        }
+    }
+
+    fn push(&mut self, len: Option<u64>, map: bool) -> Result<()> {
+        if self.stack.len() >= (RECURSION_LIMIT as usize) {
+            err_at!(LimitExceeded, msg: "stream recursion limit exceeded")?
+        }
+        self.stack.push(Frame { len, n: 0, map });
+        Ok(())
+    }
+
+    // Check that the enclosing container can accept one more item.
+    fn item_start(&mut self) -> Result<()> {
+        match self.stack.last() {
+            Some(Frame { len: Some(len), n, .. }) if len == n && !self.tagged => {
+                err_at!(Fatal, msg: "more than {} items", len)
+            }
+            _ => {
+                self.tagged = false;
+                Ok(())
+            }
+        }
+    }
+
+    fn item_done(&mut self) {
+        if let Some(frame) = self.stack.last_mut() {
+            frame.n += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+#[path = "stream_test.rs"]
+mod stream_test;
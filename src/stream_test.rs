@@ -0,0 +1,197 @@
+use arbitrary::Unstructured;
+use rand::{prelude::random, rngs::StdRng, Rng, SeedableRng};
+
+use super::*;
+use crate::{Cbor, FromCbor, IntoCbor, Key, Tag};
+
+#[test]
+fn test_stream_reader() {
+    let seed: u128 = random();
+    println!("test_stream_reader seed:{}", seed);
+    let mut rng = {
+        let mut rng_seed = [0; 32];
+        rng_seed[0..16].copy_from_slice(&seed.to_le_bytes());
+        StdRng::from_seed(rng_seed)
+    };
+
+    let mut buf: Vec<u8> = vec![];
+    let mut vals = vec![];
+    for _i in 0..1000 {
+        let val: Cbor = {
+            let bytes: Vec<u8> =
+                (0..100).flat_map(|_| rng.gen::<[u8; 32]>().to_vec()).collect();
+            let mut uns = Unstructured::new(&bytes);
+            uns.arbitrary().unwrap()
+        };
+        val.encode(&mut buf).unwrap();
+        vals.push(val);
+    }
+
+    let mut reader = Reader::new(buf.as_slice());
+    for val in vals.into_iter() {
+        assert_eq!(val, build(&mut reader));
+        assert_eq!(reader.depth(), 0);
+    }
+    assert!(reader.next().is_none());
+}
+
+#[test]
+fn test_stream_events() {
+    // [1, {"a": -2}, 2(h'01'), [_ "x"]], with an indefinite inner array.
+    let buf =
+        [0x84, 0x01, 0xa1, 0x61, 0x61, 0x21, 0xc2, 0x41, 0x01, 0x9f, 0x61, 0x78, 0xff];
+    let events: Vec<Event> = Reader::new(&buf[..]).map(|e| e.unwrap()).collect();
+    let refs = vec![
+        Event::ArrayStart(Some(4)),
+        Event::Unsigned(1),
+        Event::MapStart(Some(1)),
+        Event::Text("a".to_string()),
+        Event::Negative(1),
+        Event::End,
+        Event::Tag(2),
+        Event::Bytes(vec![1]),
+        Event::ArrayStart(None),
+        Event::Text("x".to_string()),
+        Event::Break,
+        Event::End,
+    ];
+    assert_eq!(events, refs);
+
+    // truncated input and stray break are errors.
+    assert!(Reader::new(&buf[..5]).any(|e| e.is_err()));
+    let err = Reader::new(&buf[..9]).find_map(|e| e.err()).unwrap();
+    assert_eq!(err.offset(), Some(9));
+    let err = Reader::new(&buf[..11]).find_map(|e| e.err()).unwrap();
+    assert_eq!(err.offset(), Some(11));
+    assert!(Reader::new(&[0xff][..]).next().unwrap().is_err());
+    assert!(Reader::new(&[0xc2][..]).next().unwrap().is_ok());
+    assert!(Reader::new(&[0xc2][..]).nth(1).unwrap().is_err());
+
+    // io errors are chained as source, with offset of the failing read.
+    let r = io::Read::chain(&buf[..2], Failing(io::ErrorKind::BrokenPipe));
+    let err = Reader::new(r).find_map(|e| e.err()).unwrap();
+    assert_eq!(err.kind(), crate::ErrorKind::IOError, "{}", err);
+    assert_eq!(err.offset(), Some(2));
+    let source = std::error::Error::source(&err).unwrap();
+    let source = source.downcast_ref::<io::Error>().unwrap();
+    assert_eq!(source.kind(), io::ErrorKind::BrokenPipe);
+
+    let r = io::Read::chain(&buf[..2], Failing(io::ErrorKind::UnexpectedEof));
+    let err = Reader::new(r).find_map(|e| e.err()).unwrap();
+    assert_eq!(err.kind(), crate::ErrorKind::UnexpectedEof, "{}", err);
+}
+
+struct Failing(io::ErrorKind);
+
+impl io::Read for Failing {
+    fn read(&mut self, _buf: &mut [u8]) -> io::Result<usize> {
+        Err(io::Error::new(self.0, "failing reader"))
+    }
+}
+
+fn build<R: io::Read>(reader: &mut Reader<R>) -> Cbor {
+    match reader.next_event().unwrap().unwrap() {
+        Event::Unsigned(n) => Cbor::Major0(n.into(), n),
+        Event::Negative(n) => Cbor::Major1(n.into(), n),
+        Event::Bytes(val) => Cbor::from_bytes(val).unwrap(),
+        Event::Text(val) => val.into_cbor().unwrap(),
+        Event::ArrayStart(Some(n)) => {
+            let items: Vec<Cbor> = (0..n).map(|_| build(reader)).collect();
+            assert_eq!(reader.next_event().unwrap(), Some(Event::End));
+            items.into_cbor().unwrap()
+        }
+        Event::MapStart(Some(n)) => {
+            let items: Vec<(Key, Cbor)> = (0..n)
+                .map(|_| (Key::from_cbor(build(reader)).unwrap(), build(reader)))
+                .collect();
+            assert_eq!(reader.next_event().unwrap(), Some(Event::End));
+            items.into_cbor().unwrap()
+        }
+        Event::Tag(num) => Tag::from_value(num, build(reader)).into(),
+        Event::Simple(sval) => sval.into_cbor().unwrap(),
+        event => panic!("unexpected event {:?}", event),
+    }
+}
+
+#[test]
+fn test_stream_writer() {
+    let mut writer = Writer::new(vec![]);
+    writer.begin_array(Some(4)).unwrap();
+    writer.u64(1).unwrap();
+    writer.begin_map(Some(1)).unwrap();
+    writer.text("a").unwrap();
+    writer.i64(-2).unwrap();
+    writer.end().unwrap();
+    writer.tag(2).unwrap();
+    writer.bytes(&[1]).unwrap();
+    writer.begin_array(None).unwrap();
+    writer.text("x").unwrap();
+    writer.end().unwrap();
+    writer.end().unwrap();
+    let buf = writer.finish().unwrap();
+
+    let refs =
+        [0x84, 0x01, 0xa1, 0x61, 0x61, 0x21, 0xc2, 0x41, 0x01, 0x9f, 0x61, 0x78, 0xff];
+    assert_eq!(buf, refs);
+
+    let mut writer = Writer::new(vec![]);
+    writer.begin_array(Some(1)).unwrap();
+    writer.null().unwrap();
+    assert!(writer.bool(true).is_err());
+    writer.end().unwrap();
+    assert!(writer.end().is_err());
+
+    let mut writer = Writer::new(vec![]);
+    writer.begin_array(Some(2)).unwrap();
+    writer.f64(1.5).unwrap();
+    assert!(writer.end().is_err());
+
+    let mut writer = Writer::new(vec![]);
+    writer.begin_map(None).unwrap();
+    writer.text("key").unwrap();
+    assert!(writer.end().is_err());
+
+    let mut writer = Writer::new(vec![]);
+    writer.tag(39).unwrap();
+    assert!(writer.finish().is_err());
+}
+
+#[test]
+fn test_stream_writer_reader() {
+    let seed: u128 = random();
+    println!("test_stream_writer_reader seed:{}", seed);
+    let mut rng = {
+        let mut rng_seed = [0; 32];
+        rng_seed[0..16].copy_from_slice(&seed.to_le_bytes());
+        StdRng::from_seed(rng_seed)
+    };
+
+    for _i in 0..1000 {
+        let val: Cbor = {
+            let bytes: Vec<u8> =
+                (0..100).flat_map(|_| rng.gen::<[u8; 32]>().to_vec()).collect();
+            let mut uns = Unstructured::new(&bytes);
+            uns.arbitrary().unwrap()
+        };
+
+        let mut writer = Writer::new(vec![]);
+        let mut buf: Vec<u8> = vec![];
+        val.encode(&mut buf).unwrap();
+        for event in Reader::new(buf.as_slice()) {
+            match event.unwrap() {
+                Event::Unsigned(n) => writer.u64(n).unwrap(),
+                Event::Negative(n) => writer.i64(-1 - (n as i64)).unwrap(),
+                Event::Bytes(val) => writer.bytes(&val).unwrap(),
+                Event::Text(val) => writer.text(&val).unwrap(),
+                Event::ArrayStart(len) => writer.begin_array(len).unwrap(),
+                Event::MapStart(len) => writer.begin_map(len).unwrap(),
+                Event::Tag(num) => writer.tag(num).unwrap(),
+                Event::Simple(sval) => writer.value(&sval.into_cbor().unwrap()).unwrap(),
+                Event::Break | Event::End => writer.end().unwrap(),
+            };
+        }
+        let out = writer.finish().unwrap();
+        assert_eq!(out, buf);
+        assert_eq!(build(&mut Reader::new(out.as_slice())), val);
+    }
+}
@@ -0,0 +1,168 @@
+//! Conversion between [SystemTime] and RFC 3339 date-time strings, without
+//! depending on an external date crate. Used for tag 0, refer to [Tag::DateTime].
+//!
+//! [Tag::DateTime]: crate::Tag::DateTime
+
+use std::convert::TryFrom;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::{Error, Result};
+
+/// Return seconds, and non-negative nanoseconds, relative to UNIX epoch.
+pub(crate) fn to_epoch(t: SystemTime) -> Result<(i64, u32)> {
+    let (secs, nanos) = match t.duration_since(UNIX_EPOCH) {
+        Ok(d) => (err_at!(OutOfRange, i64::try_from(d.as_secs()))?, d.subsec_nanos()),
+        Err(err) => {
+            let d = err.duration();
+            let secs = -err_at!(OutOfRange, i64::try_from(d.as_secs()))?;
+            match d.subsec_nanos() {
+                0 => (secs, 0),
+                nanos => (secs - 1, 1_000_000_000 - nanos),
+            }
+        }
+    };
+    Ok((secs, nanos))
+}
+
+/// Converse of [to_epoch].
+pub(crate) fn from_epoch(secs: i64, nanos: u32) -> Result<SystemTime> {
+    let t = match secs {
+        secs if secs >= 0 => UNIX_EPOCH.checked_add(Duration::from_secs(secs as u64)),
+        secs => UNIX_EPOCH.checked_sub(Duration::from_secs(secs.unsigned_abs())),
+    };
+    match t.and_then(|t| t.checked_add(Duration::from_nanos(nanos.into()))) {
+        Some(t) => Ok(t),
+        None => err_at!(OutOfRange, msg: "epoch time {}.{} out of range", secs, nanos),
+    }
+}
+
+/// Convert floating point seconds, relative to UNIX epoch, to SystemTime.
+pub(crate) fn from_epoch_f64(secs: f64) -> Result<SystemTime> {
+    let d = match Duration::try_from_secs_f64(secs.abs()) {
+        Ok(d) => d,
+        Err(err) => err_at!(OutOfRange, msg: "epoch time {}: {}", secs, err)?,
+    };
+    let t = match secs.is_sign_negative() {
+        true => UNIX_EPOCH.checked_sub(d),
+        false => UNIX_EPOCH.checked_add(d),
+    };
+    match t {
+        Some(t) => Ok(t),
+        None => err_at!(OutOfRange, msg: "epoch time {} out of range", secs),
+    }
+}
+
+/// Format `t` as RFC 3339 date-time in UTC, like `2013-03-21T20:04:00Z`.
+/// Fractional seconds are included only when non-zero.
+pub(crate) fn to_rfc3339(t: SystemTime) -> Result<String> {
+    let (secs, nanos) = to_epoch(t)?;
+    let (days, secs) = (secs.div_euclid(86400), secs.rem_euclid(86400));
+    let (year, month, day) = civil_from_days(days);
+    if !(0..=9999).contains(&year) {
+        err_at!(OutOfRange, msg: "year {} out of range for rfc3339", year)?
+    }
+
+    let (hh, mm, ss) = (secs / 3600, (secs % 3600) / 60, secs % 60);
+    let mut s =
+        format!("{:04}-{:02}-{:02}T{:02}:{:02}:{:02}", year, month, day, hh, mm, ss);
+    if nanos > 0 {
+        let frac = format!("{:09}", nanos);
+        s.push('.');
+        s.push_str(frac.trim_end_matches('0'));
+    }
+    s.push('Z');
+    Ok(s)
+}
+
+/// Parse RFC 3339 date-time, like `2013-03-21T20:04:00.5+05:30`.
+pub(crate) fn from_rfc3339(s: &str) -> Result<SystemTime> {
+    let b = s.as_bytes();
+    let num = |from: usize, to: usize| -> Result<i64> {
+        match b.get(from..to) {
+            Some(ds) if ds.iter().all(u8::is_ascii_digit) => {
+                Ok(ds.iter().fold(0, |acc, d| acc * 10 + i64::from(d - b'0')))
+            }
+            _ => err_at!(InvalidData, msg: "invalid rfc3339 {:?}", s),
+        }
+    };
+    let sep = |off: usize, chs: &[u8]| -> Result<()> {
+        match b.get(off) {
+            Some(ch) if chs.contains(ch) => Ok(()),
+            _ => err_at!(InvalidData, msg: "invalid rfc3339 {:?}", s),
+        }
+    };
+
+    let (year, month, day) = (num(0, 4)?, num(5, 7)?, num(8, 10)?);
+    let (hh, mm, ss) = (num(11, 13)?, num(14, 16)?, num(17, 19)?);
+    sep(4, b"-")?;
+    sep(7, b"-")?;
+    sep(10, b"Tt ")?;
+    sep(13, b":")?;
+    sep(16, b":")?;
+
+    let mut off = 19;
+    let mut nanos = 0_u32;
+    if b.get(off) == Some(&b'.') {
+        let n = b[off + 1..].iter().take_while(|d| d.is_ascii_digit()).count();
+        if n == 0 {
+            err_at!(InvalidData, msg: "invalid rfc3339 {:?}", s)?
+        }
+        let frac = num(off + 1, off + 1 + n.min(9))?;
+        nanos = (frac * 10_i64.pow(9 - n.min(9) as u32)) as u32;
+        off += n + 1;
+    }
+
+    let offset = match b.get(off) {
+        Some(b'Z') | Some(b'z') if b.len() == off + 1 => 0,
+        Some(b'+') | Some(b'-') if b.len() == off + 6 => {
+            sep(off + 3, b":")?;
+            let (oh, om) = (num(off + 1, off + 3)?, num(off + 4, off + 6)?);
+            if oh > 23 || om > 59 {
+                err_at!(InvalidData, msg: "invalid rfc3339 offset {:?}", s)?
+            }
+            match b[off] {
+                b'+' => oh * 3600 + om * 60,
+                _ => -(oh * 3600 + om * 60),
+            }
+        }
+        _ => err_at!(InvalidData, msg: "invalid rfc3339 {:?}", s)?,
+    };
+
+    let days = days_from_civil(year, month, day);
+    // leap second, 60, is accepted and rolls over to the next minute.
+    if civil_from_days(days) != (year, month, day) || hh > 23 || mm > 59 || ss > 60 {
+        err_at!(InvalidData, msg: "invalid rfc3339 {:?}", s)?
+    }
+    let secs = days * 86400 + hh * 3600 + mm * 60 + ss - offset;
+    from_epoch(secs, nanos)
+}
+
+// Days since 1970-01-01 for proleptic Gregorian date, refer to
+// http://howardhinnant.github.io/date_algorithms.html
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let y = if month <= 2 { year - 1 } else { year };
+    let era = y.div_euclid(400);
+    let yoe = y - era * 400;
+    let mp = (month + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146097 + doe - 719468
+}
+
+// Converse of days_from_civil, return (year, month, day).
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400;
+    (if month <= 2 { year + 1 } else { year }, month, day)
+}
+
+#[cfg(test)]
+#[path = "time_test.rs"]
+mod time_test;
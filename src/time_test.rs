@@ -0,0 +1,132 @@
+use rand::{prelude::random, rngs::StdRng, Rng, SeedableRng};
+
+use super::*;
+use crate::{Cbor, FromCbor, Info, IntoCbor, Key, Tag};
+
+#[test]
+fn test_rfc3339() {
+    let refs = vec![
+        ("1970-01-01T00:00:00Z", 0, 0),
+        ("2013-03-21T20:04:00Z", 1363896240, 0),
+        ("2013-03-21T20:04:00.5Z", 1363896240, 500_000_000),
+        ("2000-02-29T23:59:59.000000001Z", 951868799, 1),
+        ("1969-12-31T23:59:59.9Z", -1, 900_000_000),
+        ("0000-01-01T00:00:00Z", -62167219200, 0),
+        ("9999-12-31T23:59:59Z", 253402300799, 0),
+    ];
+    for (s, secs, nanos) in refs.into_iter() {
+        let t = from_epoch(secs, nanos).unwrap();
+        assert_eq!(to_epoch(t).unwrap(), (secs, nanos), "{}", s);
+        assert_eq!(to_rfc3339(t).unwrap(), s);
+        assert_eq!(from_rfc3339(s).unwrap(), t, "{}", s);
+    }
+
+    let t = from_rfc3339("2013-03-21T20:04:00Z").unwrap();
+    assert_eq!(from_rfc3339("2013-03-21t20:04:00z").unwrap(), t);
+    assert_eq!(from_rfc3339("2013-03-22T01:34:00+05:30").unwrap(), t);
+    assert_eq!(from_rfc3339("2013-03-21T16:04:00-04:00").unwrap(), t);
+    assert!(from_rfc3339("2013-03-21T20:03:59.9999999999Z").unwrap() < t);
+
+    let invalids = [
+        "",
+        "2013-03-21",
+        "2013-03-21T20:04:00",
+        "2013-03-21T20:04:00.Z",
+        "2013-02-29T20:04:00Z",
+        "2013-13-01T20:04:00Z",
+        "2013-03-21T24:04:00Z",
+        "2013-03-21T20:04:00+24:00",
+        "2013-03-21T20:04:00Zextra",
+        "2013/03/21T20:04:00Z",
+        "+013-03-21T20:04:00Z",
+    ];
+    for s in invalids.iter() {
+        assert!(from_rfc3339(s).is_err(), "{}", s);
+    }
+    assert!(to_rfc3339(from_epoch(253402300800, 0).unwrap()).is_err());
+}
+
+#[test]
+fn test_system_time() {
+    let seed: u128 = random();
+    println!("test_system_time seed:{}", seed);
+    let mut rng = {
+        let mut rng_seed = [0; 32];
+        rng_seed[0..16].copy_from_slice(&seed.to_le_bytes());
+        StdRng::from_seed(rng_seed)
+    };
+
+    for _i in 0..10000 {
+        let secs = rng.gen_range(-62167219200_i64..253402300800);
+        let nanos = match rng.gen::<u8>() % 2 {
+            0 => 0,
+            _ => rng.gen_range(0..1_000_000_000),
+        };
+        let t = from_epoch(secs, nanos).unwrap();
+
+        let val = t.into_cbor().unwrap();
+        let mut buf: Vec<u8> = vec![];
+        val.encode(&mut buf).unwrap();
+        let (val, _) = Cbor::decode(&mut buf.as_slice()).unwrap();
+        assert_eq!(SystemTime::from_cbor(val).unwrap(), t);
+
+        let val = Tag::from_system_time(t).unwrap().into();
+        assert_eq!(SystemTime::from_cbor(val).unwrap(), t);
+
+        let d = Duration::new(secs.unsigned_abs(), nanos);
+        let val = d.into_cbor().unwrap();
+        let mut buf: Vec<u8> = vec![];
+        val.encode(&mut buf).unwrap();
+        let (val, _) = Cbor::decode(&mut buf.as_slice()).unwrap();
+        assert_eq!(Duration::from_cbor(val).unwrap(), d);
+    }
+
+    // 1(1363896240)
+    let buf = [0xc1, 0x1a, 0x51, 0x4b, 0x67, 0xb0];
+    let t = SystemTime::from_cbor(Cbor::decode(&mut buf.as_ref()).unwrap().0).unwrap();
+    assert_eq!(t, from_epoch(1363896240, 0).unwrap());
+    let mut out: Vec<u8> = vec![];
+    t.into_cbor().unwrap().encode(&mut out).unwrap();
+    assert_eq!(out, buf.to_vec());
+
+    // 1(1363896240.5)
+    let buf = [0xc1, 0xfb, 0x41, 0xd4, 0x52, 0xd9, 0xec, 0x20, 0x00, 0x00];
+    let t = SystemTime::from_cbor(Cbor::decode(&mut buf.as_ref()).unwrap().0).unwrap();
+    assert_eq!(t, from_epoch(1363896240, 500_000_000).unwrap());
+
+    // 0("2013-03-21T20:04:00Z")
+    let mut buf = vec![0xc0, 0x74];
+    buf.extend_from_slice(b"2013-03-21T20:04:00Z");
+    let t = SystemTime::from_cbor(Cbor::decode(&mut buf.as_slice()).unwrap().0).unwrap();
+    assert_eq!(t, from_epoch(1363896240, 0).unwrap());
+
+    // 1001({1: 1363896240, -3: 500})
+    let val = vec![
+        (Key::U64(1), 1363896240_u64.into_cbor().unwrap()),
+        (Key::N64(-3), 500_u64.into_cbor().unwrap()),
+    ];
+    let val: Cbor = Tag::ExtTime(Box::new(val.into_cbor().unwrap())).into();
+    let t = SystemTime::from_cbor(val).unwrap();
+    assert_eq!(t, from_epoch(1363896240, 500_000_000).unwrap());
+
+    let invalids: Vec<Cbor> = vec![
+        1363896240_u64.into_cbor().unwrap(),
+        Tag::Epoch(Box::new("1363896240".into_cbor().unwrap())).into(),
+        Tag::DateTime(Box::new(1363896240_u64.into_cbor().unwrap())).into(),
+        Tag::Epoch(Box::new(f64::NAN.into_cbor().unwrap())).into(),
+        Tag::ExtTime(Box::new(Cbor::Major5(Info::Tiny(0), vec![]))).into(),
+    ];
+    for val in invalids.into_iter() {
+        assert!(SystemTime::from_cbor(val.clone()).is_err(), "{:?}", val);
+    }
+
+    let val = vec![
+        (Key::U64(1), 10_u64.into_cbor().unwrap()),
+        (Key::N64(-6), 1_000_000_u64.into_cbor().unwrap()),
+    ];
+    let val: Cbor = Tag::Duration(Box::new(val.into_cbor().unwrap())).into();
+    assert!(Duration::from_cbor(val).is_err());
+    let val = vec![(Key::U64(1), (-10_i64).into_cbor().unwrap())];
+    let val: Cbor = Tag::Duration(Box::new(val.into_cbor().unwrap())).into();
+    assert!(Duration::from_cbor(val).is_err());
+}
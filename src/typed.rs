@@ -0,0 +1,210 @@
+use std::convert::{TryFrom, TryInto};
+
+use crate::{Cbor, Error, FromCbor, IntoCbor, Result, Tag};
+
+/// Byte order of elements in a [TypedArray].
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Endian {
+    Big,
+    Little,
+}
+
+impl Endian {
+    /// Byte order of the target platform.
+    pub fn native() -> Endian {
+        if cfg!(target_endian = "big") {
+            Endian::Big
+        } else {
+            Endian::Little
+        }
+    }
+}
+
+/// Numeric types that can be elements of a [TypedArray].
+pub trait TypedElement: Copy {
+    /// Tag-number for big-endian array of this type, refer to RFC 8746.
+    const TAG: u64;
+
+    /// Append the element, in `endian` byte order, to `buf`.
+    fn to_bytes(self, endian: Endian, buf: &mut Vec<u8>);
+
+    /// Converse of [TypedElement::to_bytes], `bytes` is exactly the size of
+    /// the element.
+    fn from_bytes(bytes: &[u8], endian: Endian) -> Self;
+}
+
+macro_rules! typed_element {
+    ($(($t:ty, $tag:expr))*) => {$(
+        impl TypedElement for $t {
+            const TAG: u64 = $tag;
+
+            fn to_bytes(self, endian: Endian, buf: &mut Vec<u8>) {
+                match endian {
+                    Endian::Big => buf.extend_from_slice(&self.to_be_bytes()),
+                    Endian::Little => buf.extend_from_slice(&self.to_le_bytes()),
+                }
+            }
+
+            fn from_bytes(bytes: &[u8], endian: Endian) -> Self {
+                let bytes = bytes.try_into().unwrap();
+                match endian {
+                    Endian::Big => <$t>::from_be_bytes(bytes),
+                    Endian::Little => <$t>::from_le_bytes(bytes),
+                }
+            }
+        }
+    )*}
+}
+
+typed_element! {
+    (u8, 64) (u16, 65) (u32, 66) (u64, 67)
+    (i8, 72) (i16, 73) (i32, 74) (i64, 75)
+    (f32, 81) (f64, 82)
+}
+
+/// Homogeneous array of numbers, encoded as RFC 8746 typed array, a
+/// byte-string wrapped in tag 64 to 87. More compact and faster to
+/// encode than a `Vec<T>`, which is encoded as array of items.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TypedArray<T> {
+    pub data: Vec<T>,
+    pub endian: Endian,
+}
+
+impl<T> TypedArray<T>
+where
+    T: TypedElement,
+{
+    /// Construct typed array from `data`, to be encoded in `endian` byte order.
+    pub fn new(data: Vec<T>, endian: Endian) -> TypedArray<T> {
+        TypedArray { data, endian }
+    }
+
+    fn size() -> usize {
+        std::mem::size_of::<T>()
+    }
+
+    fn to_tag_value(&self) -> u64 {
+        match self.endian {
+            Endian::Little if Self::size() > 1 => T::TAG | 0x4,
+            _ => T::TAG,
+        }
+    }
+
+    fn from_tag_value(num: u64) -> Result<Endian> {
+        match num {
+            num if num == T::TAG => Ok(Endian::Big),
+            num if num == (T::TAG | 0x4) && Self::size() > 1 => Ok(Endian::Little),
+            68 if T::TAG == 64 => Ok(Endian::Big), // uint8 clamped
+            num => err_at!(TypeMismatch, msg: "typed array tag {} for {}", num, T::TAG),
+        }
+    }
+}
+
+impl<T> IntoCbor for TypedArray<T>
+where
+    T: TypedElement,
+{
+    fn into_cbor(self) -> Result<Cbor> {
+        let num = self.to_tag_value();
+        let mut buf = Vec::with_capacity(self.data.len() * Self::size());
+        for item in self.data.into_iter() {
+            item.to_bytes(self.endian, &mut buf)
+        }
+        let val = Box::new(Cbor::from_bytes(buf)?);
+        Ok(Tag::TypedArray(num, val).into())
+    }
+}
+
+impl<T> FromCbor for TypedArray<T>
+where
+    T: TypedElement,
+{
+    fn from_cbor(val: Cbor) -> Result<TypedArray<T>> {
+        let (num, val) = match val {
+            Cbor::Major6(_, Tag::TypedArray(num, val)) => (num, val),
+            _ => err_at!(TypeMismatch, msg: "not a typed array")?,
+        };
+        let endian = Self::from_tag_value(num)?;
+        let bytes = val.into_bytes()?;
+        if bytes.len() % Self::size() != 0 {
+            err_at!(InvalidData, msg: "typed array of {} bytes", bytes.len())?
+        }
+        let data = bytes
+            .chunks_exact(Self::size())
+            .map(|bytes| T::from_bytes(bytes, endian))
+            .collect();
+        Ok(TypedArray { data, endian })
+    }
+}
+
+/// Multi-dimensional array in row-major order, encoded as tag 40.
+/// Number of elements shall match the product of dimensions in `shape`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NdArray<T> {
+    pub shape: Vec<u64>,
+    pub data: TypedArray<T>,
+}
+
+impl<T> NdArray<T>
+where
+    T: TypedElement,
+{
+    /// Construct multi-dimensional array of `shape` from elements in
+    /// row-major order.
+    pub fn new(shape: Vec<u64>, data: TypedArray<T>) -> Result<NdArray<T>> {
+        let val = NdArray { shape, data };
+        val.check()?;
+        Ok(val)
+    }
+
+    fn check(&self) -> Result<()> {
+        let n = self.shape.iter().try_fold(1_u64, |acc, dim| acc.checked_mul(*dim));
+        match n {
+            Some(n) if Some(n) == u64::try_from(self.data.data.len()).ok() => Ok(()),
+            _ => err_at!(
+                InvalidData, msg: "shape {:?} for {} elements", self.shape, self.data.data.len()
+            ),
+        }
+    }
+}
+
+impl<T> IntoCbor for NdArray<T>
+where
+    T: TypedElement,
+{
+    fn into_cbor(self) -> Result<Cbor> {
+        self.check()?;
+        let val = vec![self.shape.into_cbor()?, self.data.into_cbor()?].into_cbor()?;
+        Ok(Tag::NdArray(Box::new(val)).into())
+    }
+}
+
+impl<T> FromCbor for NdArray<T>
+where
+    T: TypedElement + FromCbor,
+{
+    /// Elements can either be a typed array or an array of numbers.
+    fn from_cbor(val: Cbor) -> Result<NdArray<T>> {
+        let val = match val {
+            Cbor::Major6(_, Tag::NdArray(val)) => *val,
+            _ => err_at!(TypeMismatch, msg: "not a multi-dimensional array")?,
+        };
+        let mut items = Vec::<Cbor>::from_cbor(val)?.into_iter();
+        let (shape, data) = match (items.next(), items.next(), items.next()) {
+            (Some(shape), Some(data), None) => (Vec::<u64>::from_cbor(shape)?, data),
+            _ => err_at!(TypeMismatch, msg: "expected array of shape and elements")?,
+        };
+        let data = match data {
+            Cbor::Major4(_, _) => {
+                TypedArray::new(Vec::<T>::from_cbor(data)?, Endian::Big)
+            }
+            data => TypedArray::from_cbor(data)?,
+        };
+        NdArray::new(shape, data)
+    }
+}
+
+#[cfg(test)]
+#[path = "typed_test.rs"]
+mod typed_test;
@@ -0,0 +1,133 @@
+use arbitrary::{Arbitrary, Unstructured};
+use rand::{prelude::random, rngs::StdRng, Rng, SeedableRng};
+
+use std::fmt;
+
+use super::*;
+use crate::Info;
+
+#[test]
+fn test_typed_array() {
+    let seed: u128 = random();
+    println!("test_typed_array seed:{}", seed);
+    let mut rng = {
+        let mut rng_seed = [0; 32];
+        rng_seed[0..16].copy_from_slice(&seed.to_le_bytes());
+        StdRng::from_seed(rng_seed)
+    };
+
+    for _i in 0..1000 {
+        let bytes: Vec<u8> = (0..100).flat_map(|_| rng.gen::<[u8; 32]>()).collect();
+        let mut uns = Unstructured::new(&bytes);
+        let endian = match rng.gen::<bool>() {
+            true => Endian::Big,
+            false => Endian::Little,
+        };
+        check_typed::<u8>(&mut uns, endian);
+        check_typed::<u16>(&mut uns, endian);
+        check_typed::<u32>(&mut uns, endian);
+        check_typed::<u64>(&mut uns, endian);
+        check_typed::<i8>(&mut uns, endian);
+        check_typed::<i16>(&mut uns, endian);
+        check_typed::<i32>(&mut uns, endian);
+        check_typed::<i64>(&mut uns, endian);
+    }
+}
+
+#[test]
+fn test_typed_array_wire() {
+    // 65(h'00010002'), big-endian uint16 [1, 2]
+    let buf = [0xd8, 0x41, 0x44, 0x00, 0x01, 0x00, 0x02];
+    let val = Cbor::decode(&mut buf.as_ref()).unwrap().0;
+    let arr = TypedArray::<u16>::from_cbor(val).unwrap();
+    assert_eq!(arr, TypedArray::new(vec![1, 2], Endian::Big));
+    let mut out: Vec<u8> = vec![];
+    arr.into_cbor().unwrap().encode(&mut out).unwrap();
+    assert_eq!(out, buf.to_vec());
+
+    // 69(h'01000200'), little-endian uint16 [1, 2]
+    let buf = [0xd8, 0x45, 0x44, 0x01, 0x00, 0x02, 0x00];
+    let val = Cbor::decode(&mut buf.as_ref()).unwrap().0;
+    let arr = TypedArray::<u16>::from_cbor(val).unwrap();
+    assert_eq!(arr, TypedArray::new(vec![1, 2], Endian::Little));
+
+    // 68(h'0102'), clamped uint8
+    let buf = [0xd8, 0x44, 0x42, 0x01, 0x02];
+    let val = Cbor::decode(&mut buf.as_ref()).unwrap().0;
+    assert_eq!(TypedArray::<u8>::from_cbor(val).unwrap().data, vec![1, 2]);
+
+    // single byte elements do not have a little-endian tag.
+    let val = TypedArray::new(vec![1_i8, -1], Endian::Little).into_cbor().unwrap();
+    assert!(matches!(val, Cbor::Major6(_, Tag::TypedArray(72, _))));
+
+    // 82(h'3ff0000000000000'), big-endian float64 [1.0]
+    let buf = [0xd8, 0x52, 0x48, 0x3f, 0xf0, 0, 0, 0, 0, 0, 0];
+    let val = Cbor::decode(&mut buf.as_ref()).unwrap().0;
+    assert_eq!(TypedArray::<f64>::from_cbor(val).unwrap().data, vec![1.0]);
+
+    let bytes = |n: usize| Box::new(Cbor::from_bytes(vec![0; n]).unwrap());
+    let invalids: Vec<Cbor> = vec![
+        Tag::TypedArray(65, bytes(3)).into(),
+        Tag::TypedArray(66, bytes(4)).into(),
+        Tag::TypedArray(65, Box::new(Cbor::Major0(Info::Tiny(1), 1))).into(),
+        vec![1_u16, 2].into_cbor().unwrap(),
+    ];
+    for val in invalids.into_iter() {
+        assert!(TypedArray::<u16>::from_cbor(val).is_err());
+    }
+    assert!(TypedArray::<u8>::from_cbor(Tag::TypedArray(68, bytes(2)).into()).is_ok());
+    assert!(TypedArray::<i8>::from_cbor(Tag::TypedArray(68, bytes(2)).into()).is_err());
+}
+
+#[test]
+fn test_nd_array() {
+    let data = TypedArray::new(vec![1_u16, 2, 3, 4, 5, 6], Endian::Big);
+    let val = NdArray::new(vec![2, 3], data.clone()).unwrap();
+    let nval = NdArray::<u16>::from_cbor(roundtrip(val.clone().into_cbor().unwrap()));
+    assert_eq!(nval.unwrap(), val);
+
+    // 40([[2, 3], 65(h'000100020003000400050006')])
+    let buf = [
+        0xd8, 0x28, 0x82, 0x82, 0x02, 0x03, 0xd8, 0x41, 0x4c, 0x00, 0x01, 0x00, 0x02,
+        0x00, 0x03, 0x00, 0x04, 0x00, 0x05, 0x00, 0x06,
+    ];
+    let mut out: Vec<u8> = vec![];
+    val.clone().into_cbor().unwrap().encode(&mut out).unwrap();
+    assert_eq!(out, buf.to_vec());
+
+    // 40([[2, 3], [1, 2, 3, 4, 5, 6]]), elements as array of numbers.
+    let items = vec![vec![2_u64, 3].into_cbor().unwrap(), data.data.into_cbor().unwrap()];
+    let nval = Tag::NdArray(Box::new(items.into_cbor().unwrap())).into();
+    assert_eq!(NdArray::<u16>::from_cbor(nval).unwrap(), val);
+
+    let data = TypedArray::new(vec![1_u16, 2, 3], Endian::Big);
+    assert!(NdArray::new(vec![2, 2], data.clone()).is_err());
+    assert!(NdArray::new(vec![u64::MAX, 2], data.clone()).is_err());
+    let items = vec![vec![2_u64, 2].into_cbor().unwrap(), data.into_cbor().unwrap()];
+    let nval = Tag::NdArray(Box::new(items.into_cbor().unwrap())).into();
+    assert!(NdArray::<u16>::from_cbor(nval).is_err());
+}
+
+fn check_typed<T>(uns: &mut Unstructured, endian: Endian)
+where
+    T: TypedElement + for<'a> Arbitrary<'a> + fmt::Debug + PartialEq,
+{
+    let data: Vec<T> = uns.arbitrary().unwrap();
+    let val = TypedArray::new(data, endian);
+    let nval = TypedArray::<T>::from_cbor(roundtrip(val.clone().into_cbor().unwrap()));
+    let nval = nval.unwrap();
+    match std::mem::size_of::<T>() {
+        1 => assert_eq!(nval.endian, Endian::Big),
+        _ => assert_eq!(nval.endian, val.endian),
+    }
+    assert_eq!(nval.data, val.data);
+}
+
+fn roundtrip(val: Cbor) -> Cbor {
+    let mut buf: Vec<u8> = vec![];
+    let n = val.encode(&mut buf).unwrap();
+    let (nval, m) = Cbor::decode(&mut buf.as_slice()).unwrap();
+    assert_eq!(n, m);
+    assert_eq!(val, nval);
+    nval
+}
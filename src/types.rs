@@ -7,17 +7,23 @@ use std::os::unix::ffi::OsStringExt;
 #[cfg(windows)]
 use std::os::windows::ffi::OsStringExt;
 
+use std::collections::{BTreeSet, HashSet};
 use std::convert::{TryFrom, TryInto};
-use std::{ffi, sync::Arc};
+use std::ffi;
+use std::hash::{BuildHasher, Hash};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
 
-use crate::{Cbor, Error, FromCbor, IntoCbor, Key, Result, SimpleValue, Tag};
+use crate::{
+    f16_to_f32, time, Cbor, Error, FromCbor, IntoCbor, Key, Result, SimpleValue, Tag,
+};
 
 impl<T, const N: usize> IntoCbor for [T; N]
 where
     T: Clone + IntoCbor,
 {
     fn into_cbor(self) -> Result<Cbor> {
-        let info = err_at!(FailConvert, u64::try_from(self.len()))?.into();
+        let info = err_at!(OutOfRange, u64::try_from(self.len()))?.into();
         let mut val: Vec<Cbor> = vec![];
         for item in self.iter() {
             val.push(item.clone().into_cbor()?)
@@ -36,14 +42,14 @@ where
         match val {
             Cbor::Major4(_, data) if n == data.len() => {
                 for (i, item) in data.into_iter().enumerate() {
-                    arr[i] = T::from_cbor(item)?;
+                    arr[i] = T::from_cbor(item).map_err(|err| err.at_index(i))?;
                 }
                 Ok(arr)
             }
             Cbor::Major4(_, data) => {
-                err_at!(FailConvert, msg: "different array arity {} {}", n, data.len())
+                err_at!(TypeMismatch, msg: "different array arity {} {}", n, data.len())
             }
-            _ => err_at!(FailCbor, msg: "not an list"),
+            _ => err_at!(TypeMismatch, msg: "not an list"),
         }
     }
 }
@@ -62,7 +68,7 @@ impl FromCbor for bool {
         match val {
             Cbor::Major7(_, SimpleValue::True) => Ok(true),
             Cbor::Major7(_, SimpleValue::False) => Ok(false),
-            _ => err_at!(FailConvert, msg: "not a bool"),
+            _ => err_at!(TypeMismatch, msg: "not a bool"),
         }
     }
 }
@@ -76,8 +82,9 @@ impl IntoCbor for f32 {
 impl FromCbor for f32 {
     fn from_cbor(val: Cbor) -> Result<f32> {
         match val {
+            Cbor::Major7(_, SimpleValue::F16(val)) => Ok(f16_to_f32(val)),
             Cbor::Major7(_, SimpleValue::F32(val)) => Ok(val),
-            _ => err_at!(FailConvert, msg: "not f32"),
+            _ => err_at!(TypeMismatch, msg: "not f32"),
         }
     }
 }
@@ -91,8 +98,10 @@ impl IntoCbor for f64 {
 impl FromCbor for f64 {
     fn from_cbor(val: Cbor) -> Result<f64> {
         match val {
+            Cbor::Major7(_, SimpleValue::F16(val)) => Ok(f16_to_f32(val).into()),
+            Cbor::Major7(_, SimpleValue::F32(val)) => Ok(val.into()),
             Cbor::Major7(_, SimpleValue::F64(val)) => Ok(val),
-            _ => err_at!(FailConvert, msg: "not f64"),
+            _ => err_at!(TypeMismatch, msg: "not f64"),
         }
     }
 }
@@ -100,11 +109,11 @@ macro_rules! convert_neg_num {
     ($($t:ty)*) => {$(
         impl IntoCbor for $t {
             fn into_cbor(self) -> Result<Cbor> {
-                let val: i64 = err_at!(FailConvert, i64::try_from(self))?;
+                let val: i64 = err_at!(OutOfRange, i64::try_from(self))?;
                 if val >= 0 {
-                    Ok(err_at!(FailConvert, u64::try_from(val))?.into_cbor()?)
+                    Ok(err_at!(OutOfRange, u64::try_from(val))?.into_cbor()?)
                 } else {
-                    let val = err_at!(FailConvert, u64::try_from(val.abs() - 1))?;
+                    let val = err_at!(OutOfRange, u64::try_from(val.abs() - 1))?;
                     let info = val.into();
                     Ok(Cbor::Major1(info, val))
                 }
@@ -118,13 +127,13 @@ macro_rules! convert_neg_num {
                 let val = match val {
                     Cbor::Major0(_, val) => {
                         let val: result::Result<$t, _> = val.try_into();
-                        err_at!(FailConvert, val)?
+                        err_at!(OutOfRange, val)?
                     }
                     Cbor::Major1(_, val) => {
-                        let val: result::Result<$t, _> = (val + 1).try_into();
-                        -err_at!(FailConvert, val)?
+                        let val: result::Result<$t, _> = val.try_into();
+                        -1 - err_at!(OutOfRange, val)?
                     }
-                    _ => err_at!(FailConvert, msg: "not a number")?,
+                    _ => err_at!(TypeMismatch, msg: "not a number")?,
                 };
                 Ok(val)
             }
@@ -138,7 +147,7 @@ macro_rules! convert_pos_num {
     ($($t:ty)*) => {$(
         impl IntoCbor for $t {
             fn into_cbor(self) -> Result<Cbor> {
-                let val = err_at!(FailConvert, u64::try_from(self))?;
+                let val = err_at!(OutOfRange, u64::try_from(self))?;
                 Ok(Cbor::Major0(val.into(), val))
             }
         }
@@ -146,8 +155,8 @@ macro_rules! convert_pos_num {
         impl FromCbor for $t {
             fn from_cbor(val: Cbor) -> Result<$t> {
                 match val {
-                    Cbor::Major0(_, val) => Ok(err_at!(FailConvert, val.try_into())?),
-                    _ => err_at!(FailConvert, msg: "not a number"),
+                    Cbor::Major0(_, val) => Ok(err_at!(OutOfRange, val.try_into())?),
+                    _ => err_at!(TypeMismatch, msg: "not a number"),
                 }
             }
         }
@@ -166,18 +175,9 @@ impl FromCbor for u128 {
     fn from_cbor(val: Cbor) -> Result<u128> {
         use num_traits::cast::ToPrimitive;
 
-        let (sign, bytes) = match val {
-            Cbor::Major6(_, tag) => match tag {
-                Tag::UBigNum(val) => (Sign::Plus, val.into_bytes()?),
-                Tag::SBigNum(val) => (Sign::Minus, val.into_bytes()?),
-                _ => err_at!(FailConvert, msg: "cbor not a bigint")?,
-            },
-            _ => err_at!(FailConvert, msg: "cbor not a tag/bigint")?,
-        };
-
-        match BigInt::from_bytes_be(sign, &bytes).to_u128() {
+        match BigInt::from_cbor(val)?.to_u128() {
             Some(val) => Ok(val),
-            None => err_at!(FailConvert, msg: "from bigint to u128"),
+            None => err_at!(OutOfRange, msg: "from bigint to u128"),
         }
     }
 }
@@ -192,31 +192,24 @@ impl FromCbor for i128 {
     fn from_cbor(val: Cbor) -> Result<i128> {
         use num_traits::cast::ToPrimitive;
 
-        let (sign, bytes) = match val {
-            Cbor::Major6(_, tag) => match tag {
-                Tag::UBigNum(val) => (Sign::Plus, val.into_bytes()?),
-                Tag::SBigNum(val) => (Sign::Minus, val.into_bytes()?),
-                _ => err_at!(FailConvert, msg: "cbor not a bigint")?,
-            },
-            _ => err_at!(FailConvert, msg: "cbor not a bigint")?,
-        };
-
-        match BigInt::from_bytes_be(sign, &bytes).to_i128() {
+        match BigInt::from_cbor(val)?.to_i128() {
             Some(val) => Ok(val),
-            None => err_at!(FailConvert, msg: "from bigint to i128"),
+            None => err_at!(OutOfRange, msg: "from bigint to i128"),
         }
     }
 }
 
 impl IntoCbor for BigInt {
+    /// Negative numbers are encoded as tag 3 holding `-1 - self`, as per
+    /// RFC-8949.
     fn into_cbor(self) -> Result<Cbor> {
-        match self.to_bytes_be() {
-            (Sign::Plus, bytes) | (Sign::NoSign, bytes) => {
-                let val = Box::new(Cbor::from_bytes(bytes)?);
+        match self.sign() {
+            Sign::Plus | Sign::NoSign => {
+                let val = Box::new(Cbor::from_bytes(self.to_bytes_be().1)?);
                 Ok(Tag::UBigNum(val).into())
             }
-            (Sign::Minus, bytes) => {
-                let val = Box::new(Cbor::from_bytes(bytes)?);
+            Sign::Minus => {
+                let val = Box::new(Cbor::from_bytes((-self - 1_u32).to_bytes_be().1)?);
                 Ok(Tag::SBigNum(val).into())
             }
         }
@@ -224,22 +217,26 @@ impl IntoCbor for BigInt {
 }
 
 impl FromCbor for BigInt {
+    /// Integers of major type 0 and 1 are also accepted, deterministic
+    /// encoding uses them for bignums that fit within 64-bits.
     fn from_cbor(val: Cbor) -> Result<BigInt> {
-        let (sign, bytes) = match val {
-            Cbor::Major6(_, tag) => match tag {
-                Tag::UBigNum(val) => (Sign::Plus, val.into_bytes()?),
-                Tag::SBigNum(val) => (Sign::Minus, val.into_bytes()?),
-                _ => err_at!(FailConvert, msg: "cbor not a bigint")?,
+        match &val {
+            Cbor::Major0(_, num) => Ok(BigInt::from(*num)),
+            Cbor::Major1(_, num) => Ok(-BigInt::from(*num) - 1_u32),
+            Cbor::Major6(_, tag @ Tag::UBigNum(_))
+            | Cbor::Major6(_, tag @ Tag::SBigNum(_)) => match tag.to_bignum() {
+                Some(val) => Ok(val),
+                None => err_at!(TypeMismatch, msg: "bignum not a byte string"),
             },
-            _ => err_at!(FailConvert, msg: "cbor not a tag/bigint")?,
-        };
-        Ok(BigInt::from_bytes_be(sign, &bytes))
+            Cbor::Major6(_, _) => err_at!(TypeMismatch, msg: "cbor not a bigint"),
+            _ => err_at!(TypeMismatch, msg: "cbor not a tag/bigint"),
+        }
     }
 }
 
-impl<'a> IntoCbor for &'a [u8] {
+impl IntoCbor for &[u8] {
     fn into_cbor(self) -> Result<Cbor> {
-        let n = err_at!(FailConvert, u64::try_from(self.len()))?;
+        let n = err_at!(OutOfRange, u64::try_from(self.len()))?;
         Ok(Cbor::Major2(n.into(), self.to_vec()))
     }
 }
@@ -249,7 +246,7 @@ where
     T: IntoCbor,
 {
     fn into_cbor(self) -> Result<Cbor> {
-        let n = err_at!(FailConvert, u64::try_from(self.len()))?;
+        let n = err_at!(OutOfRange, u64::try_from(self.len()))?;
         let mut arr = vec![];
         for item in self.into_iter() {
             arr.push(item.into_cbor()?)
@@ -266,26 +263,81 @@ where
         match val {
             Cbor::Major4(_, data) => {
                 let mut arr = vec![];
-                for item in data.into_iter() {
-                    arr.push(T::from_cbor(item)?)
+                for (i, item) in data.into_iter().enumerate() {
+                    arr.push(T::from_cbor(item).map_err(|err| err.at_index(i))?)
                 }
                 Ok(arr)
             }
-            _ => err_at!(FailConvert, msg: "not a vector"),
+            _ => err_at!(TypeMismatch, msg: "not a vector"),
         }
     }
 }
 
-impl<'a> IntoCbor for &'a str {
+impl<T, S> IntoCbor for HashSet<T, S>
+where
+    T: IntoCbor,
+{
+    /// Encoded as [Tag::Set] array, items are in iteration order. With
+    /// [EncodeOptions::deterministic][crate::EncodeOptions], items are sorted
+    /// by the bytewise order of their encoding.
+    fn into_cbor(self) -> Result<Cbor> {
+        let val = self.into_iter().collect::<Vec<T>>().into_cbor()?;
+        Ok(Tag::Set(Box::new(val)).into())
+    }
+}
+
+impl<T, S> FromCbor for HashSet<T, S>
+where
+    T: FromCbor + Eq + Hash,
+    S: BuildHasher + Default,
+{
+    /// Decode from [Tag::Set] or from an array, duplicate items are
+    /// collapsed. Use [DecodeOptions::strict][crate::DecodeOptions] to
+    /// reject them.
+    fn from_cbor(val: Cbor) -> Result<HashSet<T, S>> {
+        Ok(Vec::<T>::from_cbor(set_items(val)?)?.into_iter().collect())
+    }
+}
+
+impl<T> IntoCbor for BTreeSet<T>
+where
+    T: IntoCbor,
+{
+    /// Encoded as [Tag::Set] array, items are in sort order.
+    fn into_cbor(self) -> Result<Cbor> {
+        let val = self.into_iter().collect::<Vec<T>>().into_cbor()?;
+        Ok(Tag::Set(Box::new(val)).into())
+    }
+}
+
+impl<T> FromCbor for BTreeSet<T>
+where
+    T: FromCbor + Ord,
+{
+    /// Similar to `FromCbor` for `HashSet`.
+    fn from_cbor(val: Cbor) -> Result<BTreeSet<T>> {
+        Ok(Vec::<T>::from_cbor(set_items(val)?)?.into_iter().collect())
+    }
+}
+
+fn set_items(val: Cbor) -> Result<Cbor> {
+    match val {
+        Cbor::Major6(_, Tag::Set(val)) => Ok(*val),
+        val @ Cbor::Major4(_, _) => Ok(val),
+        _ => err_at!(TypeMismatch, msg: "not a set"),
+    }
+}
+
+impl IntoCbor for &str {
     fn into_cbor(self) -> Result<Cbor> {
-        let n = err_at!(FailConvert, u64::try_from(self.len()))?;
+        let n = err_at!(OutOfRange, u64::try_from(self.len()))?;
         Ok(Cbor::Major3(n.into(), self.as_bytes().to_vec()))
     }
 }
 
 impl IntoCbor for String {
     fn into_cbor(self) -> Result<Cbor> {
-        let n = err_at!(FailConvert, u64::try_from(self.len()))?;
+        let n = err_at!(OutOfRange, u64::try_from(self.len()))?;
         Ok(Cbor::Major3(n.into(), self.as_bytes().to_vec()))
     }
 }
@@ -295,17 +347,15 @@ impl FromCbor for String {
         use std::str::from_utf8;
 
         match val {
-            Cbor::Major3(_, val) => {
-                Ok(err_at!(FailConvert, from_utf8(&val))?.to_string())
-            }
-            _ => err_at!(FailConvert, msg: "not utf8-string"),
+            Cbor::Major3(_, val) => Ok(err_at!(Utf8, from_utf8(&val))?.to_string()),
+            _ => err_at!(TypeMismatch, msg: "not utf8-string"),
         }
     }
 }
 
 impl IntoCbor for ffi::OsString {
     fn into_cbor(self) -> Result<Cbor> {
-        let n = err_at!(FailConvert, u64::try_from(self.len()))?;
+        let n = err_at!(OutOfRange, u64::try_from(self.len()))?;
         Ok(Cbor::Major3(n.into(), self.into_vec()))
     }
 }
@@ -314,14 +364,14 @@ impl FromCbor for ffi::OsString {
     fn from_cbor(val: Cbor) -> Result<ffi::OsString> {
         match val {
             Cbor::Major3(_, val) => Ok(ffi::OsString::from_vec(val)),
-            _ => err_at!(FailConvert, msg: "not utf8-string"),
+            _ => err_at!(TypeMismatch, msg: "not utf8-string"),
         }
     }
 }
 
 impl IntoCbor for Vec<Cbor> {
     fn into_cbor(self) -> Result<Cbor> {
-        let n = err_at!(FailConvert, u64::try_from(self.len()))?;
+        let n = err_at!(OutOfRange, u64::try_from(self.len()))?;
         Ok(Cbor::Major4(n.into(), self))
     }
 }
@@ -330,14 +380,14 @@ impl FromCbor for Vec<Cbor> {
     fn from_cbor(val: Cbor) -> Result<Vec<Cbor>> {
         match val {
             Cbor::Major4(_, data) => Ok(data),
-            _ => err_at!(FailConvert, msg: "not a vector"),
+            _ => err_at!(TypeMismatch, msg: "not a vector"),
         }
     }
 }
 
 impl IntoCbor for Vec<(Key, Cbor)> {
     fn into_cbor(self) -> Result<Cbor> {
-        let n = err_at!(FailConvert, u64::try_from(self.len()))?;
+        let n = err_at!(OutOfRange, u64::try_from(self.len()))?;
         Ok(Cbor::Major5(n.into(), self))
     }
 }
@@ -346,7 +396,7 @@ impl FromCbor for Vec<(Key, Cbor)> {
     fn from_cbor(val: Cbor) -> Result<Vec<(Key, Cbor)>> {
         match val {
             Cbor::Major5(_, data) => Ok(data),
-            _ => err_at!(FailConvert, msg: "not a map"),
+            _ => err_at!(TypeMismatch, msg: "not a map"),
         }
     }
 }
@@ -367,6 +417,8 @@ impl<T> FromCbor for Option<T>
 where
     T: FromCbor + Sized,
 {
+    /// Null is None, logical path of errors from `T` is kept as is, refer
+    /// [Error::path].
     fn from_cbor(val: Cbor) -> Result<Option<T>> {
         match val {
             Cbor::Major7(_, SimpleValue::Null) => Ok(None),
@@ -380,24 +432,25 @@ impl IntoCbor for Key {
         let val = match self {
             Key::U64(key) => Cbor::Major0(key.into(), key),
             Key::N64(key) if key >= 0 => {
-                err_at!(FailConvert, msg: "Key::N64({}) cannot be positive", key)?
+                err_at!(InvalidData, msg: "Key::N64({}) cannot be positive", key)?
             }
             Key::N64(key) => {
-                let val = err_at!(FailConvert, u64::try_from(key.abs() - 1))?;
+                let val = err_at!(OutOfRange, u64::try_from(-1 - key))?;
                 Cbor::Major1(val.into(), val)
             }
             Key::Bytes(key) => {
-                let val = err_at!(FailConvert, key.len().try_into())?;
+                let val = err_at!(OutOfRange, key.len().try_into())?;
                 Cbor::Major2(val, key)
             }
             Key::Text(key) => {
-                let val = err_at!(FailConvert, key.len().try_into())?;
+                let val = err_at!(OutOfRange, key.len().try_into())?;
                 Cbor::Major3(val, key.into())
             }
             Key::Bool(true) => SimpleValue::True.into_cbor()?,
             Key::Bool(false) => SimpleValue::False.into_cbor()?,
             Key::F32(key) => SimpleValue::F32(key).into_cbor()?,
             Key::F64(key) => SimpleValue::F64(key).into_cbor()?,
+            Key::Cbor(key) => *key,
         };
 
         Ok(val)
@@ -410,20 +463,20 @@ impl FromCbor for Key {
 
         let key = match val {
             Cbor::Major0(_, key) => Key::U64(key),
-            Cbor::Major1(_, key) => {
-                let val = -err_at!(FailConvert, i64::try_from(key + 1))?;
-                Key::N64(val)
-            }
+            Cbor::Major1(info, key) => match i64::try_from(key) {
+                Ok(key) => Key::N64(-1 - key),
+                Err(_) => Key::Cbor(Box::new(Cbor::Major1(info, key))),
+            },
             Cbor::Major2(_, key) => Key::Bytes(key),
             Cbor::Major3(_, key) => {
-                let val = err_at!(FailConvert, from_utf8(&key))?.to_string();
+                let val = err_at!(Utf8, from_utf8(&key))?.to_string();
                 Key::Text(val)
             }
             Cbor::Major7(_, SimpleValue::True) => Key::Bool(true),
             Cbor::Major7(_, SimpleValue::False) => Key::Bool(false),
             Cbor::Major7(_, SimpleValue::F32(key)) => Key::F32(key),
             Cbor::Major7(_, SimpleValue::F64(key)) => Key::F64(key),
-            _ => err_at!(FailCbor, msg: "cbor not a valid key")?,
+            val => Key::Cbor(Box::new(val)),
         };
 
         Ok(key)
@@ -469,8 +522,16 @@ impl<T> FromCbor for Arc<T>
 where
     T: FromCbor,
 {
+    /// Each decoded value is a new Arc, values that were shared before
+    /// encoding, or referred by [Tag::SharedRef], are not shared after
+    /// decoding. A [Tag::Shareable] item, left unresolved by
+    /// [DecodeOptions::resolve_refs][crate::DecodeOptions], is decoded as its
+    /// item.
     fn from_cbor(val: Cbor) -> Result<Self> {
-        T::from_cbor(val).map(Arc::new)
+        match val {
+            Cbor::Major6(_, Tag::Shareable(item)) => T::from_cbor(*item).map(Arc::new),
+            val => T::from_cbor(val).map(Arc::new),
+        }
     }
 }
 
@@ -478,6 +539,9 @@ impl<T> IntoCbor for Arc<T>
 where
     T: IntoCbor + Clone,
 {
+    /// Encoded as its value. Encode with
+    /// [EncodeOptions::share_values][crate::EncodeOptions] to encode
+    /// repeated values only once.
     fn into_cbor(self) -> Result<Cbor> {
         match Arc::try_unwrap(self) {
             Ok(s) => s.into_cbor(),
@@ -488,3 +552,100 @@ where
         }
     }
 }
+
+impl IntoCbor for SystemTime {
+    /// Encoded as [Tag::Epoch] integer for whole seconds, otherwise as
+    /// [Tag::ExtTime] with nanoseconds, so that no precision is lost.
+    fn into_cbor(self) -> Result<Cbor> {
+        let (secs, nanos) = time::to_epoch(self)?;
+        match nanos {
+            0 => Ok(Tag::Epoch(Box::new(secs.into_cbor()?)).into()),
+            nanos => {
+                let map = vec![
+                    (Key::U64(1), secs.into_cbor()?),
+                    (Key::N64(-9), nanos.into_cbor()?),
+                ];
+                Ok(Tag::ExtTime(Box::new(map.into_cbor()?)).into())
+            }
+        }
+    }
+}
+
+impl FromCbor for SystemTime {
+    /// Decode from [Tag::DateTime], [Tag::Epoch] or [Tag::ExtTime].
+    fn from_cbor(val: Cbor) -> Result<SystemTime> {
+        match val {
+            Cbor::Major6(_, Tag::DateTime(val)) => {
+                time::from_rfc3339(&String::from_cbor(*val)?)
+            }
+            Cbor::Major6(_, Tag::Epoch(val)) => epoch_from_cbor(*val, 0),
+            Cbor::Major6(_, Tag::ExtTime(val)) => {
+                let (base, nanos) = ext_time_from_cbor(*val)?;
+                epoch_from_cbor(base, nanos)
+            }
+            _ => err_at!(TypeMismatch, msg: "not a time"),
+        }
+    }
+}
+
+impl IntoCbor for Duration {
+    /// Encoded as [Tag::Duration] with seconds, and nanoseconds if any.
+    fn into_cbor(self) -> Result<Cbor> {
+        let mut map = vec![(Key::U64(1), self.as_secs().into_cbor()?)];
+        if self.subsec_nanos() > 0 {
+            map.push((Key::N64(-9), self.subsec_nanos().into_cbor()?));
+        }
+        Ok(Tag::Duration(Box::new(map.into_cbor()?)).into())
+    }
+}
+
+impl FromCbor for Duration {
+    fn from_cbor(val: Cbor) -> Result<Duration> {
+        let (base, nanos) = match val {
+            Cbor::Major6(_, Tag::Duration(val)) => ext_time_from_cbor(*val)?,
+            _ => err_at!(TypeMismatch, msg: "not a duration")?,
+        };
+        match base {
+            Cbor::Major0(_, secs) => Ok(Duration::new(secs, nanos)),
+            Cbor::Major7(_, _) if nanos == 0 => {
+                let secs = f64::from_cbor(base)?;
+                err_at!(OutOfRange, Duration::try_from_secs_f64(secs))
+            }
+            _ => err_at!(InvalidData, msg: "invalid duration"),
+        }
+    }
+}
+
+// Seconds relative to UNIX epoch, integer or floating point, and nanoseconds.
+fn epoch_from_cbor(val: Cbor, nanos: u32) -> Result<SystemTime> {
+    match val {
+        Cbor::Major0(_, _) | Cbor::Major1(_, _) => {
+            time::from_epoch(i64::from_cbor(val)?, nanos)
+        }
+        Cbor::Major7(_, _) if nanos == 0 => time::from_epoch_f64(f64::from_cbor(val)?),
+        _ => err_at!(InvalidData, msg: "invalid epoch time"),
+    }
+}
+
+// Split RFC 9581 map into base time, from key 1, and fraction of second, in
+// nanoseconds, from key -3, -6 or -9. Other keys are ignored.
+fn ext_time_from_cbor(val: Cbor) -> Result<(Cbor, u32)> {
+    let (mut base, mut nanos) = (None, 0_u32);
+    for (key, val) in Vec::<(Key, Cbor)>::from_cbor(val)?.into_iter() {
+        match key {
+            Key::U64(1) => base = Some(val),
+            Key::N64(exp) if exp == -3 || exp == -6 || exp == -9 => {
+                let scale = 10_u64.pow((9 + exp) as u32);
+                match u64::from_cbor(val)?.checked_mul(scale) {
+                    Some(n) if n < 1_000_000_000 => nanos = n as u32,
+                    _ => err_at!(InvalidData, msg: "invalid fraction for key {}", exp)?,
+                }
+            }
+            _ => (),
+        }
+    }
+    match base {
+        Some(base) => Ok((base, nanos)),
+        None => err_at!(InvalidData, msg: "missing key 1 for time"),
+    }
+}
@@ -0,0 +1,178 @@
+use std::convert::TryFrom;
+
+use crate::{Error, Result, RECURSION_LIMIT};
+
+// Similar to err_at!, with the input byte offset `off`, refer Error::offset.
+macro_rules! err_off {
+    ($v:ident, $off:expr, $($arg:expr),+) => {
+        err_at!($v, msg: $($arg),+).map_err(|err: Error| err.with_offset($off))
+    };
+}
+
+/// Summary of a well-formed data item, returned by [validate].
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct ValidationReport {
+    /// Number of bytes in the data item.
+    pub n_bytes: usize,
+    /// Number of data items, including nested items and map keys.
+    pub n_items: usize,
+    /// Maximum depth of nested items, within arrays, maps and tags.
+    /// Top-level item is at depth 1.
+    pub depth: u32,
+}
+
+/// Check that `buf` contains exactly one well-formed data item as per
+/// RFC-8949, without decoding it into [Cbor][crate::Cbor] tree.
+///
+/// Checks include, reserved additional info, indefinite length for
+/// major types that don't allow them, chunks of indefinite length strings,
+/// UTF-8 encoding of text strings, misplaced break, truncated input, nesting
+/// depth beyond [RECURSION_LIMIT] and trailing bytes. On failure, error carries
+/// the byte offset within `buf`, refer [Error::offset].
+pub fn validate(buf: &[u8]) -> Result<ValidationReport> {
+    do_validate(buf).map_err(Error::with_backtrace)
+}
+
+fn do_validate(buf: &[u8]) -> Result<ValidationReport> {
+    let mut v = Validator { buf, off: 0, report: ValidationReport::default() };
+    v.item(1)?;
+    if v.off < buf.len() {
+        err_off!(InvalidData, v.off, "trailing {} bytes", buf.len() - v.off)?
+    }
+    v.report.n_bytes = v.off;
+    Ok(v.report)
+}
+
+struct Validator<'a> {
+    buf: &'a [u8],
+    off: usize,
+    report: ValidationReport,
+}
+
+impl<'a> Validator<'a> {
+    // validate one data item, break is not an item.
+    fn item(&mut self, depth: u32) -> Result<()> {
+        let start = self.off;
+        let (major, ai) = self.read_hdr()?;
+        match (major, ai) {
+            (7, 31) => err_off!(InvalidHeader, start, "unexpected break"),
+            _ => self.item_body(start, major, ai, depth),
+        }
+    }
+
+    fn item_body(&mut self, start: usize, major: u8, ai: u8, depth: u32) -> Result<()> {
+        use std::str::from_utf8;
+
+        if depth > RECURSION_LIMIT {
+            err_off!(LimitExceeded, start, "recursion limit exceeded")?
+        }
+        self.report.n_items += 1;
+        self.report.depth = std::cmp::max(self.report.depth, depth);
+
+        match (major, self.read_arg(start, ai)?) {
+            (0, Some(_)) | (1, Some(_)) => (),
+            (0, None) | (1, None) | (6, None) => {
+                err_off!(InvalidHeader, start, "indefinite major {}", major)?
+            }
+            (2, None) | (3, None) => loop {
+                let off = self.off;
+                match self.read_hdr()? {
+                    (7, 31) => break,
+                    (m, 31) if m == major => {
+                        err_off!(InvalidHeader, off, "nested indefinite chunk")?
+                    }
+                    (m, ai) if m == major => {
+                        let len = self.read_arg(off, ai)?.unwrap_or(0);
+                        let chunk = self.read_slice(off, len)?;
+                        if major == 3 && from_utf8(chunk).is_err() {
+                            err_off!(Utf8, off, "invalid utf8")?
+                        }
+                    }
+                    (m, _) => {
+                        err_off!(InvalidHeader, off, "chunk major {} in {}", m, major)?
+                    }
+                }
+            },
+            (2, Some(len)) => {
+                self.read_slice(start, len)?;
+            }
+            (3, Some(len)) => {
+                if from_utf8(self.read_slice(start, len)?).is_err() {
+                    err_off!(Utf8, start, "invalid utf8")?
+                }
+            }
+            (4, None) | (5, None) => {
+                for i in 0.. {
+                    let off = self.off;
+                    match self.read_hdr()? {
+                        (7, 31) if major == 4 || i % 2 == 0 => break,
+                        (7, 31) => err_off!(InvalidHeader, off, "break after key")?,
+                        (m, ai) => self.item_body(off, m, ai, depth + 1)?,
+                    }
+                }
+            }
+            (4, Some(len)) => {
+                for _ in 0..len {
+                    self.item(depth + 1)?;
+                }
+            }
+            (5, Some(len)) => {
+                for _ in 0..len {
+                    self.item(depth + 1)?;
+                    self.item(depth + 1)?;
+                }
+            }
+            (6, Some(_)) => self.item(depth + 1)?,
+            (7, Some(val)) if ai == 24 && val < 32 => {
+                err_off!(InvalidHeader, start, "invalid simple value {}", val)?
+            }
+            (7, _) => (),
+            _ => unreachable!(),
+        }
+
+        Ok(())
+    }
+
+    fn read_hdr(&mut self) -> Result<(u8, u8)> {
+        match self.buf.get(self.off) {
+            Some(b) => {
+                self.off += 1;
+                Ok(((b & 0xe0) >> 5, b & 0x1f))
+            }
+            None => err_off!(UnexpectedEof, self.off, "unexpected eof"),
+        }
+    }
+
+    // read the argument for additional info `ai`, None for indefinite.
+    fn read_arg(&mut self, start: usize, ai: u8) -> Result<Option<u64>> {
+        let n = match ai {
+            0..=23 => return Ok(Some(ai as u64)),
+            24 => 1,
+            25 => 2,
+            26 => 4,
+            27 => 8,
+            28..=30 => err_off!(InvalidHeader, start, "reserved info {}", ai)?,
+            _ => return Ok(None),
+        };
+        let mut scratch = [0_u8; 8];
+        scratch[(8 - n)..].copy_from_slice(self.read_slice(start, n as u64)?);
+        Ok(Some(u64::from_be_bytes(scratch)))
+    }
+
+    fn read_slice(&mut self, start: usize, len: u64) -> Result<&'a [u8]> {
+        let avail = self.buf.len() - self.off;
+        match usize::try_from(len) {
+            Ok(len) if len <= avail => {
+                self.off += len;
+                Ok(&self.buf[(self.off - len)..self.off])
+            }
+            _ => {
+                err_off!(UnexpectedEof, start, "need {} bytes, have {}", len, avail)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+#[path = "validate_test.rs"]
+mod validate_test;
@@ -0,0 +1,77 @@
+use arbitrary::Unstructured;
+use rand::{prelude::random, rngs::StdRng, Rng, SeedableRng};
+
+use super::*;
+use crate::Cbor;
+
+#[test]
+fn test_validate() {
+    let seed: u128 = random();
+    println!("test_validate seed:{}", seed);
+    let mut rng = {
+        let mut rng_seed = [0; 32];
+        rng_seed[0..16].copy_from_slice(&seed.to_le_bytes());
+        StdRng::from_seed(rng_seed)
+    };
+
+    for _i in 0..1000 {
+        let val: Cbor = {
+            let bytes: Vec<u8> =
+                (0..100).flat_map(|_| rng.gen::<[u8; 32]>().to_vec()).collect();
+            let mut uns = Unstructured::new(&bytes);
+            uns.arbitrary().unwrap()
+        };
+
+        let mut buf: Vec<u8> = vec![];
+        let n = val.encode(&mut buf).unwrap();
+        let report = validate(&buf).unwrap();
+        assert_eq!(report.n_bytes, n);
+        assert!(report.n_items > 0 && report.depth > 0);
+
+        if n > 1 {
+            assert!(validate(&buf[..n - 1]).is_err());
+        }
+        buf.push(0);
+        assert!(validate(&buf).is_err());
+    }
+}
+
+#[test]
+fn test_validate_errors() {
+    // {_ "a": [_ 1, 2], "b": (_ h'01' h'02')}
+    let buf = [
+        0xbf, 0x61, 0x61, 0x9f, 0x01, 0x02, 0xff, 0x61, 0x62, 0x5f, 0x41, 0x01, 0x41,
+        0x02, 0xff, 0xff,
+    ];
+    let report = validate(&buf).unwrap();
+    assert_eq!(report, ValidationReport { n_bytes: 16, n_items: 7, depth: 3 });
+
+    let testcases: Vec<(Vec<u8>, &str)> = vec![
+        (vec![0x1c], "reserved info 28 at offset 0"),
+        (vec![0x82, 0x01, 0x3f], "indefinite major 1 at offset 2"),
+        (vec![0x81, 0xff], "unexpected break at offset 1"),
+        (vec![0x62, 0xc3, 0x28], "invalid utf8 at offset 0"),
+        (
+            vec![0x7f, 0x61, 0x61, 0x42, 0x01, 0x02, 0xff],
+            "chunk major 2 in 3 at offset 3",
+        ),
+        (vec![0x5f, 0x5f, 0xff, 0xff], "nested indefinite chunk at offset 1"),
+        (vec![0xbf, 0x01, 0xff], "break after key at offset 2"),
+        (vec![0xf8, 0x10], "invalid simple value 16 at offset 0"),
+        (vec![0x5a, 0xff, 0xff, 0xff, 0xff], "need 4294967295 bytes, have 0 at offset 0"),
+        (vec![0x01, 0x02], "trailing 1 bytes at offset 1"),
+    ];
+    for (buf, msg) in testcases.into_iter() {
+        let err = validate(&buf).unwrap_err().to_string();
+        assert!(err.contains(msg), "{:?} {}", buf, err);
+    }
+
+    // run with main thread's stack size, test threads are smaller.
+    let handle = std::thread::Builder::new().stack_size(8 * 1024 * 1024);
+    let handle = handle.spawn(|| {
+        let mut buf = vec![0x81; (RECURSION_LIMIT as usize) + 1];
+        buf.push(0x01);
+        assert!(validate(&buf).unwrap_err().to_string().contains("recursion limit"));
+    });
+    handle.unwrap().join().unwrap();
+}